@@ -0,0 +1,124 @@
+//! Compares the broad phase algorithms available to `Physics`
+//! in a scene resembling a level: lots of static blocks and some dynamic bodies,
+//! as well as the cost of the same scene once everything has fallen asleep.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use starframe::{
+    event::EventSink,
+    graph::{Graph, Layer},
+    math as m,
+    physics::{self as phys, BroadPhase, Physics, SleepSettings},
+};
+
+struct World {
+    graph: Graph,
+    l_pose: Layer<m::Pose>,
+    l_body: Layer<phys::Body>,
+    l_collider: Layer<phys::Collider>,
+    l_evt_sink: Layer<EventSink>,
+}
+
+impl World {
+    fn new(static_blocks: usize, dynamic_bodies: usize) -> Self {
+        let mut graph = Graph::new();
+        let mut l_pose = graph.create_layer();
+        let mut l_body = graph.create_layer();
+        let mut l_collider = graph.create_layer();
+        let l_evt_sink = graph.create_layer();
+
+        // a floor of blocks
+        for i in 0..static_blocks {
+            let pose = l_pose.insert(
+                m::Pose::new(m::Vec2::new(i as f64, 0.0), m::Rotor2::identity()),
+                &mut graph,
+            );
+            let coll = l_collider.insert(phys::Collider::new_square(1.0), &mut graph);
+            graph.connect(&pose, &coll);
+        }
+        // boxes falling on it
+        for i in 0..dynamic_bodies {
+            let x = (i * static_blocks) as f64 / dynamic_bodies as f64;
+            let y = 1.0 + (i % 4) as f64;
+            let coll = phys::Collider::new_rect(0.6, 0.4);
+            let pose = l_pose.insert(
+                m::Pose::new(m::Vec2::new(x, y), m::Rotor2::identity()),
+                &mut graph,
+            );
+            let body = l_body.insert(phys::Body::new_dynamic(&coll, 1.0), &mut graph);
+            let coll = l_collider.insert(coll, &mut graph);
+            graph.connect(&pose, &body);
+            graph.connect(&pose, &coll);
+            graph.connect(&body, &coll);
+        }
+
+        World {
+            graph,
+            l_pose,
+            l_body,
+            l_collider,
+            l_evt_sink,
+        }
+    }
+
+    fn tick(&mut self, physics: &mut Physics) {
+        physics.tick(
+            &self.graph,
+            &mut self.l_pose,
+            &mut self.l_body,
+            &self.l_collider,
+            &mut self.l_evt_sink,
+            1.0 / 60.0,
+            &phys::forcefield::Gravity(m::Vec2::new(0.0, -9.81)),
+        );
+    }
+}
+
+fn broad_phase(c: &mut Criterion) {
+    let mut group = c.benchmark_group("physics tick");
+    for &static_blocks in &[50, 200, 500] {
+        for &method in &[BroadPhase::BruteForce, BroadPhase::SweepAndPrune] {
+            group.bench_with_input(
+                BenchmarkId::new(format!("{:?}", method), static_blocks),
+                &static_blocks,
+                |b, &static_blocks| {
+                    let mut world = World::new(static_blocks, static_blocks / 5);
+                    // keep bodies awake so that we're measuring the same work every frame
+                    let mut physics = Physics::with_substeps(10)
+                        .with_broad_phase(method)
+                        .with_sleep(SleepSettings {
+                            enabled: false,
+                            ..Default::default()
+                        });
+                    b.iter(|| world.tick(&mut physics));
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+fn resting(c: &mut Criterion) {
+    let mut group = c.benchmark_group("resting bodies");
+    for &sleep_enabled in &[false, true] {
+        group.bench_with_input(
+            BenchmarkId::new("sleep enabled", sleep_enabled),
+            &sleep_enabled,
+            |b, &enabled| {
+                let mut world = World::new(200, 200);
+                let mut physics = Physics::with_substeps(10).with_sleep(SleepSettings {
+                    enabled,
+                    ..Default::default()
+                });
+                // let everything settle first
+                for _ in 0..600 {
+                    world.tick(&mut physics);
+                }
+                b.iter(|| world.tick(&mut physics));
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, broad_phase, resting);
+criterion_main!(benches);
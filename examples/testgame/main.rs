@@ -67,7 +67,12 @@ impl State {
             player: player::PlayerController::new(),
             mouse_mode: MouseMode::Grab,
             mouse_grabber: MouseGrabber::new(),
-            physics: phys::Physics::with_substeps(10),
+            physics: {
+                // make ropes and chains snap at one point at a time
+                let mut physics = phys::Physics::with_substeps(10);
+                physics.one_break_per_island = true;
+                physics
+            },
             camera: gx::camera::MouseDragCamera::new(
                 gx::camera::ScalingStrategy::ConstantDisplayArea {
                     width: 20.0,
@@ -80,6 +85,7 @@ impl State {
 
     fn reset(&mut self) {
         self.physics.clear_constraints();
+        self.physics.clear_particle_fluids();
         self.graph = MyGraph::new();
     }
 
@@ -162,6 +168,9 @@ pub struct MyGraph {
     l_collider: graph::Layer<phys::Collider>,
     l_body: graph::Layer<phys::Body>,
     l_shape: graph::Layer<gx::Shape>,
+    l_rope: graph::Layer<phys::Rope>,
+    l_soft_body: graph::Layer<phys::SoftBody>,
+    l_water: graph::Layer<recipes::ParticleWater>,
     l_player: graph::Layer<player::Player>,
     evt_graph: sf::event::EventGraph<MyGraph>,
 }
@@ -172,6 +181,9 @@ impl MyGraph {
         let l_collider = graph.create_layer();
         let l_body = graph.create_layer();
         let l_shape = graph.create_layer();
+        let l_rope = graph.create_layer();
+        let l_soft_body = graph.create_layer();
+        let l_water = graph.create_layer();
         let l_player = graph.create_layer();
         let evt_graph = sf::event::EventGraph::new(&mut graph);
         MyGraph {
@@ -180,6 +192,9 @@ impl MyGraph {
             l_collider,
             l_body,
             l_shape,
+            l_rope,
+            l_soft_body,
+            l_water,
             l_player,
             evt_graph,
         }
@@ -305,6 +320,7 @@ impl game::GameState for State {
                 radius: distr::Uniform::from(0.1..0.4).sample(&mut rng),
                 restitution: 1.0,
                 start_velocity: random_vel(),
+                bullet: true,
             })
             .spawn(&mut self.graph, &mut self.physics);
         }
@@ -344,11 +360,20 @@ impl game::GameState for State {
                 }
                 {
                     microprofile::scope!("update", "player");
-                    self.player.tick(&mut self.graph, &game.input);
+                    self.player.tick(
+                        &mut self.graph,
+                        &self.physics,
+                        dt,
+                        self.scene.gravity.into(),
+                        &game.input,
+                    );
                 }
 
                 self.graph.evt_graph.flush(&self.graph.graph)(&mut self.graph);
 
+                recipes::update_deformable_shapes(&mut self.graph, &self.physics);
+                recipes::update_particle_water(&mut self.graph, &mut self.physics, dt);
+
                 Some(())
             }
             //
@@ -4,6 +4,9 @@ use sf::{
 };
 use starframe as sf;
 
+/// How hard a grabbed object can be pulled before the mouse lets go of it.
+const MAX_GRAB_FORCE: f64 = 200.0;
+
 #[derive(Clone, Copy, Debug)]
 pub struct MouseGrabber {
     constraint: Option<ConstraintHandle>,
@@ -27,6 +30,7 @@ impl MouseGrabber {
                 camera.point_screen_to_world(viewport_size, input.cursor_position().into());
             match self.constraint {
                 Some(handle) => {
+                    // if the grab broke, the handle is dead until the button is released
                     if let Some(constr) = physics.get_constraint_mut(handle) {
                         constr.offsets[1] = target_point;
                     }
@@ -46,6 +50,7 @@ impl MouseGrabber {
                                 .with_compliance(0.01)
                                 .with_linear_damping(10.0)
                                 .with_angular_damping(0.5)
+                                .with_break_force(MAX_GRAB_FORCE)
                                 .build_attachment();
                         self.constraint = Some(physics.add_constraint(constr));
                     }
@@ -8,11 +8,13 @@ use starframe::{
 #[derive(Clone, Copy, Debug)]
 pub struct Player {
     facing: Facing,
+    controller: phys::CharacterController,
 }
 impl Player {
     fn new() -> Self {
         Player {
             facing: Facing::Left,
+            controller: phys::CharacterController::new(),
         }
     }
 }
@@ -30,6 +32,9 @@ impl Facing {
     }
 }
 
+const PLAYER_WIDTH: f64 = 0.2;
+const PLAYER_HEIGHT: f64 = 0.4;
+
 #[derive(Clone, Copy, Debug, Default, serde::Deserialize)]
 #[serde(default)]
 pub struct PlayerRecipe {
@@ -38,20 +43,17 @@ pub struct PlayerRecipe {
 
 impl PlayerRecipe {
     pub fn spawn(&self, graph: &mut MyGraph) {
-        const WIDTH: f64 = 0.2;
-        const HEIGHT: f64 = 0.4;
-
         let pose_node = graph.l_pose.insert(self.pose.into(), &mut graph.graph);
         let shape_node = graph.l_shape.insert(
             gx::Shape::Rect {
-                w: WIDTH,
-                h: HEIGHT,
+                w: PLAYER_WIDTH,
+                h: PLAYER_HEIGHT,
                 color: [0.2, 0.8, 0.6, 1.0],
             },
             &mut graph.graph,
         );
-        let coll = phys::Collider::new_rect(WIDTH, HEIGHT);
-        let body = phys::Body::new_dynamic(&coll, 3.0);
+        let coll = phys::Collider::new_rect(PLAYER_WIDTH, PLAYER_HEIGHT);
+        let body = phys::Body::new_kinematic();
         let coll_node = graph.l_collider.insert(coll, &mut graph.graph);
         let body_node = graph.l_body.insert(body, &mut graph.graph);
         let tag_node = graph.l_player.insert(Player::new(), &mut graph.graph);
@@ -77,46 +79,67 @@ impl PlayerController {
         }
     }
 
-    pub fn tick(&mut self, g: &mut MyGraph, input: &sf::InputCache) {
+    pub fn tick(
+        &mut self,
+        g: &mut MyGraph,
+        physics: &phys::Physics,
+        dt: f64,
+        gravity: m::Vec2,
+        input: &sf::InputCache,
+    ) {
         let (target_facing, target_hdir) = match input.get_key_axis_state(Key::Right, Key::Left) {
             KeyAxisState::Zero => (None, 0.0),
             KeyAxisState::Pos => (Some(Facing::Right), 1.0),
             KeyAxisState::Neg => (Some(Facing::Left), -1.0),
         };
 
-        let mut bullet_queue: Vec<(m::Pose, phys::Velocity)> = Vec::new();
+        let mut bullet_queue: Vec<(m::Pose, phys::Velocity, sf::graph::Node<phys::Body>)> =
+            Vec::new();
         for mut player in g.l_player.iter_mut(&g.graph) {
-            let mut player_body = g.graph.get_neighbor_mut(&player, &mut g.l_body).unwrap();
-            let mut player_tr = g.graph.get_neighbor_mut(&player, &mut g.l_pose).unwrap();
-
-            // move and orient
-
-            if let Some(facing) = target_facing {
-                player.facing = facing;
-            }
+            let player_body = g.graph.get_neighbor(&player, &g.l_body).unwrap();
+            let player_body = sf::graph::NodeRef::as_node(&player_body, &g.graph);
+            let controller = &mut player.controller;
 
-            let move_speed = self.base_move_speed;
+            // move
 
-            let target_hvel = target_hdir * move_speed;
-            let accel_needed = target_hvel - player_body.velocity.linear.x;
+            let target_hvel = target_hdir * self.base_move_speed;
+            let accel_needed = target_hvel - controller.velocity.x;
             let accel = accel_needed.min(self.max_acceleration);
-            player_body.velocity.linear.x += accel;
+            controller.velocity.x += accel;
+            controller.velocity += gravity * dt;
 
-            // hacked up rotation locking
+            // jump, or drop down from a one-way platform if holding down
 
-            player_body.velocity.angular = 0.0;
-            player_tr.rotation = m::Rotor2::identity();
+            if input.is_key_pressed(Key::LShift, Some(0)) {
+                if controller.is_grounded() && input.is_key_pressed(Key::Down, None) {
+                    controller.drop_through_one_way();
+                } else if controller.can_jump() {
+                    // TODO: double jump, custom curve
+                    controller.jump();
+                    controller.velocity.y = 4.0;
+                }
+            }
 
-            // jump
+            controller.move_and_slide(
+                physics,
+                phys::CharacterContext {
+                    graph: &g.graph,
+                    l_pose: &mut g.l_pose,
+                    l_body: &mut g.l_body,
+                    l_collider: &g.l_collider,
+                },
+                player_body,
+                dt,
+            );
 
-            if input.is_key_pressed(Key::LShift, Some(0)) {
-                // TODO: only on ground, double jump, custom curve
-                player_body.velocity.linear.y = 4.0;
+            if let Some(facing) = target_facing {
+                player.facing = facing;
             }
 
             // shoot
 
             if input.is_key_pressed(Key::Z, Some(0)) {
+                let player_tr = g.graph.get_neighbor(&player, &g.l_pose).unwrap();
                 bullet_queue.push((
                     m::PoseBuilder::new()
                         .with_position(
@@ -128,16 +151,22 @@ impl PlayerController {
                         angular: 0.0,
                         linear: player.facing.orient_vec(m::Vec2::new(20.0, 0.1)),
                     },
+                    player_body,
                 ));
             }
         }
 
-        for (bullet_tr, bullet_vel) in bullet_queue {
-            Self::spawn_bullet(bullet_tr, bullet_vel, g)
+        for (bullet_tr, bullet_vel, shooter) in bullet_queue {
+            Self::spawn_bullet(bullet_tr, bullet_vel, shooter, g)
         }
     }
 
-    fn spawn_bullet(tr: m::Pose, vel: phys::Velocity, g: &mut MyGraph) {
+    fn spawn_bullet(
+        tr: m::Pose,
+        vel: phys::Velocity,
+        shooter: sf::graph::Node<phys::Body>,
+        g: &mut MyGraph,
+    ) {
         const R: f64 = 0.05;
         let pose_node = g.l_pose.insert(tr, &mut g.graph);
         let shape_node = g.l_shape.insert(
@@ -148,14 +177,15 @@ impl PlayerController {
             },
             &mut g.graph,
         );
-        let coll = phys::Collider::new_circle(R);
+        // don't hit the one who shot it
+        let coll = phys::Collider::new_circle(R).with_excluded_body(shooter);
         let body = phys::Body::new_dynamic_const_mass(&coll, 1.0).with_velocity(vel);
         let coll_node = g.l_collider.insert(coll, &mut g.graph);
         let body_node = g.l_body.insert(body, &mut g.graph);
 
         let evt_sink_node = g.evt_graph.add_sink(
             |g: &mut MyGraph, node, evt| match evt {
-                sf::Event::Contact(_) => {
+                sf::Event::ContactBegan(_) => {
                     if let Some(checked) = node.check(&g.graph) {
                         g.graph.delete(checked);
                     }
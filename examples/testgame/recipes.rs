@@ -16,12 +16,85 @@ pub enum Recipe {
         radius: f64,
         pose: m::PoseBuilder,
     },
+    LBlock {
+        size: f64,
+        pose: m::PoseBuilder,
+    },
     Blockchain {
         width: f64,
         spacing: f64,
         links: Vec<[f64; 2]>,
         anchored_start: bool,
         anchored_end: bool,
+        /// Maximum angle in degrees each link can bend relative to the previous one.
+        #[serde(default)]
+        max_bend: Option<f64>,
+    },
+    /// A rope made of particles, optionally pinned in place at either end.
+    Rope {
+        start: [f64; 2],
+        end: [f64; 2],
+        segments: usize,
+        anchored_start: bool,
+        anchored_end: bool,
+        /// Makes the rope resist bending if set.
+        #[serde(default)]
+        bending_compliance: Option<f64>,
+        /// Makes the rope snap if pulled harder than this.
+        #[serde(default)]
+        break_force: Option<f64>,
+    },
+    /// A squishy ball made of a ring of particles.
+    SoftBall {
+        position: [f64; 2],
+        radius: f64,
+        particles: usize,
+        /// How much the ball can be squashed, zero for not at all.
+        #[serde(default)]
+        area_compliance: f64,
+    },
+    /// A wobbly rectangle made of a grid of particles.
+    Jelly {
+        position: [f64; 2],
+        width: f64,
+        height: f64,
+        columns: usize,
+        rows: usize,
+    },
+    /// A platform that can only move up and down between its starting height and `travel` above.
+    Elevator {
+        position: [f64; 2],
+        width: f64,
+        travel: f64,
+    },
+    /// A row of motorized wheels carrying things along at `speed`.
+    Conveyor {
+        position: [f64; 2],
+        wheels: usize,
+        radius: f64,
+        speed: f64,
+    },
+    /// A thin static platform that can be jumped through from below and stood on.
+    OneWayPlatform {
+        width: f64,
+        pose: m::PoseBuilder,
+    },
+    /// A rectangular pool of water that things float in.
+    Water {
+        position: [f64; 2],
+        width: f64,
+        height: f64,
+        density: f64,
+    },
+    /// Water simulated as particles, starting out as rectangular blocks
+    /// and poured in from taps.
+    ParticleWater {
+        particle_radius: f64,
+        /// Bottom left and top right corners of the blocks.
+        #[serde(default)]
+        blocks: Vec<[[f64; 2]; 2]>,
+        #[serde(default)]
+        taps: Vec<Tap>,
     },
     Oscillator {
         position: [f64; 2],
@@ -31,6 +104,19 @@ pub enum Recipe {
     },
 }
 
+#[derive(Clone, Copy, Debug, serde::Deserialize)]
+pub struct Tap {
+    pub position: [f64; 2],
+    pub velocity: [f64; 2],
+    pub width: f64,
+}
+
+/// A particle fluid in the physics system along with the taps pouring more of it.
+pub struct ParticleWater {
+    pub fluid: phys::ParticleFluidHandle,
+    pub taps: Vec<phys::ParticleEmitter>,
+}
+
 #[derive(Clone, Copy, Debug, serde::Deserialize)]
 #[serde(default)]
 pub struct Ball {
@@ -38,6 +124,8 @@ pub struct Ball {
     pub position: [f64; 2],
     pub restitution: f64,
     pub start_velocity: [f64; 2],
+    /// Use continuous collision detection so the ball can't pass through thin things.
+    pub bullet: bool,
 }
 
 impl Default for Ball {
@@ -47,6 +135,7 @@ impl Default for Ball {
             position: [0.0, 0.0],
             restitution: 0.0,
             start_velocity: [0.0, 0.0],
+            bullet: false,
         }
     }
 }
@@ -123,6 +212,29 @@ fn spawn_body(
     sf::graph::NodeRef::as_node(&body_node, &g.graph)
 }
 
+fn spawn_soft_body(
+    builder: phys::SoftBodyBuilder,
+    g: &mut crate::MyGraph,
+    physics: &mut phys::Physics,
+) {
+    let soft_body = builder.build(
+        physics,
+        &mut g.graph,
+        &mut g.l_pose,
+        &mut g.l_body,
+        &mut g.l_collider,
+    );
+    // like ropes, soft bodies are drawn in world space and updated every frame
+    let shape_node = g.l_shape.insert(
+        gx::Shape::from_soft_body(&soft_body, &g.graph, &g.l_pose, &g.l_body, random_color()),
+        &mut g.graph,
+    );
+    let pose_node = g.l_pose.insert(m::Pose::default(), &mut g.graph);
+    let soft_node = g.l_soft_body.insert(soft_body, &mut g.graph);
+    g.graph.connect(&pose_node, &shape_node);
+    g.graph.connect(&soft_node, &shape_node);
+}
+
 impl Recipe {
     pub fn spawn(&self, graph: &mut crate::MyGraph, physics: &mut phys::Physics) {
         match self {
@@ -138,6 +250,7 @@ impl Recipe {
                 position,
                 restitution,
                 start_velocity,
+                bullet,
             }) => {
                 let pose_node = graph.l_pose.insert(
                     m::Pose::new(position.into(), m::Rotor2::identity()),
@@ -147,10 +260,13 @@ impl Recipe {
                     restitution_coef: *restitution,
                     ..Default::default()
                 });
-                let body = phys::Body::new_dynamic(&coll, 0.5).with_velocity(Velocity {
+                let mut body = phys::Body::new_dynamic(&coll, 0.5).with_velocity(Velocity {
                     linear: start_velocity.into(),
                     angular: 0.0,
                 });
+                if *bullet {
+                    body = body.as_bullet();
+                }
                 let coll_node = graph.l_collider.insert(coll, &mut graph.graph);
                 let body_node = graph.l_body.insert(body, &mut graph.graph);
                 let shape_node = graph.l_shape.insert(
@@ -178,12 +294,63 @@ impl Recipe {
                     graph,
                 );
             }
+            Recipe::LBlock { size, pose } => {
+                // two arms a third of the size thick,
+                // positioned so that the center of mass is at the origin
+                let thickness = size / 3.0;
+                let vert_center = m::Vec2::new(thickness / 2.0, size / 2.0);
+                let vert_area = thickness * size;
+                let horiz_center = m::Vec2::new((size + thickness) / 2.0, thickness / 2.0);
+                let horiz_area = (size - thickness) * thickness;
+                let com = (vert_area * vert_center + horiz_area * horiz_center)
+                    / (vert_area + horiz_area);
+                let colls = [
+                    phys::Collider::new_rect(thickness, *size)
+                        .with_offset(m::Pose::new(vert_center - com, m::Rotor2::identity())),
+                    phys::Collider::new_rect(size - thickness, thickness)
+                        .with_offset(m::Pose::new(horiz_center - com, m::Rotor2::identity())),
+                ];
+                // outline starts from the inner corner so that it triangulates correctly
+                let outline = [
+                    (thickness, thickness),
+                    (thickness, *size),
+                    (0.0, *size),
+                    (0.0, 0.0),
+                    (*size, 0.0),
+                    (*size, thickness),
+                ]
+                .iter()
+                .map(|(x, y)| m::Vec2::new(*x, *y) - com)
+                .collect();
+
+                let pose_node = graph.l_pose.insert((*pose).into(), &mut graph.graph);
+                let shape_node = graph.l_shape.insert(
+                    gx::Shape::Poly {
+                        points: outline,
+                        color: random_color(),
+                    },
+                    &mut graph.graph,
+                );
+                let body_node = graph.l_body.insert(
+                    phys::Body::new_dynamic_compound(&colls, 0.5),
+                    &mut graph.graph,
+                );
+                graph.graph.connect(&pose_node, &shape_node);
+                graph.graph.connect(&pose_node, &body_node);
+                phys::insert_compound_colliders(
+                    &mut graph.graph,
+                    &mut graph.l_collider,
+                    &body_node,
+                    &colls,
+                );
+            }
             Recipe::Blockchain {
                 width,
                 spacing,
                 links,
                 anchored_start,
                 anchored_end,
+                max_bend,
             } => {
                 if links.len() < 2 {
                     println!("Too few links in a chain");
@@ -196,7 +363,7 @@ impl Recipe {
                 let mut links_iter = links.iter().map(|p| m::Vec2::new(p[0], p[1])).peekable();
 
                 // to connect another block to it
-                let mut prev_block: Option<(sf::graph::Node<phys::Body>, f64)> = None;
+                let mut prev_block: Option<(sf::graph::Node<phys::Body>, f64, f64)> = None;
                 while let (Some(link1), Some(link2)) = (links_iter.next(), links_iter.peek()) {
                     let distance = *link2 - link1;
                     let dist_norm = distance.mag();
@@ -215,14 +382,19 @@ impl Recipe {
                     );
                     let caps_length_half = caps_full_length / 2.0;
                     if let Some((prev_block, prev_block_offset)) = prev_block {
-                        physics.add_constraint(
-                            phys::ConstraintBuilder::new(capsule)
-                                .with_target(prev_block)
-                                .with_origin(m::Vec2::new(-caps_length_half - half_spacing, 0.0))
-                                .with_target_origin(m::Vec2::new(prev_block_offset, 0.0))
-                                .with_compliance(0.015)
-                                .build_attachment(),
-                        );
+                        let mut joint = phys::ConstraintBuilder::new(capsule)
+                            .with_target(prev_block)
+                            .with_origin(m::Vec2::new(-caps_length_half - half_spacing, 0.0))
+                            .with_target_origin(m::Vec2::new(prev_block_offset, 0.0))
+                            .with_compliance(0.015)
+                            .disable_collision();
+                        if let Some(max_bend) = max_bend {
+                            joint = joint.with_angle_limits(
+                                m::Angle::Deg(-max_bend),
+                                m::Angle::Deg(*max_bend),
+                            );
+                        }
+                        physics.add_constraint(joint.build_revolute());
                     } else if *anchored_start {
                         physics.add_constraint(
                             phys::ConstraintBuilder::new(capsule)
@@ -250,6 +422,200 @@ impl Recipe {
                     );
                 }
             }
+            Recipe::Elevator {
+                position,
+                width,
+                travel,
+            } => {
+                let position: m::Vec2 = position.into();
+                let platform = spawn_block(
+                    Block {
+                        width: *width,
+                        height: 0.2,
+                        pose: m::PoseBuilder::new().with_position(position),
+                    },
+                    random_color(),
+                    false,
+                    graph,
+                )
+                .unwrap();
+                physics.add_constraint(
+                    phys::ConstraintBuilder::new(platform)
+                        .with_target_origin(position)
+                        .with_translation_limits(0.0, *travel)
+                        .build_prismatic(m::Vec2::unit_y()),
+                );
+            }
+            Recipe::OneWayPlatform { width, pose } => {
+                let pose_node = graph.l_pose.insert((*pose).into(), &mut graph.graph);
+                let coll = phys::Collider::new_rect(*width, 0.1).with_one_way(m::Vec2::unit_y());
+                let coll_node = graph.l_collider.insert(coll, &mut graph.graph);
+                let shape_node = graph.l_shape.insert(
+                    gx::Shape::from_collider(&coll, [0.7, 0.7, 0.5, 1.0]),
+                    &mut graph.graph,
+                );
+                graph.graph.connect(&pose_node, &coll_node);
+                graph.graph.connect(&pose_node, &shape_node);
+            }
+            Recipe::Rope {
+                start,
+                end,
+                segments,
+                anchored_start,
+                anchored_end,
+                bending_compliance,
+                break_force,
+            } => {
+                let mut builder = phys::RopeBuilder::new(start.into(), end.into())
+                    .with_segments(*segments)
+                    .with_collision(0.05);
+                if *anchored_start {
+                    builder = builder.with_start_pinned();
+                }
+                if *anchored_end {
+                    builder = builder.with_end_pinned();
+                }
+                if let Some(compliance) = bending_compliance {
+                    builder = builder.with_bending_compliance(*compliance);
+                }
+                if let Some(force) = break_force {
+                    builder = builder.with_break_force(*force);
+                }
+                let rope = builder.build(
+                    physics,
+                    &mut graph.graph,
+                    &mut graph.l_pose,
+                    &mut graph.l_body,
+                    &mut graph.l_collider,
+                );
+
+                // the rope's shape is drawn in world space and updated every frame
+                let shape_node = graph.l_shape.insert(
+                    gx::Shape::from_rope(
+                        &rope,
+                        physics,
+                        &graph.graph,
+                        &graph.l_pose,
+                        &graph.l_body,
+                        ROPE_THICKNESS,
+                        ROPE_COLOR,
+                    ),
+                    &mut graph.graph,
+                );
+                let pose_node = graph.l_pose.insert(m::Pose::default(), &mut graph.graph);
+                let rope_node = graph.l_rope.insert(rope, &mut graph.graph);
+                graph.graph.connect(&pose_node, &shape_node);
+                graph.graph.connect(&rope_node, &shape_node);
+            }
+            Recipe::SoftBall {
+                position,
+                radius,
+                particles,
+                area_compliance,
+            } => {
+                let builder = phys::SoftBodyBuilder::new_ring(position.into(), *radius, *particles)
+                    .with_edge_compliance(0.001)
+                    .with_area_compliance(*area_compliance);
+                spawn_soft_body(builder, graph, physics);
+            }
+            Recipe::Jelly {
+                position,
+                width,
+                height,
+                columns,
+                rows,
+            } => {
+                let builder = phys::SoftBodyBuilder::new_lattice(
+                    position.into(),
+                    *width,
+                    *height,
+                    *columns,
+                    *rows,
+                )
+                .with_edge_compliance(0.005);
+                spawn_soft_body(builder, graph, physics);
+            }
+            Recipe::Water {
+                position,
+                width,
+                height,
+                density,
+            } => {
+                let pose_node = graph.l_pose.insert(
+                    m::Pose::new(position.into(), m::Rotor2::identity()),
+                    &mut graph.graph,
+                );
+                let coll = phys::Collider::new_rect(*width, *height).fluid(phys::Fluid {
+                    density: *density,
+                    ..Default::default()
+                });
+                let coll_node = graph.l_collider.insert(coll, &mut graph.graph);
+                let shape_node = graph.l_shape.insert(
+                    gx::Shape::from_collider(&coll, [0.2, 0.4, 0.8, 0.5]),
+                    &mut graph.graph,
+                );
+                graph.graph.connect(&pose_node, &coll_node);
+                graph.graph.connect(&pose_node, &shape_node);
+            }
+            Recipe::Conveyor {
+                position,
+                wheels,
+                radius,
+                speed,
+            } => {
+                let position: m::Vec2 = position.into();
+                let color = random_color();
+                for i in 0..*wheels {
+                    let center = position + m::Vec2::new(i as f64 * (2.0 * radius + 0.05), 0.0);
+                    let wheel = spawn_body(
+                        m::Pose::new(center, m::Rotor2::identity()),
+                        phys::Collider::new_circle(*radius),
+                        color,
+                        graph,
+                    );
+                    // spinning clockwise moves things on top to the right
+                    physics.add_constraint(
+                        phys::ConstraintBuilder::new(wheel)
+                            .with_target_origin(center)
+                            .with_motor(phys::Motor::AngularVelocity {
+                                velocity: -speed / radius,
+                                max_torque: 10.0,
+                            })
+                            .build_revolute(),
+                    );
+                }
+            }
+            Recipe::ParticleWater {
+                particle_radius,
+                blocks,
+                taps,
+            } => {
+                let mut fluid = phys::ParticleFluid::new(*particle_radius).with_max_particles(4000);
+                for [min, max] in blocks {
+                    fluid.spawn_rect(min.into(), max.into(), m::Vec2::zero());
+                }
+                let shape_node = graph.l_shape.insert(
+                    gx::Shape::from_particle_fluid(&fluid, WATER_COLOR),
+                    &mut graph.graph,
+                );
+                let water = ParticleWater {
+                    fluid: physics.add_particle_fluid(fluid),
+                    taps: taps
+                        .iter()
+                        .map(|tap| {
+                            phys::ParticleEmitter::new(
+                                tap.position.into(),
+                                tap.velocity.into(),
+                                tap.width,
+                            )
+                        })
+                        .collect(),
+                };
+                let pose_node = graph.l_pose.insert(m::Pose::default(), &mut graph.graph);
+                let water_node = graph.l_water.insert(water, &mut graph.graph);
+                graph.graph.connect(&pose_node, &shape_node);
+                graph.graph.connect(&water_node, &shape_node);
+            }
             Recipe::Oscillator {
                 position,
                 begin_length,
@@ -301,3 +667,56 @@ fn random_color() -> [f32; 4] {
         1.0,
     ]
 }
+
+const ROPE_THICKNESS: f64 = 0.1;
+const ROPE_COLOR: [f32; 4] = [0.8, 0.7, 0.5, 1.0];
+const WATER_COLOR: [f32; 4] = [0.2, 0.4, 0.8, 1.0];
+
+/// Move the shapes of ropes and soft bodies to where their particles are.
+pub fn update_deformable_shapes(g: &mut crate::MyGraph, physics: &phys::Physics) {
+    for rope in g.l_rope.iter(&g.graph) {
+        if let Some(mut shape) = g.graph.get_neighbor_mut(&rope, &mut g.l_shape) {
+            *shape = gx::Shape::from_rope(
+                &rope,
+                physics,
+                &g.graph,
+                &g.l_pose,
+                &g.l_body,
+                ROPE_THICKNESS,
+                ROPE_COLOR,
+            );
+        }
+    }
+    for soft_body in g.l_soft_body.iter(&g.graph) {
+        if let Some(mut shape) = g.graph.get_neighbor_mut(&soft_body, &mut g.l_shape) {
+            let color = match *shape {
+                gx::Shape::Poly { color, .. } => color,
+                _ => continue,
+            };
+            *shape = gx::Shape::from_soft_body(&soft_body, &g.graph, &g.l_pose, &g.l_body, color);
+        }
+    }
+}
+
+/// Pour water from taps, remove particles that have fallen out of the world,
+/// and move the shapes of particle fluids to where their particles are.
+pub fn update_particle_water(g: &mut crate::MyGraph, physics: &mut phys::Physics, dt: f64) {
+    for mut water in g.l_water.iter_mut(&g.graph) {
+        let fluid = match physics.particle_fluid_mut(water.fluid) {
+            Some(fluid) => fluid,
+            None => continue,
+        };
+        for tap in &mut water.taps {
+            tap.emit(fluid, dt);
+        }
+        fluid.retain(|pos, _| pos.y > -50.0);
+    }
+    for water in g.l_water.iter(&g.graph) {
+        if let (Some(mut shape), Some(fluid)) = (
+            g.graph.get_neighbor_mut(&water, &mut g.l_shape),
+            physics.particle_fluid(water.fluid),
+        ) {
+            *shape = gx::Shape::from_particle_fluid(fluid, WATER_COLOR);
+        }
+    }
+}
@@ -12,9 +12,31 @@ use super::graph as g;
 /// Expect major changes here.
 #[derive(Clone, Copy, Debug)]
 pub enum Event {
-    /// A contact happened in the physics system. Received if connected to a
-    /// [`Collider`][crate::physics::Collider].
-    Contact(crate::physics::ContactEvent),
+    /// Two colliders started touching in the physics system. Received if connected to a
+    /// [`Collider`][crate::physics::Collider], or to the [`Body`][crate::physics::Body]
+    /// it's attached to if the collider doesn't have a sink of its own.
+    ContactBegan(crate::physics::ContactEvent),
+    /// Two colliders that were touching on the previous frame are still touching.
+    /// Only sent if enabled with
+    /// [`Physics::persisted_contact_events`][crate::physics::Physics::persisted_contact_events].
+    /// Received the same way as `ContactBegan`.
+    ContactPersisted(crate::physics::ContactEvent),
+    /// Two colliders that were touching on the previous frame stopped touching.
+    /// Received the same way as `ContactBegan`.
+    ContactEnded(crate::physics::ContactEvent),
+    /// A collider started overlapping a trigger collider.
+    /// Received by sinks connected to either collider, or to their bodies
+    /// if the colliders don't have sinks of their own.
+    TriggerEnter(crate::physics::TriggerEvent),
+    /// A collider stopped overlapping a trigger collider.
+    /// Received the same way as `TriggerEnter`.
+    TriggerExit(crate::physics::TriggerEvent),
+    /// A constraint needed more force than its
+    /// [`break_force`][crate::physics::Constraint::break_force]
+    /// or more torque than its [`break_torque`][crate::physics::Constraint::break_torque]
+    /// and was removed.
+    /// Received if connected to the constraint's owner [`Body`][crate::physics::Body].
+    ConstraintBroken(crate::physics::ConstraintBrokenEvent),
 }
 
 /// A function that consumes events.
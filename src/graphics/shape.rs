@@ -7,7 +7,7 @@ use std::borrow::Cow;
 use zerocopy::{AsBytes, FromBytes};
 
 type Color = [f32; 4];
-/// A flat-colored convex polygon shape.
+/// A flat-colored convex polygon shape, or a line through a series of points.
 ///
 /// Concavity will not result in an error but will be rendered incorrectly.
 #[derive(Clone, Debug)]
@@ -32,6 +32,20 @@ pub enum Shape {
         points: Vec<m::Vec2>,
         color: Color,
     },
+    /// A line of the given thickness going through the points in order.
+    /// Unlike the other shapes, this can be concave.
+    /// A non-finite point splits the line into separate pieces.
+    Polyline {
+        points: Vec<m::Vec2>,
+        thickness: f64,
+        color: Color,
+    },
+    /// Many small circles of the same size, like the particles of a fluid.
+    Particles {
+        points: Vec<m::Vec2>,
+        radius: f64,
+        color: Color,
+    },
 }
 
 impl Shape {
@@ -55,6 +69,78 @@ impl Shape {
                 points_per_cap: 8,
                 color,
             },
+            ColliderShape::Polygon(poly) => Shape::Poly {
+                points: poly.points().to_vec(),
+                color,
+            },
+        }
+    }
+
+    /// Create a polyline Shape following the particles of a rope.
+    ///
+    /// The points are in world space, so the shape should be connected to a pose
+    /// at the world origin. Ropes move, so the shape needs to be recreated every frame.
+    /// If the rope has broken, the gaps are left undrawn.
+    pub fn from_rope(
+        rope: &crate::physics::Rope,
+        physics: &crate::physics::Physics,
+        graph: &graph::Graph,
+        l_pose: &graph::Layer<m::Pose>,
+        l_body: &graph::Layer<crate::physics::Body>,
+        thickness: f64,
+        color: Color,
+    ) -> Self {
+        let mut points = Vec::new();
+        for piece in rope.pieces(physics, graph, l_pose, l_body) {
+            if !points.is_empty() {
+                points.push(m::Vec2::new(f64::NAN, f64::NAN));
+            }
+            points.extend(piece);
+        }
+        Shape::Polyline {
+            points,
+            thickness,
+            color,
+        }
+    }
+
+    /// Create a polygon Shape following the outline of a soft body.
+    ///
+    /// Like with [`from_rope`][Self::from_rope], the points are in world space
+    /// and the shape needs to be recreated every frame.
+    /// The polygon is drawn as a fan around the middle of the outline,
+    /// so squished outlines render correctly as long as they don't fold over themselves.
+    pub fn from_soft_body(
+        soft_body: &crate::physics::SoftBody,
+        graph: &graph::Graph,
+        l_pose: &graph::Layer<m::Pose>,
+        l_body: &graph::Layer<crate::physics::Body>,
+        color: Color,
+    ) -> Self {
+        let outline = soft_body.outline_points(graph, l_pose, l_body);
+        if outline.is_empty() {
+            return Shape::Poly {
+                points: outline,
+                color,
+            };
+        }
+        let middle = outline.iter().fold(m::Vec2::zero(), |acc, p| acc + *p) / outline.len() as f64;
+        let points = std::iter::once(middle)
+            .chain(outline.iter().copied())
+            .chain(std::iter::once(outline[0]))
+            .collect();
+        Shape::Poly { points, color }
+    }
+
+    /// Create a Shape drawing every particle of a particle fluid.
+    ///
+    /// Like with [`from_rope`][Self::from_rope], the points are in world space
+    /// and the shape needs to be recreated every frame.
+    pub fn from_particle_fluid(fluid: &crate::physics::ParticleFluid, color: Color) -> Self {
+        Shape::Particles {
+            points: fluid.positions().to_vec(),
+            radius: fluid.particle_radius(),
+            color,
         }
     }
 
@@ -133,6 +219,53 @@ impl Shape {
                 as_verts(verts.as_slice(), pose, *color)
             }
             Shape::Poly { points, color } => as_verts(points.as_slice(), pose, *color),
+            Shape::Polyline {
+                points,
+                thickness,
+                color,
+            } => {
+                // a rectangle for each segment, extended past the ends
+                // to cover the gaps at the joints
+                let half_thickness = thickness / 2.0;
+                points
+                    .windows(2)
+                    .filter_map(|segment| {
+                        let dir = (segment[1] - segment[0]).normalized();
+                        if !dir.x.is_finite() || !dir.y.is_finite() {
+                            return None;
+                        }
+                        let along = half_thickness * dir;
+                        let across = half_thickness * m::left_normal(dir);
+                        let (start, end) = (segment[0] - along, segment[1] + along);
+                        Some(as_verts(
+                            &[start - across, end - across, end + across, start + across],
+                            pose,
+                            *color,
+                        ))
+                    })
+                    .flatten()
+                    .collect()
+            }
+            Shape::Particles {
+                points,
+                radius,
+                color,
+            } => {
+                // hexagons are round enough at the size particles usually are
+                let corners: Vec<m::Vec2> = (0..6)
+                    .map(|i| {
+                        let angle = std::f64::consts::PI / 3.0 * i as f64;
+                        m::Vec2::new(radius * angle.cos(), radius * angle.sin())
+                    })
+                    .collect();
+                points
+                    .iter()
+                    .flat_map(|point| {
+                        let hexagon: Vec<m::Vec2> = corners.iter().map(|c| *point + *c).collect();
+                        as_verts(&hexagon, pose, *color)
+                    })
+                    .collect()
+            }
         }
     }
 }
@@ -6,21 +6,46 @@ use crate::{
 
 use itertools::izip;
 use slotmap as sm;
+use std::collections::{HashMap, HashSet};
 
 //
 
 pub mod collision;
+pub use collision::query::Ray;
 use collision::{shape_shape::intersection_check, SpatialIndex};
-pub use collision::{Collider, ColliderShape, ColliderType, Contact, ContactResult, Material};
+pub use collision::{
+    BroadPhase, Collider, ColliderShape, ColliderType, CollisionFilter, CombineRule, Contact,
+    ContactResult, ConvexPolygon, Material, PairMaterial, AABB,
+};
 
 pub mod constraint;
-pub use constraint::{Constraint, ConstraintBuilder, ConstraintLimit, ConstraintType};
+pub use constraint::{Constraint, ConstraintBuilder, ConstraintLimit, ConstraintType, Motor};
 
 pub mod forcefield;
 pub use forcefield::ForceField;
 
+pub mod fluid;
+pub use fluid::Fluid;
+
+pub mod rope;
+pub use rope::{Rope, RopeAttachment, RopeBuilder};
+
+pub mod softbody;
+pub use softbody::{AreaConstraint, SoftBody, SoftBodyBuilder};
+
+pub mod particle_fluid;
+pub use particle_fluid::{ParticleEmitter, ParticleFluid};
+
+pub mod character;
+pub use character::{CharacterContext, CharacterController, Ground};
+
 pub mod body;
-pub use body::{Body, Mass};
+use body::SleepState;
+pub use body::{Body, Mass, MassProperties};
+
+pub mod sleep;
+use sleep::Islands;
+pub use sleep::SleepSettings;
 
 //
 
@@ -29,7 +54,7 @@ pub use body::{Body, Mass};
 // Equivalent to a Vec3 but with names for the translational and rotational part.
 #[derive(Copy, Clone, Debug)]
 pub struct Velocity {
-    /// Linear velocity in metres per second.
+    /// Linear velocity of the center of mass in metres per second.
     pub linear: m::Vec2,
     /// Angular velocity in radians per second.
     pub angular: f64,
@@ -45,6 +70,11 @@ impl Default for Velocity {
 }
 
 impl Velocity {
+    /// Check whether the velocity is exactly zero.
+    pub fn is_zero(&self) -> bool {
+        self.linear == m::Vec2::zero() && self.angular == 0.0
+    }
+
     /// Get the linear velocity of a point offset from the center of mass.
     pub fn point_velocity(&self, offset: m::Vec2) -> m::Vec2 {
         let tangent = m::left_normal(offset) * self.angular;
@@ -86,22 +116,206 @@ impl std::ops::Mul<f64> for Velocity {
 
 /// Events produced by the physics system when two physics objects collide.
 ///
-/// Note: for now this doesn't tell you anything except which body the contact was with.
-/// This isn't great, but that's the only thing I've needed so far.
-/// More info will be added when needed in a project.
+/// These are gathered once per frame, and sent as
+/// [`ContactBegan`][crate::event::Event::ContactBegan],
+/// [`ContactPersisted`][crate::event::Event::ContactPersisted] or
+/// [`ContactEnded`][crate::event::Event::ContactEnded] depending on whether
+/// the colliders were already touching on the previous frame.
 #[derive(Clone, Copy, Debug)]
 pub struct ContactEvent {
+    /// The collider of this object that was hit.
+    /// Useful for telling apart the parts of a compound body.
+    pub collider: graph::Node<Collider>,
     /// The collider that this body was in contact with.
     pub other_collider: graph::Node<Collider>,
+    /// Contact normal in world space, pointing from this collider towards the other one.
+    pub normal: m::Unit<m::Vec2>,
+    /// Points of contact in world space, halfway between the surfaces of the colliders.
+    /// There's always at least one point.
+    pub points: [Option<m::Vec2>; 2],
+    /// Total impulse applied along the normal to push the colliders apart during the frame.
+    ///
+    /// Ended contacts have the normal and points they had on the last frame they were touching,
+    /// but zero impulse.
+    pub impulse: f64,
+}
+
+/// Events produced by the physics system when a collider enters or exits a trigger.
+///
+/// Sent as [`TriggerEnter`][crate::event::Event::TriggerEnter]
+/// and [`TriggerExit`][crate::event::Event::TriggerExit]
+/// to both the trigger and the collider overlapping it.
+/// Triggers don't detect other triggers.
+#[derive(Clone, Copy, Debug)]
+pub struct TriggerEvent {
+    /// The trigger collider.
+    pub trigger: graph::Node<Collider>,
+    /// The collider that entered or exited the trigger.
+    pub other: graph::Node<Collider>,
+    /// The body the other collider is attached to, if any.
+    pub other_body: Option<graph::Node<Body>>,
+}
+
+/// Event produced by the physics system when a constraint breaks.
+///
+/// Sent as [`ConstraintBroken`][crate::event::Event::ConstraintBroken]
+/// to the owner of the constraint.
+#[derive(Clone, Copy, Debug)]
+pub struct ConstraintBrokenEvent {
+    /// The handle the constraint had. It no longer refers to anything.
+    pub handle: ConstraintHandle,
+    /// The constraint as it was when it broke.
+    pub constraint: Constraint,
+    /// The force the constraint was applying, averaged over the frame.
+    pub force: f64,
+    /// The torque the constraint was applying to hold the bodies at an angle,
+    /// averaged over the frame.
+    pub torque: f64,
+}
+
+impl ContactEvent {
+    /// The same event from the point of view of the other collider.
+    fn flipped(self) -> Self {
+        ContactEvent {
+            collider: self.other_collider,
+            other_collider: self.collider,
+            normal: m::Unit::new_unchecked(-*self.normal),
+            ..self
+        }
+    }
+}
+
+/// A collider hit by a ray cast with [`Physics::raycast`][self::Physics::raycast].
+pub struct RayHit<'g> {
+    /// The collider that was hit.
+    pub collider: graph::NodeRef<'g, Collider>,
+    /// The body the collider is attached to, if any.
+    pub body: Option<graph::NodeRef<'g, Body>>,
+    /// The point where the ray hit the collider's surface.
+    pub point: m::Vec2,
+    /// Surface normal at the hit point.
+    pub normal: m::Unit<m::Vec2>,
+    /// Distance from the start of the ray to the hit point.
+    pub distance: f64,
+}
+
+/// A collider hit by a shape cast with [`Physics::shape_cast`][self::Physics::shape_cast].
+pub struct ShapeCastHit<'g> {
+    /// The collider that was hit.
+    pub collider: graph::NodeRef<'g, Collider>,
+    /// The body the collider is attached to, if any.
+    pub body: Option<graph::NodeRef<'g, Body>>,
+    /// Fraction of the translation the cast shape traveled before hitting, between 0 and 1.
+    pub time_of_impact: f64,
+    /// Point of contact on the surface of the collider that was hit.
+    pub point: m::Vec2,
+    /// Surface normal of the collider that was hit at the contact point.
+    pub normal: m::Unit<m::Vec2>,
+}
+
+/// The parts of the graph that spatial queries like [`Physics::raycast`][self::Physics::raycast]
+/// look at, bundled together since every query needs all of them.
+#[derive(Clone, Copy)]
+pub struct QueryContext<'g> {
+    pub graph: &'g graph::Graph,
+    pub l_pose: &'g graph::Layer<m::Pose>,
+    pub l_collider: &'g graph::Layer<Collider>,
+    pub l_body: &'g graph::Layer<Body>,
+}
+
+/// Restricts which colliders [`Physics::query_aabb`][self::Physics::query_aabb]
+/// and [`Physics::query_shape`][self::Physics::query_shape] find.
+///
+/// The default finds every collider.
+#[derive(Clone, Copy, Debug)]
+pub struct QueryFilter {
+    /// Whether to find solid colliders.
+    pub solids: bool,
+    /// Whether to find triggers.
+    pub triggers: bool,
+    /// Whether to find fluids.
+    pub fluids: bool,
+    /// Only find colliders attached to a body if `Some(true)`,
+    /// only ones not attached to a body if `Some(false)`, or both if `None`.
+    pub attached_to_body: Option<bool>,
+}
+
+impl Default for QueryFilter {
+    fn default() -> Self {
+        QueryFilter {
+            solids: true,
+            triggers: true,
+            fluids: true,
+            attached_to_body: None,
+        }
+    }
+}
+
+impl QueryFilter {
+    /// A filter that only finds solid colliders.
+    pub fn solids() -> Self {
+        QueryFilter {
+            triggers: false,
+            fluids: false,
+            ..Default::default()
+        }
+    }
+
+    /// Only find colliders attached to a body if `attached` is true,
+    /// or only ones not attached to a body if it's false.
+    pub fn attached_to_body(mut self, attached: bool) -> Self {
+        self.attached_to_body = Some(attached);
+        self
+    }
+
+    /// Check whether a collider passes the filter.
+    pub fn allows(&self, coll: &Collider, has_body: bool) -> bool {
+        let type_allowed = match coll.ty {
+            ColliderType::Solid(_) => self.solids,
+            ColliderType::Trigger => self.triggers,
+            ColliderType::Fluid(_) => self.fluids,
+        };
+        type_allowed && self.attached_to_body != Some(!has_body)
+    }
 }
 
 sm::new_key_type! {
     pub struct ConstraintHandle;
+    pub struct AreaConstraintHandle;
+    pub struct ParticleFluidHandle;
 }
 
 pub struct Physics {
     pub substeps: usize,
+    pub sleep: SleepSettings,
+    /// Whether to send [`ContactPersisted`][crate::event::Event::ContactPersisted] events
+    /// every frame for contacts that were already touching on the previous frame.
+    /// Off by default.
+    pub persisted_contact_events: bool,
+    /// Whether only the most overloaded constraint in each island breaks per frame.
+    ///
+    /// Breaking a constraint changes the forces on the others, so this makes e.g.
+    /// a taut rope snap at one point instead of at every segment over its limit at once.
+    /// Off by default, which breaks every constraint over its limit.
+    pub one_break_per_island: bool,
     user_constraints: sm::DenseSlotMap<ConstraintHandle, Constraint>,
+    area_constraints: sm::DenseSlotMap<AreaConstraintHandle, AreaConstraint>,
+    particle_fluids: sm::DenseSlotMap<ParticleFluidHandle, ParticleFluid>,
+    // coefficients for specific pairs of material ids, keyed in ascending order
+    material_pairs: HashMap<[u32; 2], PairMaterial>,
+    spatial_index: SpatialIndex,
+    // contacts touching on the last tick, used to tell when they begin and end.
+    // keyed by collider pair in order of index, with the event seen from the first collider
+    contacts: HashMap<[graph::Node<Collider>; 2], ContactEvent>,
+    // colliders overlapping triggers on the last tick, keyed by [trigger, other]
+    trigger_overlaps: HashMap<[graph::Node<Collider>; 2], TriggerEvent>,
+    // pairs passing through a one-way collider, ignored until they stop overlapping.
+    // keyed like `contacts`
+    one_way_passes: HashSet<[graph::Node<Collider>; 2]>,
+    // bodies told to drop through one-way colliders on the next tick
+    bodies_to_drop: Vec<graph::Node<Body>>,
+    // bodies whose constraints were changed since the last tick
+    bodies_to_wake: Vec<graph::Node<Body>>,
 }
 
 impl Default for Physics {
@@ -115,12 +329,64 @@ impl Physics {
     pub fn with_substeps(substeps: usize) -> Self {
         Physics {
             substeps,
+            sleep: SleepSettings::default(),
+            persisted_contact_events: false,
+            one_break_per_island: false,
             user_constraints: sm::DenseSlotMap::with_key(),
+            area_constraints: sm::DenseSlotMap::with_key(),
+            particle_fluids: sm::DenseSlotMap::with_key(),
+            material_pairs: HashMap::new(),
+            spatial_index: SpatialIndex::default(),
+            contacts: HashMap::new(),
+            trigger_overlaps: HashMap::new(),
+            one_way_passes: HashSet::new(),
+            bodies_to_drop: Vec::new(),
+            bodies_to_wake: Vec::new(),
         }
     }
 
+    /// Set the parameters for putting bodies to sleep in a builder-like chain.
+    pub fn with_sleep(mut self, sleep: SleepSettings) -> Self {
+        self.sleep = sleep;
+        self
+    }
+
+    /// Set the broad phase algorithm used to find potentially colliding pairs
+    /// in a builder-like chain.
+    pub fn with_broad_phase(mut self, broad_phase: BroadPhase) -> Self {
+        self.set_broad_phase(broad_phase);
+        self
+    }
+
+    /// Set the broad phase algorithm used to find potentially colliding pairs.
+    ///
+    /// This discards the persistent state of the previous algorithm,
+    /// so it should not be called every frame.
+    pub fn set_broad_phase(&mut self, broad_phase: BroadPhase) {
+        let margin = self.spatial_index.margin;
+        self.spatial_index = SpatialIndex::new(broad_phase);
+        self.spatial_index.margin = margin;
+    }
+
+    /// Get the broad phase algorithm currently in use.
+    pub fn broad_phase(&self) -> BroadPhase {
+        self.spatial_index.method()
+    }
+
+    /// Set the extra space added around bounding boxes in the broad phase.
+    ///
+    /// Bounding boxes are already extended to cover motion predicted from velocity,
+    /// so this only needs to be large enough for motion caused by forces and collisions
+    /// during a single frame.
+    pub fn set_broad_phase_margin(&mut self, margin: f64) {
+        self.spatial_index.margin = margin;
+    }
+
     /// Add a user-defined constraint to the system. Returns a handle that can be used to remove it later.
+    ///
+    /// Wakes up the bodies involved.
     pub fn add_constraint(&mut self, constraint: Constraint) -> ConstraintHandle {
+        self.wake_constraint_bodies(&constraint);
         self.user_constraints.insert(constraint)
     }
 
@@ -130,8 +396,13 @@ impl Physics {
     }
 
     /// Mutably access a constraint if it still exists.
+    ///
+    /// Wakes up the bodies involved, since the constraint may be changed.
     pub fn get_constraint_mut(&mut self, handle: ConstraintHandle) -> Option<&mut Constraint> {
-        self.user_constraints.get_mut(handle)
+        let constraint = self.user_constraints.get_mut(handle)?;
+        self.bodies_to_wake.push(constraint.owner);
+        self.bodies_to_wake.extend(constraint.target);
+        Some(constraint)
     }
 
     /// Remove a constraint from the system. Returns the constraint if it still existed.
@@ -140,12 +411,129 @@ impl Physics {
     /// are destroyed, so it's not guaranteed the constraint will exist
     /// even if it hasn't been explicitly removed before.
     pub fn remove_constraint(&mut self, handle: ConstraintHandle) -> Option<Constraint> {
-        self.user_constraints.remove(handle)
+        let constraint = self.user_constraints.remove(handle)?;
+        self.wake_constraint_bodies(&constraint);
+        Some(constraint)
+    }
+
+    /// Add a constraint keeping the area enclosed by a loop of bodies constant.
+    /// Returns a handle that can be used to remove it later.
+    ///
+    /// Wakes up the bodies involved.
+    pub fn add_area_constraint(&mut self, constraint: AreaConstraint) -> AreaConstraintHandle {
+        self.bodies_to_wake.extend(&constraint.bodies);
+        self.area_constraints.insert(constraint)
+    }
+
+    /// Access an area constraint if it still exists.
+    pub fn get_area_constraint(&self, handle: AreaConstraintHandle) -> Option<&AreaConstraint> {
+        self.area_constraints.get(handle)
+    }
+
+    /// Mutably access an area constraint if it still exists.
+    ///
+    /// Wakes up the bodies involved, since the constraint may be changed.
+    pub fn get_area_constraint_mut(
+        &mut self,
+        handle: AreaConstraintHandle,
+    ) -> Option<&mut AreaConstraint> {
+        let constraint = self.area_constraints.get_mut(handle)?;
+        self.bodies_to_wake.extend(&constraint.bodies);
+        Some(constraint)
+    }
+
+    /// Remove an area constraint from the system. Returns the constraint if it still existed.
+    ///
+    /// Like other constraints, area constraints disappear on their own
+    /// if any of their bodies are destroyed.
+    pub fn remove_area_constraint(
+        &mut self,
+        handle: AreaConstraintHandle,
+    ) -> Option<AreaConstraint> {
+        let constraint = self.area_constraints.remove(handle)?;
+        self.bodies_to_wake.extend(&constraint.bodies);
+        Some(constraint)
     }
 
     /// Remove all constraints.
+    ///
+    /// Wakes up the bodies involved.
     pub fn clear_constraints(&mut self) {
-        self.user_constraints.clear();
+        for (_, constraint) in self.user_constraints.drain() {
+            self.bodies_to_wake.push(constraint.owner);
+            self.bodies_to_wake.extend(constraint.target);
+        }
+        for (_, constraint) in self.area_constraints.drain() {
+            self.bodies_to_wake.extend(&constraint.bodies);
+        }
+    }
+
+    /// Add a particle fluid to be simulated along with everything else.
+    /// Returns a handle that can be used to access and remove it later.
+    pub fn add_particle_fluid(&mut self, fluid: ParticleFluid) -> ParticleFluidHandle {
+        self.particle_fluids.insert(fluid)
+    }
+
+    /// Access a particle fluid, e.g. to read its particles' positions for rendering.
+    pub fn particle_fluid(&self, handle: ParticleFluidHandle) -> Option<&ParticleFluid> {
+        self.particle_fluids.get(handle)
+    }
+
+    /// Mutably access a particle fluid, e.g. to spawn more particles.
+    pub fn particle_fluid_mut(
+        &mut self,
+        handle: ParticleFluidHandle,
+    ) -> Option<&mut ParticleFluid> {
+        self.particle_fluids.get_mut(handle)
+    }
+
+    /// Remove a particle fluid and all its particles. Returns the fluid if it existed.
+    pub fn remove_particle_fluid(&mut self, handle: ParticleFluidHandle) -> Option<ParticleFluid> {
+        self.particle_fluids.remove(handle)
+    }
+
+    /// Remove all particle fluids.
+    pub fn clear_particle_fluids(&mut self) {
+        self.particle_fluids.clear();
+    }
+
+    /// Use the given coefficients for collisions between materials with the given ids
+    /// instead of combining the materials' own coefficients.
+    ///
+    /// The order of the ids doesn't matter,
+    /// and they can be the same to tune how a material interacts with itself.
+    pub fn set_material_pair(&mut self, id_a: u32, id_b: u32, coefs: PairMaterial) {
+        self.material_pairs
+            .insert(material_pair_key(id_a, id_b), coefs);
+    }
+
+    /// Stop using special coefficients for a pair of material ids.
+    /// Returns the coefficients if they were set.
+    pub fn remove_material_pair(&mut self, id_a: u32, id_b: u32) -> Option<PairMaterial> {
+        self.material_pairs.remove(&material_pair_key(id_a, id_b))
+    }
+
+    /// Get the coefficients used for a collision between two materials,
+    /// either from the pair table or combined from the materials.
+    pub fn material_pair(&self, a: &Material, b: &Material) -> PairMaterial {
+        match self.material_pairs.get(&material_pair_key(a.id, b.id)) {
+            Some(coefs) => *coefs,
+            None => a.combined_with(b),
+        }
+    }
+
+    /// Let a body fall through the one-way colliders it's touching on the next tick.
+    ///
+    /// The body keeps passing through each of them until they no longer overlap,
+    /// after which it collides with them normally again.
+    pub fn drop_through_one_way(&mut self, body: graph::Node<Body>) {
+        self.bodies_to_drop.push(body);
+        self.bodies_to_wake.push(body);
+    }
+
+    fn wake_constraint_bodies(&mut self, constraint: &Constraint) {
+        self.bodies_to_wake.push(constraint.owner);
+        self.bodies_to_wake.extend(constraint.target);
     }
 
     /// Detect collisions, solve constraint forces and move bodies.
@@ -159,6 +547,7 @@ impl Physics {
         dt: f64,
         forcefield: &impl ForceField,
     ) {
+        let frame_dt = dt;
         let dt = dt / self.substeps as f64;
         let inv_dt = 1.0 / dt;
         let inv_dt_sq = inv_dt * inv_dt;
@@ -189,9 +578,38 @@ impl Physics {
         let mut old_velocities: Vec<Velocity> =
             body_refs.iter().map(|body| body.velocity).collect();
         let mut velocities: Vec<Velocity> = old_velocities.clone();
+        // colliders for force fields that depend on the shape of the body
+        let body_colliders: Vec<Option<graph::NodeRef<Collider>>> = body_refs
+            .iter()
+            .map(|b| graph.get_neighbor(b, l_collider))
+            .collect();
         // accelerations from external forces used as a speed limit for restitution
         let mut ext_f_accelerations: Vec<m::Vec2> = vec![m::Vec2::default(); velocities.len()];
 
+        // sleeping bodies wake up if they were moved or given a velocity since the last tick
+        let mut sleep_states: Vec<SleepState> = izip!(&body_refs, &poses)
+            .map(|(body, pose)| match body.sleep_state {
+                _ if !self.sleep.enabled => SleepState::awake(),
+                SleepState::Asleep { pose: None } => SleepState::Asleep { pose: Some(*pose) },
+                SleepState::Asleep {
+                    pose: Some(sleep_pose),
+                } if sleep_pose != *pose || !body.velocity.is_zero() => SleepState::awake(),
+                state => state,
+            })
+            .collect();
+        // as do bodies whose constraints were changed
+        for body in self.bodies_to_wake.drain(..) {
+            if body.pos().item_idx < node_ref_map.len() && body.check(graph).is_some() {
+                sleep_states[node_ref_map[body.pos().item_idx]] = SleepState::awake();
+            }
+        }
+        let mut dropping: Vec<bool> = vec![false; body_refs.len()];
+        for body in self.bodies_to_drop.drain(..) {
+            if body.pos().item_idx < node_ref_map.len() && body.check(graph).is_some() {
+                dropping[node_ref_map[body.pos().item_idx]] = true;
+            }
+        }
+
         //
         // set up user-defined constraints
         //
@@ -216,6 +634,20 @@ impl Physics {
             })
             .collect();
 
+        // same for area constraints, which need all of their bodies
+        self.area_constraints
+            .retain(|_, c| c.bodies.iter().all(|b| b.check(graph).is_some()));
+        let area_constraint_bodies: Vec<Vec<usize>> = self
+            .area_constraints
+            .values()
+            .map(|c| {
+                c.bodies
+                    .iter()
+                    .map(|b| node_ref_map[b.pos().item_idx])
+                    .collect()
+            })
+            .collect();
+
         //
         // Set up collision detection
         //
@@ -230,9 +662,47 @@ impl Physics {
         // generate potentially colliding pairs,
         // these will be used to re-detect collisions every substep.
         // we can map them to NodeRefs here because we won't borrow colliders mutably
-        let coll_pairs: Vec<[graph::NodeRef<Collider>; 2]> = SpatialIndex::pairs(l_collider, graph)
+        self.spatial_index
+            .update(graph, l_pose, l_body, l_collider, frame_dt);
+        // bodies connected by constraints that don't let them collide
+        let non_colliding_bodies: HashSet<[graph::Node<Body>; 2]> = self
+            .user_constraints
+            .values()
+            .filter(|c| c.disable_collision)
+            .filter_map(|c| c.target.map(|t| [c.owner, t]))
+            .collect();
+        let coll_pairs: Vec<[graph::NodeRef<Collider>; 2]> = self
+            .spatial_index
+            .pairs()
             .iter()
             .map(|colls| map_pair(colls, |c| l_collider.get_unchecked(c.pos())))
+            // triggers don't detect each other
+            .filter(|colls| colls[0].is_solid() || colls[1].is_solid())
+            // collision layers
+            .filter(|colls| colls[0].filter.allows(&colls[1].filter))
+            .filter(|colls| {
+                let bodies = map_pair(colls, |c| {
+                    graph
+                        .get_neighbor_unchecked(c, l_body)
+                        .map(|b| graph::NodeRef::as_node(&b, graph))
+                });
+                // colliders can exclude a specific body
+                if (0..2).any(|i| {
+                    bodies[1 - i].is_some() && colls[i].filter.excluded_body == bodies[1 - i]
+                }) {
+                    return false;
+                }
+                match bodies {
+                    // parts of the same compound body don't collide with each other,
+                    // and neither do bodies whose constraint says so
+                    [Some(b0), Some(b1)] => {
+                        b0 != b1
+                            && !non_colliding_bodies.contains(&[b0, b1])
+                            && !non_colliding_bodies.contains(&[b1, b0])
+                    }
+                    _ => true,
+                }
+            })
             .collect();
         // indices to bodies in `body_refs` corresponding to `coll_pairs`
         let ctx_pairs: Vec<[ColliderContext; 2]> = coll_pairs
@@ -250,32 +720,372 @@ impl Physics {
             })
             .collect();
 
+        //
+        // Find islands of bodies connected by contacts and constraints.
+        // An island is either completely asleep or completely awake
+        //
+
+        // static and kinematic bodies aren't affected by anything,
+        // so they don't connect islands and never sleep
+        let is_dynamic = |ctx: &ColliderContext| match ctx {
+            ColliderContext::Body(bi) => body_refs[*bi].sees_forces(),
+            ColliderContext::Static(_) => false,
+        };
+        let mut islands = Islands::new(body_refs.len());
+        for (colls, ctxs) in izip!(&coll_pairs, &ctx_pairs) {
+            if let [ColliderContext::Body(b0), ColliderContext::Body(b1)] = ctxs {
+                if !is_dynamic(&ctxs[0])
+                    || !is_dynamic(&ctxs[1])
+                    || !colls[0].is_solid()
+                    || !colls[1].is_solid()
+                {
+                    continue;
+                }
+                // only bodies that are actually touching are connected.
+                // contacts from the last tick keep resting bodies together,
+                // and pairs with an awake body are checked in case they touch now
+                let key = contact_key(map_pair(colls, |c| graph::NodeRef::as_node(c, graph)));
+                let touching = self.contacts.contains_key(&key)
+                    || ((matches!(sleep_states[*b0], SleepState::Awake { .. })
+                        || matches!(sleep_states[*b1], SleepState::Awake { .. }))
+                        && !matches!(
+                            intersection_check(
+                                &(poses[*b0] * colls[0].offset),
+                                &colls[0],
+                                &(poses[*b1] * colls[1].offset),
+                                &colls[1],
+                            ),
+                            ContactResult::Zero
+                        ));
+                if touching {
+                    islands.join(*b0, *b1);
+                }
+            }
+        }
+        for pair in &constraint_body_pairs {
+            if let (b0, Some(b1)) = pair {
+                islands.join(*b0, *b1);
+            }
+        }
+        for bodies in &area_constraint_bodies {
+            for pair in bodies.windows(2) {
+                islands.join(pair[0], pair[1]);
+            }
+        }
+
+        let mut island_awake = vec![false; body_refs.len()];
+        for (bi, (body, state)) in body_refs.iter().zip(&sleep_states).enumerate() {
+            if body.sees_forces() && matches!(state, SleepState::Awake { .. }) {
+                island_awake[islands.root(bi)] = true;
+            }
+        }
+        // moving kinematic bodies wake up what they touch
+        for ctxs in &ctx_pairs {
+            for (i, ctx) in ctxs.iter().enumerate() {
+                if let (ColliderContext::Body(bi), ColliderContext::Body(other)) =
+                    (ctx, &ctxs[1 - i])
+                {
+                    if !body_refs[*bi].sees_forces() && !body_refs[*bi].velocity.is_zero() {
+                        island_awake[islands.root(*other)] = true;
+                    }
+                }
+            }
+        }
+        let awake: Vec<bool> = (0..body_refs.len())
+            .map(|bi| {
+                if !body_refs[bi].sees_forces() {
+                    return true;
+                }
+                if island_awake[islands.root(bi)] {
+                    if let SleepState::Asleep { .. } = sleep_states[bi] {
+                        sleep_states[bi] = SleepState::awake();
+                    }
+                    true
+                } else {
+                    false
+                }
+            })
+            .collect();
+
+        // pairs where nothing is awake don't need to be solved,
+        // except that triggers also detect kinematic bodies
+        let (awake_pairs, sleeping_pairs): (Vec<_>, Vec<_>) = coll_pairs
+            .into_iter()
+            .zip(ctx_pairs)
+            .partition(|(colls, ctxs)| {
+                let is_trigger_pair = !colls[0].is_solid() || !colls[1].is_solid();
+                ctxs.iter().any(|ctx| match ctx {
+                    ColliderContext::Body(bi) => {
+                        awake[*bi] && (is_trigger_pair || body_refs[*bi].sees_forces())
+                    }
+                    ColliderContext::Static(_) => false,
+                })
+            });
+        let (coll_pairs, ctx_pairs): (Vec<_>, Vec<_>) = awake_pairs.into_iter().unzip();
+
+        //
+        // Move to the center of mass
+        //
+
+        // the solver works with poses at each body's center of mass so that bodies rotate around it.
+        // sleeping bodies aren't touched so that rounding errors don't wake them up
+        let com_offsets: Vec<m::Vec2> = izip!(&body_refs, &awake)
+            .map(|(body, is_awake)| {
+                if *is_awake {
+                    body.center_of_mass
+                } else {
+                    m::Vec2::zero()
+                }
+            })
+            .collect();
+        for (old_pose, pose, com) in izip!(&mut old_poses, &mut poses, &com_offsets) {
+            pose.translation += pose.rotation * *com;
+            *old_pose = *pose;
+        }
+        // collider offsets and constraint points relative to the center of mass
+        let offset_pairs: Vec<[m::Pose; 2]> = izip!(&coll_pairs, &ctx_pairs)
+            .map(|(colls, ctxs)| {
+                map_pair(&[0, 1], |i| {
+                    let mut offset = colls[*i].offset;
+                    if let ColliderContext::Body(bi) = ctxs[*i] {
+                        offset.translation -= com_offsets[bi];
+                    }
+                    offset
+                })
+            })
+            .collect();
+        // new joints measure their angles from the angle the bodies are at now,
+        // and prismatic axes given in world space are moved into the target's space
+        for (constraint, pair) in izip!(self.user_constraints.values_mut(), &constraint_body_pairs)
+        {
+            if constraint.reference_angle.is_none() {
+                constraint.reference_angle = Some(relative_angle(&poses, *pair));
+                if let (ConstraintType::Prismatic { axis, .. }, Some(p1)) =
+                    (&mut constraint.ty, pair.1)
+                {
+                    *axis = poses[p1].rotation.reversed() * *axis;
+                }
+            }
+        }
+        let constraints: Vec<Constraint> =
+            izip!(self.user_constraints.values(), &constraint_body_pairs)
+                .map(|(constraint, pair)| {
+                    let mut constraint = *constraint;
+                    constraint.offsets[0] -= com_offsets[pair.0];
+                    if let Some(p1) = pair.1 {
+                        constraint.offsets[1] -= com_offsets[p1];
+                    }
+                    constraint
+                })
+                .collect();
+        // sums of the lagrange multipliers of each constraint over the frame,
+        // separately for positional and angular corrections, used to check if they break
+        let mut constraint_lambdas = vec![0.0; constraints.len()];
+        let mut constraint_angular_lambdas = vec![0.0; constraints.len()];
+
+        // solid colliders close enough to particle fluids that particles might hit them
+        let fluid_bounds: Option<AABB> = self
+            .particle_fluids
+            .values()
+            .filter_map(|fluid| {
+                let reach = fluid.max_speed() * frame_dt + fluid.particle_radius();
+                fluid.aabb().map(|aabb| aabb.padded(reach))
+            })
+            .fold(None, |bounds: Option<AABB>, aabb| {
+                Some(bounds.map_or(aabb, |bounds| bounds.union(&aabb)))
+            });
+        let fluid_obstacles: Vec<particle_fluid::FluidObstacle> = match fluid_bounds {
+            None => Vec::new(),
+            Some(bounds) => l_collider
+                .iter(graph)
+                .filter(|coll| coll.is_solid())
+                .filter_map(|coll| {
+                    let (body, offset) = match graph.get_neighbor(&coll, l_body) {
+                        Some(body) => {
+                            let bi = node_ref_map[body.pos().item_idx];
+                            let mut offset = coll.offset;
+                            offset.translation -= com_offsets[bi];
+                            (Some(bi), offset)
+                        }
+                        None => {
+                            let pose = graph.get_neighbor(&coll, l_pose).map(|p| *p);
+                            (None, pose.unwrap_or_default() * coll.offset)
+                        }
+                    };
+                    let (pose, reach) = match body {
+                        Some(bi) => (poses[bi] * offset, velocities[bi].linear.mag() * frame_dt),
+                        None => (offset, 0.0),
+                    };
+                    if !coll.aabb(&pose).padded(reach).overlaps(&bounds) {
+                        return None;
+                    }
+                    // sleeping bodies don't move, particles touching them wake them up
+                    let (inv_mass, inv_mom_inertia) = match body {
+                        Some(bi) if awake[bi] => (
+                            body_refs[bi].mass.inv(),
+                            body_refs[bi].moment_of_inertia.inv(),
+                        ),
+                        _ => (0.0, 0.0),
+                    };
+                    Some(particle_fluid::FluidObstacle {
+                        collider: *coll,
+                        offset,
+                        body,
+                        inv_mass,
+                        inv_mom_inertia,
+                    })
+                })
+                .collect(),
+        };
+
         // store latest contacts for use in the velocity step
         let mut contacts: Vec<ContactResult> = vec![ContactResult::Zero; coll_pairs.len()];
         // store contact forces for friction purposes
         let mut contact_lambdas: Vec<f64> = vec![0.0; coll_pairs.len()];
+        // latest contacts and total impulses during the whole frame for contact events
+        let mut frame_contacts: Vec<ContactResult> = vec![ContactResult::Zero; coll_pairs.len()];
+        let mut frame_impulses: Vec<f64> = vec![0.0; coll_pairs.len()];
+        // whether pairs are passing through a one-way collider,
+        // and whether they were still overlapping on the latest substep
+        let mut one_way_passing: Vec<bool> = coll_pairs
+            .iter()
+            .map(|colls| {
+                let key = contact_key(map_pair(colls, |c| graph::NodeRef::as_node(c, graph)));
+                self.one_way_passes.contains(&key)
+            })
+            .collect();
+        let mut one_way_overlaps: Vec<bool> = vec![false; coll_pairs.len()];
+        // friction and restitution for solid pairs, None if one of them isn't solid
+        let pair_materials: Vec<Option<PairMaterial>> = coll_pairs
+            .iter()
+            .map(|colls| match (colls[0].ty, colls[1].ty) {
+                (ColliderType::Solid(m0), ColliderType::Solid(m1)) => {
+                    Some(self.material_pair(&m0, &m1))
+                }
+                _ => None,
+            })
+            .collect();
+        // bullets and the pairs they could tunnel through,
+        // along with which side of the pair the bullet is on
+        let bullet_pairs: Vec<(usize, Vec<(usize, usize)>)> = (0..body_refs.len())
+            .filter(|&bi| body_refs[bi].bullet && body_refs[bi].sees_forces() && awake[bi])
+            .map(|bi| {
+                let pairs = izip!(&coll_pairs, &ctx_pairs)
+                    .enumerate()
+                    .filter(|(_, (colls, _))| colls[0].is_solid() && colls[1].is_solid())
+                    .filter_map(|(pi, (_, ctxs))| {
+                        ctxs.iter()
+                            .position(|ctx| matches!(ctx, ColliderContext::Body(b) if *b == bi))
+                            .map(|side| (pi, side))
+                    })
+                    .collect();
+                (bi, pairs)
+            })
+            .collect();
+        let mut ccd_skipped: Vec<m::Vec2> = vec![m::Vec2::zero(); body_refs.len()];
+        // pairs of a fluid and a solid collider on a dynamic body,
+        // along with which side of the pair the body is on
+        let fluid_pairs: Vec<(usize, usize)> = izip!(&coll_pairs, &ctx_pairs)
+            .enumerate()
+            .filter_map(|(pi, (colls, ctxs))| {
+                let side = (0..2).find(|&i| {
+                    colls[i].is_solid() && matches!(colls[1 - i].ty, ColliderType::Fluid(_))
+                })?;
+                match ctxs[side] {
+                    ColliderContext::Body(bi) if awake[bi] && body_refs[bi].sees_forces() => {
+                        Some((pi, side))
+                    }
+                    _ => None,
+                }
+            })
+            .collect();
+        let mut fluid_forces: Vec<(m::Vec2, f64)> = vec![(m::Vec2::zero(), 0.0); body_refs.len()];
 
         //
         // Actual physics step
         //
 
         for _substep in 0..self.substeps {
+            //
+            // buoyancy and drag from fluids
+            //
+
+            for force in &mut fluid_forces {
+                *force = (m::Vec2::zero(), 0.0);
+            }
+            for &(pi, side) in &fluid_pairs {
+                let colls = &coll_pairs[pi];
+                let (fluid, bi) = match (colls[1 - side].ty, &ctx_pairs[pi][side]) {
+                    (ColliderType::Fluid(fluid), ColliderContext::Body(bi)) => (fluid, *bi),
+                    _ => continue,
+                };
+                let fluid_pose = match ctx_pairs[pi][1 - side] {
+                    ColliderContext::Body(fi) => poses[fi],
+                    ColliderContext::Static(pose) => pose,
+                } * offset_pairs[pi][1 - side];
+                let (area, centroid) = match fluid::submerged_part(
+                    &colls[side],
+                    &(poses[bi] * offset_pairs[pi][side]),
+                    &colls[1 - side],
+                    &fluid_pose,
+                ) {
+                    Some(part) => part,
+                    None => continue,
+                };
+
+                // buoyancy is the opposite of the force the field would apply to the
+                // displaced fluid, which is at rest at the center of the submerged part.
+                // drag slows down the body where it's submerged
+                let submerged_mass = fluid.density * area;
+                let offset = centroid - poses[bi].translation;
+                let buoyancy = -forcefield.force_on(&forcefield::FieldBody {
+                    pose: &m::Pose::new(centroid, m::Rotor2::identity()),
+                    velocity: &Velocity::default(),
+                    mass: Mass::from(submerged_mass),
+                    gravity_scale: 1.0,
+                    collider: None,
+                });
+                let force = buoyancy
+                    - fluid.linear_drag * submerged_mass * velocities[bi].point_velocity(offset);
+                let torque = offset.wedge(force).xy
+                    - fluid.angular_drag
+                        * submerged_mass
+                        * colls[side].moment_of_inertia_coef()
+                        * velocities[bi].angular;
+                fluid_forces[bi].0 += force;
+                fluid_forces[bi].1 += torque;
+            }
+
             //
             // apply external forces and estimate post-step pose with explicit Euler step
             //
-            for (body, old_pose, pose, old_vel, vel, ext_accel) in izip!(
+            for (body, collider, is_awake, fluid_force, old_pose, pose, old_vel, vel, ext_accel) in izip!(
                 &body_refs,
+                &body_colliders,
+                &awake,
+                &fluid_forces,
                 &mut old_poses,
                 &mut poses,
                 &mut old_velocities,
                 &mut velocities,
                 &mut ext_f_accelerations
             ) {
+                if !is_awake {
+                    continue;
+                }
                 if let Mass::Finite { .. } = body.mass {
-                    // TODO: rename forcefield to accelerationfield or allow it to depend on mass
-                    let ff_accel = forcefield.value_at(pose.translation);
-                    vel.linear += ff_accel * dt;
-                    *ext_accel = ff_accel;
+                    let ff_force = forcefield.force_on(&forcefield::FieldBody {
+                        pose,
+                        velocity: vel,
+                        mass: body.mass,
+                        gravity_scale: body.gravity_scale,
+                        collider: collider.as_deref(),
+                    });
+                    let accel = body.mass.inv() * (ff_force + body.force + fluid_force.0);
+                    vel.linear += accel * dt;
+                    vel.angular +=
+                        body.moment_of_inertia.inv() * (body.torque + fluid_force.1) * dt;
+                    *ext_accel = accel;
 
                     // old_vel is velocity after external forces but before collisions
                     *old_vel = *vel;
@@ -284,6 +1094,90 @@ impl Physics {
                 }
             }
 
+            //
+            // continuous collision detection for bullets
+            //
+
+            for skipped in &mut ccd_skipped {
+                *skipped = m::Vec2::zero();
+            }
+            for (bi, pairs) in &bullet_pairs {
+                let bi = *bi;
+                let motion = poses[bi].translation - old_poses[bi].translation;
+                let motion_mag = motion.mag();
+                // advance in steps no longer than the radius of a circle that fits inside
+                // the bullet, which can't skip over anything
+                let max_step = pairs
+                    .iter()
+                    .map(|&(pi, side)| coll_pairs[pi][side].inner_radius())
+                    .fold(f64::INFINITY, f64::min);
+                if max_step <= 0.0 || motion_mag <= max_step {
+                    continue;
+                }
+
+                let other_pose = |pi: usize, side: usize| {
+                    let pose = match ctx_pairs[pi][1 - side] {
+                        ColliderContext::Body(oi) => poses[oi],
+                        ColliderContext::Static(pose) => pose,
+                    };
+                    pose * offset_pairs[pi][1 - side]
+                };
+                let overlaps_at = |pi: usize, side: usize, t: f64| {
+                    let colls = &coll_pairs[pi];
+                    let mut bullet_pose = poses[bi];
+                    bullet_pose.translation = old_poses[bi].translation + t * motion;
+                    !matches!(
+                        intersection_check(
+                            &(bullet_pose * offset_pairs[pi][side]),
+                            &colls[side],
+                            &other_pose(pi, side),
+                            &colls[1 - side],
+                        ),
+                        ContactResult::Zero
+                    )
+                };
+                // things the bullet is already touching, passing through,
+                // or approaching from the open side of are handled as usual
+                let candidates: Vec<(usize, usize)> = pairs
+                    .iter()
+                    .copied()
+                    .filter(|&(pi, side)| !one_way_passing[pi] && !overlaps_at(pi, side, 0.0))
+                    .filter(|&(pi, side)| match coll_pairs[pi][1 - side].one_way {
+                        Some(dir) => motion.dot(other_pose(pi, side).rotation * *dir) < 0.0,
+                        None => true,
+                    })
+                    .collect();
+                let any_overlap = |t: f64| {
+                    candidates
+                        .iter()
+                        .any(|&(pi, side)| overlaps_at(pi, side, t))
+                };
+
+                let step_count = (motion_mag / max_step).ceil() as usize;
+                let first_hit = (1..=step_count)
+                    .map(|i| i as f64 / step_count as f64)
+                    .find(|&t| any_overlap(t));
+                if let Some(mut hit_t) = first_hit {
+                    // narrow down the time of impact so the bullet only overlaps a little,
+                    // then let the contact solver handle it
+                    let mut free_t = hit_t - 1.0 / step_count as f64;
+                    while (hit_t - free_t) * motion_mag > 0.1 * max_step {
+                        let mid = (free_t + hit_t) / 2.0;
+                        if any_overlap(mid) {
+                            hit_t = mid;
+                        } else {
+                            free_t = mid;
+                        }
+                    }
+                    // move the bullet to the time of impact as if it started the substep there,
+                    // remembering the motion so velocity doesn't get lost
+                    // when it's updated from the pose difference
+                    poses[bi].translation = old_poses[bi].translation + hit_t * motion;
+                    old_poses[bi].translation = poses[bi].translation;
+                    ccd_skipped[bi] = motion;
+                }
+            }
+
             //
             // Nonlinear Gauss-Seidel constraint solve step
             //
@@ -292,85 +1186,145 @@ impl Physics {
             // User-defined constraints
             //
 
-            for (constraint, pair) in izip!(self.user_constraints.values(), &constraint_body_pairs)
-            {
+            for (constraint, pair, lambda_sum, angular_lambda_sum) in izip!(
+                &constraints,
+                &constraint_body_pairs,
+                &mut constraint_lambdas,
+                &mut constraint_angular_lambdas
+            ) {
+                // constrained bodies are in the same island, so checking one is enough
+                if !awake[pair.0] {
+                    continue;
+                }
                 let inv_masses = map_semi_pair(*pair, |b| body_refs[*b].mass.inv(), 0.0);
                 let inv_mom_inertias =
                     map_semi_pair(*pair, |b| body_refs[*b].moment_of_inertia.inv(), 0.0);
 
+                let compliance = constraint.compliance * inv_dt_sq;
+                let points_worldspace = |poses: &[m::Pose]| {
+                    [
+                        poses[pair.0] * constraint.offsets[0],
+                        pair.1
+                            .map(|p1| poses[p1] * constraint.offsets[1])
+                            .unwrap_or(constraint.offsets[1]),
+                    ]
+                };
+
                 match constraint.ty {
                     ConstraintType::Distance { distance } => {
-                        let offsets_worldspace = [
-                            poses[pair.0] * constraint.offsets[0],
-                            pair.1
-                                .map(|p1| poses[p1] * constraint.offsets[1])
-                                .unwrap_or(constraint.offsets[1]),
-                        ];
-                        let actual_dist = offsets_worldspace[1] - offsets_worldspace[0];
+                        // the length of a motorized distance constraint is up to the motor,
+                        // except for one-sided limits
+                        if matches!(
+                            constraint.motor,
+                            Some(Motor::LinearVelocity { .. }) | Some(Motor::Length { .. })
+                        ) && matches!(constraint.limit, ConstraintLimit::Eq)
+                        {
+                            continue;
+                        }
+                        let points = points_worldspace(&poses);
+                        let actual_dist = points[1] - points[0];
                         let actual_dist_mag = actual_dist.mag();
                         let error = distance - actual_dist_mag;
 
-                        if match constraint.limit {
-                            ConstraintLimit::Eq => true,
-                            ConstraintLimit::Lt if error < 0.0 => true,
-                            ConstraintLimit::Gt if error > 0.0 => true,
-                            _ => false,
-                        } {
+                        if constraint.limit.applies_to(error) {
                             let dir = if actual_dist_mag != 0.0 {
                                 actual_dist / actual_dist_mag
                             } else {
                                 m::Vec2::unit_y()
                             };
+                            *lambda_sum += apply_positional_correction(
+                                &mut poses,
+                                *pair,
+                                constraint.offsets,
+                                inv_masses,
+                                inv_mom_inertias,
+                                error * dir,
+                                compliance,
+                            );
+                        }
+                    }
+                    ConstraintType::Revolute { angle_limits } => {
+                        // keep the points together like an attachment
+                        let points = points_worldspace(&poses);
+                        *lambda_sum += apply_positional_correction(
+                            &mut poses,
+                            *pair,
+                            constraint.offsets,
+                            inv_masses,
+                            inv_mom_inertias,
+                            points[0] - points[1],
+                            compliance,
+                        );
 
-                            match pair.1 {
-                                Some(p1) => {
-                                    let pair = [pair.0, p1];
-                                    let offsets_rotated = map_pair(&[0, 1], |i| {
-                                        poses[pair[*i]].rotation * constraint.offsets[*i]
-                                    });
-                                    let offsets_wedge_dir =
-                                        map_pair(&[0, 1], |i| offsets_rotated[*i].wedge(dir).xy);
-                                    let eff_inv_masses = map_pair(&[0, 1], |i| {
-                                        inv_masses[*i]
-                                            + (offsets_wedge_dir[*i].powi(2) * inv_mom_inertias[*i])
-                                    });
-
-                                    let lambda = -error
-                                        / (eff_inv_masses[0]
-                                            + eff_inv_masses[1]
-                                            + constraint.compliance * inv_dt_sq);
-
-                                    poses[pair[0]].append_translation(inv_masses[0] * lambda * dir);
-                                    poses[pair[0]].prepend_rotation(
-                                        Angle::Rad(
-                                            inv_mom_inertias[0] * lambda * offsets_wedge_dir[0],
-                                        )
-                                        .into(),
-                                    );
-                                    poses[pair[1]]
-                                        .append_translation(-inv_masses[1] * lambda * dir);
-                                    poses[pair[1]].prepend_rotation(
-                                        Angle::Rad(
-                                            -inv_mom_inertias[1] * lambda * offsets_wedge_dir[1],
-                                        )
-                                        .into(),
-                                    );
-                                }
-                                None => {
-                                    // this is repetitive but kind of hard to abstract :thinking:
-                                    let offset_rotated =
-                                        poses[pair.0].rotation * constraint.offsets[0];
-                                    let offset_wedge_dir = offset_rotated.wedge(dir).xy;
-                                    let eff_inv_mass = inv_masses[0]
-                                        + offset_wedge_dir.powi(2) * inv_mom_inertias[0];
-
-                                    let lambda =
-                                        -error / (eff_inv_mass + constraint.compliance * inv_dt_sq);
-
-                                    poses[pair.0].append_translation(inv_masses[0] * lambda * dir);
-                                    poses[pair.0].prepend_rotation(
-                                        Angle::Rad(inv_mom_inertias[0] * lambda * offset_wedge_dir)
-                                            .into(),
+                        if let Some([min_angle, max_angle]) = angle_limits {
+                            let angle = wrap_angle(
+                                relative_angle(&poses, *pair)
+                                    - constraint.reference_angle.unwrap_or(0.0),
+                            );
+                            let correction = if angle < min_angle {
+                                min_angle - angle
+                            } else if angle > max_angle {
+                                max_angle - angle
+                            } else {
+                                0.0
+                            };
+                            *angular_lambda_sum += apply_angular_correction(
+                                &mut poses,
+                                *pair,
+                                inv_mom_inertias,
+                                correction,
+                            );
+                        }
+                    }
+                    ConstraintType::Prismatic {
+                        axis,
+                        translation_limits,
+                    } => {
+                        // lock rotation first so the axis is where it should be
+                        // when correcting positions
+                        let angle = wrap_angle(
+                            relative_angle(&poses, *pair)
+                                - constraint.reference_angle.unwrap_or(0.0),
+                        );
+                        *angular_lambda_sum +=
+                            apply_angular_correction(&mut poses, *pair, inv_mom_inertias, -angle);
+
+                        let axis = pair
+                            .1
+                            .map(|p1| *(poses[p1].rotation * axis))
+                            .unwrap_or(*axis);
+                        let normal = m::Vec2::new(-axis.y, axis.x);
+
+                        // keep the owner's point on the axis
+                        let points = points_worldspace(&poses);
+                        let offset = points[0] - points[1];
+                        *lambda_sum += apply_positional_correction(
+                            &mut poses,
+                            *pair,
+                            constraint.offsets,
+                            inv_masses,
+                            inv_mom_inertias,
+                            offset.dot(normal) * normal,
+                            compliance,
+                        );
+
+                        if let Some([min_dist, max_dist]) = translation_limits {
+                            let points = points_worldspace(&poses);
+                            let dist = (points[0] - points[1]).dot(axis);
+                            for &(limit_dist, limit) in &[
+                                (min_dist, ConstraintLimit::Gt),
+                                (max_dist, ConstraintLimit::Lt),
+                            ] {
+                                let error = limit_dist - dist;
+                                if limit.applies_to(error) {
+                                    *lambda_sum += apply_positional_correction(
+                                        &mut poses,
+                                        *pair,
+                                        constraint.offsets,
+                                        inv_masses,
+                                        inv_mom_inertias,
+                                        -error * axis,
+                                        compliance,
                                     );
                                 }
                             }
@@ -380,44 +1334,147 @@ impl Physics {
             }
 
             //
-            // Contacts
+            // Area constraints
             //
 
-            for (colls, ctxs, contact, lambda_n) in
-                izip!(&coll_pairs, &ctx_pairs, &mut contacts, &mut contact_lambdas)
+            for (constraint, bodies) in
+                izip!(self.area_constraints.values(), &area_constraint_bodies)
             {
-                if !match ctxs[0] {
-                    ColliderContext::Body(bi) => body_refs[bi].sees_forces(),
-                    ColliderContext::Static(_) => false,
-                } && !match ctxs[1] {
-                    ColliderContext::Body(bi) => body_refs[bi].sees_forces(),
-                    ColliderContext::Static(_) => false,
-                } {
-                    // both bodies are kinematic or static, skip this pair
-                    *contact = ContactResult::Zero;
+                if bodies.len() < 3 || !awake[bodies[0]] {
+                    continue;
+                }
+                let count = bodies.len();
+                let points: Vec<m::Vec2> = bodies.iter().map(|bi| poses[*bi].translation).collect();
+                let error = softbody::polygon_area(&points) - constraint.rest_area;
+                // moving a point outward perpendicular to the line between its neighbors
+                // grows the area the fastest
+                let gradients: Vec<m::Vec2> = (0..count)
+                    .map(|i| {
+                        0.5 * m::right_normal(
+                            points[(i + 1) % count] - points[(i + count - 1) % count],
+                        )
+                    })
+                    .collect();
+                let inv_masses: Vec<f64> =
+                    bodies.iter().map(|bi| body_refs[*bi].mass.inv()).collect();
+                let weight_sum: f64 = izip!(&gradients, &inv_masses)
+                    .map(|(grad, inv_mass)| inv_mass * grad.mag_sq())
+                    .sum();
+                let denominator = weight_sum + constraint.compliance * inv_dt_sq;
+                if denominator == 0.0 {
                     continue;
                 }
+                let lambda = -error / denominator;
+                for (bi, grad, inv_mass) in izip!(bodies, &gradients, &inv_masses) {
+                    poses[*bi].translation += lambda * inv_mass * *grad;
+                }
+            }
 
-                // check for collision
-                *contact = {
-                    // poses for bodies are in our temporary buffer, so we need to get the
-                    // neighboring body to find the pose (if the collider belongs to a body,
-                    // otherwise we get the pose from the graph)
-                    let poses =
-                        map_pair(colls, |c| match graph.get_neighbor_unchecked(c, l_body) {
-                            Some(b) => poses[node_ref_map[b.pos().item_idx]],
-                            None => match graph.get_neighbor_unchecked(c, l_pose) {
-                                Some(pose) => *pose,
-                                None => m::Pose::default(),
-                            },
-                        });
-                    intersection_check(&poses[0], &*colls[0], &poses[1], &*colls[1])
-                };
+            //
+            // Contacts
+            //
+
+            for (
+                colls,
+                ctxs,
+                offsets,
+                material,
+                contact,
+                lambda_n,
+                frame_contact,
+                frame_impulse,
+                passing,
+                overlaps,
+            ) in izip!(
+                &coll_pairs,
+                &ctx_pairs,
+                &offset_pairs,
+                &pair_materials,
+                &mut contacts,
+                &mut contact_lambdas,
+                &mut frame_contacts,
+                &mut frame_impulses,
+                &mut one_way_passing,
+                &mut one_way_overlaps
+            ) {
+                if colls[0].is_solid()
+                    && colls[1].is_solid()
+                    && !match ctxs[0] {
+                        ColliderContext::Body(bi) => body_refs[bi].sees_forces(),
+                        ColliderContext::Static(_) => false,
+                    }
+                    && !match ctxs[1] {
+                        ColliderContext::Body(bi) => body_refs[bi].sees_forces(),
+                        ColliderContext::Static(_) => false,
+                    }
+                {
+                    // both bodies are kinematic or static, skip this pair
+                    *contact = ContactResult::Zero;
+                    continue;
+                }
 
-                let materials = match (colls[0].ty, colls[1].ty) {
-                    (ColliderType::Solid(m0), ColliderType::Solid(m1)) => [m0, m1],
+                // poses for bodies are in our temporary buffer,
+                // static colliders have theirs in the context
+                let coll_poses = map_pair(&[0, 1], |i| {
+                    let pose = match ctxs[*i] {
+                        ColliderContext::Body(bi) => poses[bi],
+                        ColliderContext::Static(pose) => pose,
+                    };
+                    pose * offsets[*i]
+                });
+                // check for collision.
+                // contacts come out relative to the colliders,
+                // but the solver wants them relative to the bodies
+                *contact =
+                    intersection_check(&coll_poses[0], &*colls[0], &coll_poses[1], &*colls[1]).map(
+                        |c| Contact {
+                            offsets: [offsets[0] * c.offsets[0], offsets[1] * c.offsets[1]],
+                            ..c
+                        },
+                    );
+
+                // one-way colliders only block things that come at them from the right side
+                // while moving towards them. anything else passes through
+                // until the colliders have separated
+                if !*passing {
+                    let one_way = (0..2).find_map(|i| colls[i].one_way.map(|dir| (i, dir)));
+                    if let (Some((i, dir)), Some(first)) = (one_way, contact.iter().next()) {
+                        let dir = coll_poses[i].rotation * *dir;
+                        // contact normal points from the first collider to the second
+                        let normal = if i == 0 {
+                            *first.normal
+                        } else {
+                            -*first.normal
+                        };
+                        let linear_vel = |ctx: &ColliderContext| match ctx {
+                            ColliderContext::Body(bi) => velocities[*bi].linear,
+                            ColliderContext::Static(_) => m::Vec2::zero(),
+                        };
+                        let rel_vel = linear_vel(&ctxs[1 - i]) - linear_vel(&ctxs[i]);
+                        let other_dropping =
+                            matches!(ctxs[1 - i], ColliderContext::Body(bi) if dropping[bi]);
+                        // a normal more than 60 degrees off means the other collider
+                        // is hitting the side or is already partway through
+                        *passing =
+                            normal.dot(dir) < 0.5 || rel_vel.dot(dir) > 0.0 || other_dropping;
+                    }
+                }
+                if *passing {
+                    *overlaps = !matches!(contact, ContactResult::Zero);
+                    // once they've separated they collide normally again,
+                    // even if they come back together later in the same frame
+                    *passing = *overlaps;
+                    *contact = ContactResult::Zero;
+                    continue;
+                }
+                if !matches!(contact, ContactResult::Zero) {
+                    *frame_contact = *contact;
+                }
+
+                let material = match material {
+                    Some(material) => material,
                     // one of the colliders was a trigger, no physics response
-                    _ => {
+                    None => {
                         continue;
                     }
                 };
@@ -482,6 +1539,7 @@ impl Physics {
                     }
 
                     *lambda_n = -depth / (vars[0].eff_inv_mass_n + vars[1].eff_inv_mass_n);
+                    *frame_impulse -= *lambda_n * inv_dt;
 
                     if let ColliderContext::Body(bi) = ctxs[0] {
                         let im = body_refs[bi].mass.inv();
@@ -507,13 +1565,13 @@ impl Physics {
                         - (vars[1].offset_worldspace - vars[1].offset_worldspace_old);
                     let motion_along_tan = offset_diff_motion.dot(tangent);
 
-                    let friction_coef = materials[0].static_friction_with(&materials[1]);
+                    let friction_coef = material.static_friction_coef;
                     let max_coulomb_dx = *lambda_n * friction_coef;
 
                     let lambda_t =
                         -motion_along_tan / (vars[0].eff_inv_mass_tan + vars[1].eff_inv_mass_tan);
 
-                    if lambda_t < max_coulomb_dx {
+                    if lambda_t.abs() < max_coulomb_dx {
                         if let ColliderContext::Body(bi) = ctxs[0] {
                             let im = body_refs[bi].mass.inv();
                             let imi = body_refs[bi].moment_of_inertia.inv();
@@ -534,12 +1592,34 @@ impl Physics {
                 }
             }
 
+            //
+            // Particle fluids
+            //
+
+            for fluid in self.particle_fluids.values_mut() {
+                fluid.predict(dt, forcefield);
+                fluid.solve_density();
+                let touched = fluid.collide(&fluid_obstacles, &mut poses);
+                fluid.update_velocities(dt);
+                for (obstacle, touched) in izip!(&fluid_obstacles, touched) {
+                    match obstacle.body {
+                        Some(bi) if touched && !awake[bi] => {
+                            self.bodies_to_wake
+                                .push(graph::NodeRef::as_node(&body_refs[bi], graph));
+                        }
+                        _ => (),
+                    }
+                }
+            }
+
             //
             // update velocities from pose differences
             //
 
-            for (old_pose, pose, vel) in izip!(&old_poses, &poses, &mut velocities) {
-                vel.linear = (pose.translation - old_pose.translation) * inv_dt;
+            for (old_pose, pose, skipped, vel) in
+                izip!(&old_poses, &poses, &ccd_skipped, &mut velocities)
+            {
+                vel.linear = (pose.translation - old_pose.translation + *skipped) * inv_dt;
                 // I'm sure there are more efficient ways to handle the angle but this'll do
                 vel.angular =
                     Angle::from(pose.rotation * old_pose.rotation.reversed()).rad() * inv_dt;
@@ -549,13 +1629,17 @@ impl Physics {
             // velocity step for dynamic friction and restitution on contacts + damping on other constraints
             //
 
-            for (colls, ctxs, contact, lambda_n) in
-                izip!(&coll_pairs, &ctx_pairs, &contacts, &contact_lambdas)
-            {
-                let materials = match (colls[0].ty, colls[1].ty) {
-                    (ColliderType::Solid(m0), ColliderType::Solid(m1)) => [m0, m1],
+            for (material, ctxs, contact, lambda_n, frame_impulse) in izip!(
+                &pair_materials,
+                &ctx_pairs,
+                &contacts,
+                &contact_lambdas,
+                &mut frame_impulses
+            ) {
+                let material = match material {
+                    Some(material) => material,
                     // one of the colliders was a trigger, no physics response
-                    _ => {
+                    None => {
                         continue;
                     }
                 };
@@ -605,7 +1689,7 @@ impl Physics {
                         // don't bounce if the normal velocity is very small to avoid jitter
                         0.0
                     } else {
-                        materials[0].restitution_with(&materials[1])
+                        material.restitution_coef
                     };
                     let delta_normal_vel = -normal_vel - restitution_coef * old_normal_vel.max(0.0);
 
@@ -613,7 +1697,7 @@ impl Physics {
 
                     let tangent = m::left_normal(*contact.normal);
                     let tangent_vel = relative_vel_at_p.dot(tangent);
-                    let friction_coef = materials[0].dynamic_friction_with(&materials[1]);
+                    let friction_coef = material.dynamic_friction_coef;
                     let max_coulomb_dv = inv_dt * lambda_n * friction_coef;
                     let delta_tan_vel =
                         tangent_vel.abs().min(max_coulomb_dv.abs()) * -tangent_vel.signum();
@@ -635,6 +1719,7 @@ impl Physics {
                             + (offsets_wedge_dv[*i].powi(2) * vars[*i].inv_mom_inertia)
                     });
                     let impulse_mag = vel_update_mag / (eff_inv_masses[0] + eff_inv_masses[1]);
+                    *frame_impulse -= impulse_mag * vel_update_dir.dot(*contact.normal);
 
                     if let ColliderContext::Body(bi) = ctxs[0] {
                         velocities[bi].linear += vars[0].inv_mass * impulse_mag * vel_update_dir;
@@ -651,8 +1736,11 @@ impl Physics {
 
             // damping
 
-            for (constraint, pair) in izip!(self.user_constraints.values(), &constraint_body_pairs)
-            {
+            for (constraint, pair) in izip!(&constraints, &constraint_body_pairs) {
+                // constrained bodies are in the same island, so checking one is enough
+                if !awake[pair.0] {
+                    continue;
+                }
                 let inv_masses = map_semi_pair(*pair, |b| body_refs[*b].mass.inv(), 0.0);
                 let inv_mom_inertias =
                     map_semi_pair(*pair, |b| body_refs[*b].moment_of_inertia.inv(), 0.0);
@@ -667,7 +1755,12 @@ impl Physics {
                         let relative_vel = velocities[pair[0]].point_velocity(offsets_rotated[0])
                             - velocities[pair[1]].point_velocity(offsets_rotated[1]);
                         let relative_vel_mag = relative_vel.mag();
-                        let dir = relative_vel / relative_vel_mag;
+                        // zero velocity would give us a NaN direction
+                        let dir = if relative_vel_mag != 0.0 {
+                            relative_vel / relative_vel_mag
+                        } else {
+                            m::Vec2::zero()
+                        };
 
                         let offsets_wedge_dir =
                             map_pair(&[0, 1], |i| offsets_rotated[*i].wedge(dir).xy);
@@ -704,7 +1797,11 @@ impl Physics {
 
                         let point_vel = velocities[pair.0].point_velocity(offset_rotated);
                         let point_vel_mag = point_vel.mag();
-                        let dir = point_vel / point_vel_mag;
+                        let dir = if point_vel_mag != 0.0 {
+                            point_vel / point_vel_mag
+                        } else {
+                            m::Vec2::zero()
+                        };
 
                         let offset_wedge_dir = offset_rotated.wedge(dir).xy;
                         let eff_inv_mass =
@@ -728,29 +1825,374 @@ impl Physics {
                 }
             }
 
-            //
-            // Event gathering
-            //
+            // motors
 
-            for (colls, contact) in izip!(&coll_pairs, &contacts) {
-                match contact {
-                    ContactResult::Zero => (),
-                    _ => {
-                        if let Some(mut sink) =
-                            graph.get_neighbor_mut_unchecked(&colls[0], l_evt_sink)
-                        {
-                            sink.push(Event::Contact(ContactEvent {
-                                other_collider: graph::NodeRef::as_node(&colls[1], graph),
-                            }));
+            for (constraint, pair) in izip!(&constraints, &constraint_body_pairs) {
+                if !awake[pair.0] {
+                    continue;
+                }
+                let motor = match constraint.motor {
+                    Some(motor) if motor.can_drive(&constraint.ty) => motor,
+                    _ => continue,
+                };
+                let inv_masses = map_semi_pair(*pair, |b| body_refs[*b].mass.inv(), 0.0);
+                let inv_mom_inertias =
+                    map_semi_pair(*pair, |b| body_refs[*b].moment_of_inertia.inv(), 0.0);
+
+                let max_force = match motor {
+                    Motor::AngularVelocity {
+                        velocity,
+                        max_torque,
+                    } => {
+                        let inv_mom_inertia_sum = inv_mom_inertias[0] + inv_mom_inertias[1];
+                        if inv_mom_inertia_sum <= 0.0 {
+                            continue;
                         }
-                        if let Some(mut sink) =
-                            graph.get_neighbor_mut_unchecked(&colls[1], l_evt_sink)
-                        {
-                            sink.push(Event::Contact(ContactEvent {
-                                other_collider: graph::NodeRef::as_node(&colls[0], graph),
-                            }));
+                        let rel_angular_vel = velocities[pair.0].angular
+                            - pair.1.map(|p1| velocities[p1].angular).unwrap_or(0.0);
+                        let max_impulse = max_torque * dt;
+                        let impulse = ((velocity - rel_angular_vel) / inv_mom_inertia_sum)
+                            .max(-max_impulse)
+                            .min(max_impulse);
+                        velocities[pair.0].angular += inv_mom_inertias[0] * impulse;
+                        if let Some(p1) = pair.1 {
+                            velocities[p1].angular -= inv_mom_inertias[1] * impulse;
+                        }
+                        continue;
+                    }
+                    Motor::LinearVelocity { max_force, .. } | Motor::Length { max_force, .. } => {
+                        max_force
+                    }
+                };
+
+                let offsets_rotated = [
+                    poses[pair.0].rotation * constraint.offsets[0],
+                    pair.1
+                        .map(|p1| poses[p1].rotation * constraint.offsets[1])
+                        .unwrap_or_else(m::Vec2::zero),
+                ];
+                let points = [
+                    poses[pair.0].translation + offsets_rotated[0],
+                    pair.1
+                        .map(|p1| poses[p1].translation + offsets_rotated[1])
+                        .unwrap_or(constraint.offsets[1]),
+                ];
+                // direction in which the length grows when the owner moves relative to the target
+                let (dir, length) = match constraint.ty {
+                    ConstraintType::Distance { .. } => {
+                        let dist = points[0] - points[1];
+                        let dist_mag = dist.mag();
+                        if dist_mag == 0.0 {
+                            continue;
                         }
+                        (dist / dist_mag, dist_mag)
+                    }
+                    ConstraintType::Prismatic { axis, .. } => {
+                        let axis = pair
+                            .1
+                            .map(|p1| *(poses[p1].rotation * axis))
+                            .unwrap_or(*axis);
+                        (axis, (points[0] - points[1]).dot(axis))
                     }
+                    ConstraintType::Revolute { .. } => continue,
+                };
+                let offsets_wedge_dir = map_pair(&offsets_rotated, |o| o.wedge(dir).xy);
+                let eff_inv_masses = map_pair(&[0, 1], |i| {
+                    inv_masses[*i] + offsets_wedge_dir[*i].powi(2) * inv_mom_inertias[*i]
+                });
+                let eff_inv_mass = eff_inv_masses[0] + eff_inv_masses[1];
+
+                let target_vel = match motor {
+                    Motor::LinearVelocity { velocity, .. } => velocity,
+                    Motor::Length {
+                        length: target_length,
+                        ..
+                    } => {
+                        // approach the target no faster than the motor can stop at it,
+                        // otherwise it overshoots and oscillates around the target forever
+                        let error = target_length - length;
+                        let stopping_speed = (2.0 * max_force * eff_inv_mass * error.abs()).sqrt();
+                        error.signum() * (error.abs() * inv_dt).min(stopping_speed)
+                    }
+                    Motor::AngularVelocity { .. } => continue,
+                };
+
+                let rel_vel = (velocities[pair.0].point_velocity(offsets_rotated[0])
+                    - pair
+                        .1
+                        .map(|p1| velocities[p1].point_velocity(offsets_rotated[1]))
+                        .unwrap_or_else(m::Vec2::zero))
+                .dot(dir);
+                let max_impulse = max_force * dt;
+                let impulse = ((target_vel - rel_vel) / eff_inv_mass)
+                    .max(-max_impulse)
+                    .min(max_impulse);
+
+                velocities[pair.0].linear += inv_masses[0] * impulse * dir;
+                velocities[pair.0].angular += inv_mom_inertias[0] * impulse * offsets_wedge_dir[0];
+                if let Some(p1) = pair.1 {
+                    velocities[p1].linear -= inv_masses[1] * impulse * dir;
+                    velocities[p1].angular -= inv_mom_inertias[1] * impulse * offsets_wedge_dir[1];
+                }
+            }
+        }
+
+        //
+        // Contact events
+        //
+
+        let pose_of = |ctx: &ColliderContext| match ctx {
+            ColliderContext::Body(bi) => poses[*bi],
+            ColliderContext::Static(pose) => *pose,
+        };
+        let mut prev_contacts = std::mem::take(&mut self.contacts);
+        // sleeping pairs weren't checked, they stay the way they were
+        for (colls, _) in &sleeping_pairs {
+            if !colls[0].is_solid() || !colls[1].is_solid() {
+                continue;
+            }
+            let key = contact_key(map_pair(colls, |c| graph::NodeRef::as_node(c, graph)));
+            if let Some((key, evt)) = prev_contacts.remove_entry(&key) {
+                self.contacts.insert(key, evt);
+            }
+        }
+        for (colls, ctxs, contact, impulse) in
+            izip!(&coll_pairs, &ctx_pairs, &frame_contacts, &frame_impulses)
+        {
+            if !colls[0].is_solid() || !colls[1].is_solid() {
+                continue;
+            }
+            let normal = match contact.iter().next() {
+                Some(first) => first.normal,
+                None => continue,
+            };
+            let ctx_poses = map_pair(ctxs, pose_of);
+            let mut points = contact
+                .iter()
+                .map(|c| (ctx_poses[0] * c.offsets[0] + ctx_poses[1] * c.offsets[1]) / 2.0);
+            let nodes = map_pair(colls, |c| graph::NodeRef::as_node(c, graph));
+            let evt = ContactEvent {
+                collider: nodes[0],
+                other_collider: nodes[1],
+                normal,
+                points: [points.next(), points.next()],
+                impulse: *impulse,
+            };
+            let key = contact_key(nodes);
+            let evt = if key[0] == nodes[0] {
+                evt
+            } else {
+                evt.flipped()
+            };
+
+            let wrap_evt = if prev_contacts.remove(&key).is_none() {
+                Some(Event::ContactBegan as fn(ContactEvent) -> Event)
+            } else if self.persisted_contact_events {
+                Some(Event::ContactPersisted as fn(ContactEvent) -> Event)
+            } else {
+                None
+            };
+            if let Some(wrap_evt) = wrap_evt {
+                push_event(graph, l_body, l_evt_sink, key[0], wrap_evt(evt));
+                push_event(graph, l_body, l_evt_sink, key[1], wrap_evt(evt.flipped()));
+            }
+            self.contacts.insert(key, evt);
+        }
+        // whatever is left wasn't touching anymore.
+        // one of the colliders may have been deleted from under a sleeping body,
+        // so wake up whatever's left so it doesn't float in the air
+        for (key, evt) in prev_contacts {
+            for coll in &key {
+                if coll.check(graph).is_none() {
+                    continue;
+                }
+                if let Some(body) = graph.get_neighbor_unchecked(coll, l_body) {
+                    self.bodies_to_wake
+                        .push(graph::NodeRef::as_node(&body, graph));
+                }
+            }
+            let evt = ContactEvent {
+                impulse: 0.0,
+                ..evt
+            };
+            push_event(graph, l_body, l_evt_sink, key[0], Event::ContactEnded(evt));
+            push_event(
+                graph,
+                l_body,
+                l_evt_sink,
+                key[1],
+                Event::ContactEnded(evt.flipped()),
+            );
+        }
+
+        //
+        // One-way pass-throughs
+        //
+
+        let mut prev_passes = std::mem::take(&mut self.one_way_passes);
+        for (colls, _) in &sleeping_pairs {
+            let key = contact_key(map_pair(colls, |c| graph::NodeRef::as_node(c, graph)));
+            if prev_passes.remove(&key) {
+                self.one_way_passes.insert(key);
+            }
+        }
+        for (colls, passing, overlaps) in izip!(&coll_pairs, &one_way_passing, &one_way_overlaps) {
+            if *passing && *overlaps {
+                self.one_way_passes.insert(contact_key(map_pair(colls, |c| {
+                    graph::NodeRef::as_node(c, graph)
+                })));
+            }
+        }
+
+        //
+        // Trigger events
+        //
+
+        let trigger_key = |colls: &[graph::NodeRef<Collider>; 2]| {
+            let nodes = map_pair(colls, |c| graph::NodeRef::as_node(c, graph));
+            if colls[0].is_solid() {
+                [nodes[1], nodes[0]]
+            } else {
+                nodes
+            }
+        };
+        let mut prev_overlaps = std::mem::take(&mut self.trigger_overlaps);
+        for (colls, _) in &sleeping_pairs {
+            if colls[0].is_solid() && colls[1].is_solid() {
+                continue;
+            }
+            if let Some((key, evt)) = prev_overlaps.remove_entry(&trigger_key(colls)) {
+                self.trigger_overlaps.insert(key, evt);
+            }
+        }
+        for (colls, contact) in izip!(&coll_pairs, &frame_contacts) {
+            if (colls[0].is_solid() && colls[1].is_solid())
+                || matches!(contact, ContactResult::Zero)
+            {
+                continue;
+            }
+            let key = trigger_key(colls);
+            let evt = match prev_overlaps.remove(&key) {
+                Some(evt) => evt,
+                None => {
+                    let evt = TriggerEvent {
+                        trigger: key[0],
+                        other: key[1],
+                        other_body: graph
+                            .get_neighbor_unchecked(&key[1], l_body)
+                            .map(|body| graph::NodeRef::as_node(&body, graph)),
+                    };
+                    push_event(graph, l_body, l_evt_sink, key[0], Event::TriggerEnter(evt));
+                    push_event(graph, l_body, l_evt_sink, key[1], Event::TriggerEnter(evt));
+                    evt
+                }
+            };
+            self.trigger_overlaps.insert(key, evt);
+        }
+        for (key, evt) in prev_overlaps {
+            push_event(graph, l_body, l_evt_sink, key[0], Event::TriggerExit(evt));
+            push_event(graph, l_body, l_evt_sink, key[1], Event::TriggerExit(evt));
+        }
+
+        //
+        // Broken constraints
+        //
+
+        // lambda is an impulse times the timestep, so these are average forces over the frame.
+        // (overload ratio, constraint index, force, torque) for every constraint over its limit
+        let lambda_to_force = inv_dt_sq / self.substeps as f64;
+        let overloaded = izip!(
+            &constraints,
+            &constraint_lambdas,
+            &constraint_angular_lambdas
+        )
+        .enumerate()
+        .filter_map(|(ci, (constraint, lambda_sum, angular_lambda_sum))| {
+            let force = lambda_sum * lambda_to_force;
+            let torque = angular_lambda_sum * lambda_to_force;
+            let overload = |value: f64, limit: Option<f64>| match limit {
+                Some(limit) if value > limit => value / limit,
+                _ => 0.0,
+            };
+            let overload = overload(force, constraint.break_force)
+                .max(overload(torque, constraint.break_torque));
+            if overload > 0.0 {
+                Some((overload, ci, force, torque))
+            } else {
+                None
+            }
+        });
+        let breaks: Vec<(f64, usize, f64, f64)> = if self.one_break_per_island {
+            // keep only the most overloaded constraint for each island root
+            let mut island_breaks: Vec<Option<(f64, usize, f64, f64)>> =
+                vec![None; body_refs.len()];
+            for brk in overloaded {
+                let island_break = &mut island_breaks[islands.root(constraint_body_pairs[brk.1].0)];
+                match island_break {
+                    Some(worst) if worst.0 >= brk.0 => {}
+                    _ => *island_break = Some(brk),
+                }
+            }
+            island_breaks.into_iter().flatten().collect()
+        } else {
+            overloaded.collect()
+        };
+        let constraint_handles: Vec<ConstraintHandle> = self.user_constraints.keys().collect();
+        for (_, ci, force, torque) in breaks {
+            let handle = constraint_handles[ci];
+            let constraint = match self.remove_constraint(handle) {
+                Some(c) => c,
+                None => continue,
+            };
+            // the owner was checked to exist when setting up constraints
+            if let Some(mut sink) = graph.get_neighbor_mut_unchecked(&constraint.owner, l_evt_sink)
+            {
+                sink.push(Event::ConstraintBroken(ConstraintBrokenEvent {
+                    handle,
+                    constraint,
+                    force,
+                    torque,
+                }));
+            }
+        }
+
+        // back from the center of mass to the poses the user sees
+        for (pose, com) in izip!(&mut poses, &com_offsets) {
+            pose.translation -= pose.rotation * *com;
+        }
+
+        //
+        // put islands to sleep if all their bodies have been still for long enough
+        //
+
+        if self.sleep.enabled {
+            let lin_threshold_sq = self.sleep.linear_threshold * self.sleep.linear_threshold;
+            let mut island_still = vec![true; body_refs.len()];
+            for (bi, (body, state, vel)) in
+                izip!(&body_refs, &mut sleep_states, &velocities).enumerate()
+            {
+                if !body.sees_forces() {
+                    continue;
+                }
+                if let SleepState::Awake { still_time } = state {
+                    if vel.linear.mag_sq() < lin_threshold_sq
+                        && vel.angular.abs() < self.sleep.angular_threshold
+                    {
+                        *still_time += frame_dt;
+                    } else {
+                        *still_time = 0.0;
+                    }
+                    if *still_time < self.sleep.time_to_sleep {
+                        island_still[islands.root(bi)] = false;
+                    }
+                }
+            }
+            for (bi, (body, state, pose, vel)) in
+                izip!(&body_refs, &mut sleep_states, &poses, &mut velocities).enumerate()
+            {
+                if body.sees_forces() && island_still[islands.root(bi)] {
+                    *state = SleepState::Asleep { pose: Some(*pose) };
+                    *vel = Velocity::default();
                 }
             }
         }
@@ -762,14 +2204,30 @@ impl Physics {
         // drop body_refs so we can get mutable references
         let body_nodes: Vec<graph::NodePosition> =
             body_refs.into_iter().map(|br| br.pos()).collect();
-        for (body, pose_result, vel_result) in izip!(body_nodes, poses, velocities) {
+        for (body, pose_result, vel_result, sleep_state) in
+            izip!(body_nodes, poses, velocities, sleep_states)
+        {
             let mut body = l_body.get_mut_unchecked(body);
             let mut pose = graph.get_neighbor_mut_unchecked(&body, l_pose).unwrap();
             body.velocity = vel_result;
+            body.force = m::Vec2::zero();
+            body.torque = 0.0;
+            body.sleep_state = sleep_state;
             *pose = pose_result;
         }
     }
 
+    /// List the colliders that were inside the given trigger collider on the last tick.
+    pub fn overlaps(
+        &self,
+        trigger: graph::Node<Collider>,
+    ) -> impl Iterator<Item = graph::Node<Collider>> + '_ {
+        self.trigger_overlaps
+            .keys()
+            .filter(move |key| key[0] == trigger)
+            .map(|key| key[1])
+    }
+
     /// Find the first rigid body that intersects with the given point.
     pub fn query_point_body<'g>(
         &self,
@@ -783,16 +2241,386 @@ impl Physics {
         graph::NodeRef<'g, Collider>,
         graph::NodeRef<'g, Body>,
     )> {
-        l_body.iter(graph).find_map(|rb| {
-            let coll = graph.get_neighbor(&rb, &l_collider)?;
-            let pose = graph.get_neighbor(&rb, &l_pose)?;
-            if collision::query::point_collider_bool(point, &*pose, &*coll) {
+        l_collider.iter(graph).find_map(|coll| {
+            let rb = graph.get_neighbor(&coll, l_body)?;
+            let pose = graph.get_neighbor(&rb, l_pose)?;
+            if collision::query::point_collider_bool(point, &(*pose * coll.offset), &coll) {
                 Some((pose, coll, rb))
             } else {
                 None
             }
         })
     }
+
+    /// Find the first collider hit by a ray within `max_distance`.
+    ///
+    /// Colliders for which `filter` returns false are ignored,
+    /// e.g. to prevent a ray from hitting the object it's cast from.
+    /// Pass `|_, _| true` to consider every collider.
+    ///
+    /// A ray starting inside a collider hits it at distance zero.
+    pub fn raycast<'g>(
+        &self,
+        ctx: QueryContext<'g>,
+        ray: Ray,
+        max_distance: f64,
+        filter: impl Fn(&graph::NodeRef<'g, Collider>, Option<&graph::NodeRef<'g, Body>>) -> bool,
+    ) -> Option<RayHit<'g>> {
+        let mut closest: Option<RayHit<'g>> = None;
+        for coll in ctx.l_collider.iter(ctx.graph) {
+            let max_distance = closest.as_ref().map(|h| h.distance).unwrap_or(max_distance);
+            if let Some(hit) = ray_hit(ctx, coll, &ray, max_distance, &filter) {
+                closest = Some(hit);
+            }
+        }
+        closest
+    }
+
+    /// Find every collider hit by a ray within `max_distance`, sorted by distance.
+    ///
+    /// See [`raycast`][self::Physics::raycast] for details on the parameters.
+    pub fn raycast_all<'g>(
+        &self,
+        ctx: QueryContext<'g>,
+        ray: Ray,
+        max_distance: f64,
+        filter: impl Fn(&graph::NodeRef<'g, Collider>, Option<&graph::NodeRef<'g, Body>>) -> bool,
+    ) -> Vec<RayHit<'g>> {
+        let mut hits: Vec<RayHit<'g>> = ctx
+            .l_collider
+            .iter(ctx.graph)
+            .filter_map(|coll| ray_hit(ctx, coll, &ray, max_distance, &filter))
+            .collect();
+        hits.sort_by(|h1, h2| {
+            h1.distance
+                .partial_cmp(&h2.distance)
+                .expect("There was a NaN somewhere")
+        });
+        hits
+    }
+
+    /// Find the first collider hit by `collider` moving by `translation` from `start_pose`.
+    ///
+    /// `start_pose` is the pose of the body the collider would be attached to,
+    /// so the collider's offset is taken into account.
+    /// The shape doesn't rotate during the cast.
+    /// Colliders for which `filter` returns false are ignored,
+    /// e.g. to prevent the shape from hitting the body it's cast for.
+    ///
+    /// A shape starting inside a collider hits it with zero time of impact.
+    ///
+    /// Candidates are found with the broad phase as it was on the last tick,
+    /// so colliders added since then aren't hit.
+    pub fn shape_cast<'g>(
+        &self,
+        ctx: QueryContext<'g>,
+        collider: &Collider,
+        start_pose: m::Pose,
+        translation: m::Vec2,
+        filter: impl Fn(&graph::NodeRef<'g, Collider>, Option<&graph::NodeRef<'g, Body>>) -> bool,
+    ) -> Option<ShapeCastHit<'g>> {
+        let mut end_pose = start_pose;
+        end_pose.translation += translation;
+        let swept_aabb = collider
+            .aabb(&(start_pose * collider.offset))
+            .union(&collider.aabb(&(end_pose * collider.offset)));
+
+        let mut closest: Option<ShapeCastHit<'g>> = None;
+        for coll in self.colliders_near(ctx, swept_aabb) {
+            let pose = collider_pose(ctx.graph, ctx.l_pose, ctx.l_body, &coll);
+            if !coll.aabb(&pose).overlaps(&swept_aabb) {
+                continue;
+            }
+            let body = ctx.graph.get_neighbor(&coll, ctx.l_body);
+            if !filter(&coll, body.as_ref()) {
+                continue;
+            }
+            let hit = match collision::query::shape_cast_collider(
+                collider,
+                &start_pose,
+                translation,
+                &pose,
+                &coll,
+            ) {
+                Some(hit) => hit,
+                None => continue,
+            };
+            if closest
+                .as_ref()
+                .map(|c| hit.t < c.time_of_impact)
+                .unwrap_or(true)
+            {
+                closest = Some(ShapeCastHit {
+                    collider: coll,
+                    body,
+                    time_of_impact: hit.t,
+                    point: hit.point,
+                    normal: hit.normal,
+                });
+            }
+        }
+        closest
+    }
+
+    /// Find every collider that intersects with the axis-aligned box between `min` and `max`.
+    ///
+    /// The corners can be given in any order.
+    /// Colliders are found with the broad phase as it was on the last tick,
+    /// so ones added since then aren't found.
+    pub fn query_aabb<'g>(
+        &self,
+        ctx: QueryContext<'g>,
+        min: m::Vec2,
+        max: m::Vec2,
+        filter: QueryFilter,
+    ) -> Vec<graph::NodeRef<'g, Collider>> {
+        let (min, max) = (min.min_by_component(max), min.max_by_component(max));
+        let size = max - min;
+        let box_coll = Collider::new_rect(size.x, size.y);
+        let box_pose = m::Pose::new((min + max) / 2.0, m::Rotor2::identity());
+        self.query_shape(ctx, &box_coll, box_pose, filter)
+    }
+
+    /// Find every collider that intersects with the given collider at the given pose.
+    ///
+    /// `pose` is the pose of the body the collider would be attached to,
+    /// so the collider's offset is taken into account.
+    /// Like [`query_aabb`][self::Physics::query_aabb], this uses the broad phase
+    /// from the last tick.
+    pub fn query_shape<'g>(
+        &self,
+        ctx: QueryContext<'g>,
+        collider: &Collider,
+        pose: m::Pose,
+        filter: QueryFilter,
+    ) -> Vec<graph::NodeRef<'g, Collider>> {
+        let query_pose = pose * collider.offset;
+        let query_aabb = collider.aabb(&query_pose);
+        self.colliders_near(ctx, query_aabb)
+            .filter(|coll| {
+                let coll_pose = collider_pose(ctx.graph, ctx.l_pose, ctx.l_body, coll);
+                coll.aabb(&coll_pose).overlaps(&query_aabb)
+                    && filter.allows(coll, ctx.graph.get_neighbor(coll, ctx.l_body).is_some())
+                    && !matches!(
+                        intersection_check(&query_pose, collider, &coll_pose, coll),
+                        ContactResult::Zero
+                    )
+            })
+            .collect()
+    }
+
+    /// Colliders whose bounding boxes overlapped the given box in the broad phase on the last tick.
+    fn colliders_near<'s, 'g: 's>(
+        &'s self,
+        ctx: QueryContext<'g>,
+        aabb: AABB,
+    ) -> impl Iterator<Item = graph::NodeRef<'g, Collider>> + 's {
+        // the index may have been used with a different graph or outlived some colliders
+        let coll_count = ctx.l_collider.content.len();
+        self.spatial_index
+            .query(aabb)
+            .filter(move |node| node.pos().item_idx < coll_count && node.check(ctx.graph).is_some())
+            .map(move |node| ctx.l_collider.get_unchecked(node.pos()))
+    }
+}
+
+/// Insert the parts of a compound body into the graph and attach them to the body.
+///
+/// The first collider is connected to the body both ways like a single collider would be.
+/// Because a node can only have one edge to a given layer, the rest are chained after it,
+/// each one keeping the next one alive, and given a one-way edge to the body.
+/// Deleting the body deletes the whole chain.
+///
+/// Returns the created collider nodes in the same order as the given colliders.
+pub fn insert_compound_colliders(
+    graph: &mut graph::Graph,
+    l_collider: &mut graph::Layer<Collider>,
+    body: &impl graph::SafeNode,
+    colliders: &[Collider],
+) -> Vec<graph::Node<Collider>> {
+    let mut nodes: Vec<graph::Node<Collider>> = Vec::with_capacity(colliders.len());
+    for coll in colliders {
+        let pos = l_collider.insert(*coll, graph).pos();
+        let coll_ref = l_collider.get_unchecked(pos);
+        match nodes.last() {
+            None => graph.connect(body, &coll_ref),
+            Some(prev) => {
+                graph.connect_oneway(&l_collider.get_unchecked(prev.pos()), &coll_ref);
+                graph.connect_oneway(&coll_ref, body);
+            }
+        }
+        nodes.push(graph::NodeRef::as_node(&coll_ref, graph));
+    }
+    nodes
+}
+
+/// Get the pose of a collider, which is its offset from the pose of its body if it has one.
+fn collider_pose(
+    graph: &graph::Graph,
+    l_pose: &graph::Layer<m::Pose>,
+    l_body: &graph::Layer<Body>,
+    coll: &graph::NodeRef<Collider>,
+) -> m::Pose {
+    let pose = match graph.get_neighbor(coll, l_body) {
+        Some(body) => graph.get_neighbor(&body, l_pose),
+        None => graph.get_neighbor(coll, l_pose),
+    };
+    match pose {
+        Some(pose) => *pose * coll.offset,
+        None => coll.offset,
+    }
+}
+
+fn ray_hit<'g>(
+    ctx: QueryContext<'g>,
+    coll: graph::NodeRef<'g, Collider>,
+    ray: &Ray,
+    max_distance: f64,
+    filter: &impl Fn(&graph::NodeRef<'g, Collider>, Option<&graph::NodeRef<'g, Body>>) -> bool,
+) -> Option<RayHit<'g>> {
+    let body = ctx.graph.get_neighbor(&coll, ctx.l_body);
+    if !filter(&coll, body.as_ref()) {
+        return None;
+    }
+    let pose = collider_pose(ctx.graph, ctx.l_pose, ctx.l_body, &coll);
+    let hit = collision::query::ray_collider(ray, max_distance, &pose, &coll)?;
+    Some(RayHit {
+        collider: coll,
+        body,
+        point: ray.point_at(hit.t),
+        normal: hit.normal,
+        distance: hit.t,
+    })
+}
+
+/// Order a pair of material ids so the pair table doesn't depend on which collider is first.
+fn material_pair_key(id_a: u32, id_b: u32) -> [u32; 2] {
+    [id_a.min(id_b), id_a.max(id_b)]
+}
+
+/// Order a pair of colliders consistently so the same pair can be found between frames.
+fn contact_key(colls: [graph::Node<Collider>; 2]) -> [graph::Node<Collider>; 2] {
+    if colls[0].pos().item_idx <= colls[1].pos().item_idx {
+        colls
+    } else {
+        [colls[1], colls[0]]
+    }
+}
+
+/// Deliver an event to the sink of the collider it happened to,
+/// or the collider's body if the collider doesn't have a sink of its own.
+fn push_event(
+    graph: &graph::Graph,
+    l_body: &graph::Layer<Body>,
+    l_evt_sink: &mut graph::Layer<EventSink>,
+    coll: graph::Node<Collider>,
+    evt: Event,
+) {
+    // the collider may have been deleted since we last saw it
+    if coll.check(graph).is_none() {
+        return;
+    }
+    let sink = if graph.get_neighbor_unchecked(&coll, l_evt_sink).is_some() {
+        graph.get_neighbor_mut_unchecked(&coll, l_evt_sink)
+    } else {
+        graph
+            .get_neighbor_unchecked(&coll, l_body)
+            .and_then(|body| graph.get_neighbor_mut_unchecked(&body, l_evt_sink))
+    };
+    if let Some(mut sink) = sink {
+        sink.push(evt);
+    }
+}
+
+/// Move the points of a user constraint so that the target's point moves by `correction`
+/// relative to the owner's, weighting the movement of each body by its inverse mass.
+///
+/// Returns the magnitude of the Lagrange multiplier, which divided by the timestep squared
+/// is the force the constraint applied.
+fn apply_positional_correction(
+    poses: &mut [m::Pose],
+    pair: (usize, Option<usize>),
+    offsets: [m::Vec2; 2],
+    inv_masses: [f64; 2],
+    inv_mom_inertias: [f64; 2],
+    correction: m::Vec2,
+    compliance: f64,
+) -> f64 {
+    let error = correction.mag();
+    if error == 0.0 {
+        return 0.0;
+    }
+    let dir = correction / error;
+
+    let offsets_rotated = [
+        poses[pair.0].rotation * offsets[0],
+        pair.1
+            .map(|p1| poses[p1].rotation * offsets[1])
+            .unwrap_or_else(m::Vec2::zero),
+    ];
+    let offsets_wedge_dir = map_pair(&offsets_rotated, |o| o.wedge(dir).xy);
+    let eff_inv_masses = map_pair(&[0, 1], |i| {
+        inv_masses[*i] + offsets_wedge_dir[*i].powi(2) * inv_mom_inertias[*i]
+    });
+
+    let lambda = error / (eff_inv_masses[0] + eff_inv_masses[1] + compliance);
+
+    poses[pair.0].append_translation(-inv_masses[0] * lambda * dir);
+    poses[pair.0]
+        .prepend_rotation(Angle::Rad(-inv_mom_inertias[0] * lambda * offsets_wedge_dir[0]).into());
+    if let Some(p1) = pair.1 {
+        poses[p1].append_translation(inv_masses[1] * lambda * dir);
+        poses[p1].prepend_rotation(
+            Angle::Rad(inv_mom_inertias[1] * lambda * offsets_wedge_dir[1]).into(),
+        );
+    }
+    lambda
+}
+
+/// Rotate the bodies of a user constraint so that the owner turns by `correction` radians
+/// relative to the target, weighting the rotation of each body by its inverse moment of inertia.
+///
+/// Returns the magnitude of the Lagrange multiplier, which divided by the timestep squared
+/// is the torque the constraint applied.
+fn apply_angular_correction(
+    poses: &mut [m::Pose],
+    pair: (usize, Option<usize>),
+    inv_mom_inertias: [f64; 2],
+    correction: f64,
+) -> f64 {
+    let inv_mom_inertia_sum = inv_mom_inertias[0] + inv_mom_inertias[1];
+    if correction == 0.0 || inv_mom_inertia_sum <= 0.0 {
+        return 0.0;
+    }
+    let lambda = correction / inv_mom_inertia_sum;
+    poses[pair.0].prepend_rotation(Angle::Rad(inv_mom_inertias[0] * lambda).into());
+    if let Some(p1) = pair.1 {
+        poses[p1].prepend_rotation(Angle::Rad(-inv_mom_inertias[1] * lambda).into());
+    }
+    lambda.abs()
+}
+
+/// Angle of a user constraint's owner relative to its target (or the world), in [-π, π].
+fn relative_angle(poses: &[m::Pose], pair: (usize, Option<usize>)) -> f64 {
+    let target_rotation = pair
+        .1
+        .map(|p1| poses[p1].rotation)
+        .unwrap_or_else(m::Rotor2::identity);
+    // rotors cover every angle twice, bring it back to [-π, π]
+    wrap_angle(Angle::from(poses[pair.0].rotation * target_rotation.reversed()).rad())
+}
+
+/// Bring an angle in [-3π, 3π] to the equivalent angle in [-π, π].
+fn wrap_angle(angle: f64) -> f64 {
+    use std::f64::consts::PI;
+
+    if angle > PI {
+        angle - 2.0 * PI
+    } else if angle < -PI {
+        angle + 2.0 * PI
+    } else {
+        angle
+    }
 }
 //
 // helpers to reduce duplication when fetching info for pairs of objects
@@ -803,3 +2631,591 @@ fn map_pair<T, R>(pair: &[T; 2], f: impl Fn(&T) -> R) -> [R; 2] {
 fn map_semi_pair<T, R>(pair: (T, Option<T>), f: impl Fn(&T) -> R, snd_default: R) -> [R; 2] {
     [f(&pair.0), pair.1.map(|x| f(&x)).unwrap_or(snd_default)]
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::event::EventGraph;
+
+    pub(crate) const DT: f64 = 1.0 / 60.0;
+    pub(crate) const GRAVITY: forcefield::Gravity = forcefield::Gravity(m::Vec2::new(0.0, -9.81));
+
+    /// A small world for running the whole solver a tick at a time,
+    /// with events gathered into a list.
+    pub(crate) struct TestWorld {
+        pub graph: graph::Graph,
+        pub l_pose: graph::Layer<m::Pose>,
+        pub l_collider: graph::Layer<Collider>,
+        pub l_body: graph::Layer<Body>,
+        pub events: EventGraph<Vec<Event>>,
+        pub physics: Physics,
+    }
+
+    impl TestWorld {
+        pub fn new() -> Self {
+            let mut graph = graph::Graph::new();
+            TestWorld {
+                l_pose: graph.create_layer(),
+                l_collider: graph.create_layer(),
+                l_body: graph.create_layer(),
+                events: EventGraph::new(&mut graph),
+                graph,
+                physics: Physics::default(),
+            }
+        }
+
+        pub fn ctx(&self) -> QueryContext<'_> {
+            QueryContext {
+                graph: &self.graph,
+                l_pose: &self.l_pose,
+                l_collider: &self.l_collider,
+                l_body: &self.l_body,
+            }
+        }
+
+        /// Add a collider that isn't attached to a body.
+        pub fn add_static(&mut self, coll: Collider, position: m::Vec2) -> graph::Node<Collider> {
+            let pose = self.l_pose.insert(
+                m::Pose::new(position, m::Rotor2::identity()),
+                &mut self.graph,
+            );
+            let coll = self.l_collider.insert(coll, &mut self.graph);
+            self.graph.connect(&pose, &coll);
+            graph::NodeRef::as_node(&coll, &self.graph)
+        }
+
+        /// Add a body with a single collider.
+        pub fn add_body(
+            &mut self,
+            coll: Collider,
+            body: Body,
+            position: m::Vec2,
+        ) -> graph::Node<Body> {
+            let pose = self.l_pose.insert(
+                m::Pose::new(position, m::Rotor2::identity()),
+                &mut self.graph,
+            );
+            let coll = self.l_collider.insert(coll, &mut self.graph);
+            let body = self.l_body.insert(body, &mut self.graph);
+            self.graph.connect(&pose, &coll);
+            self.graph.connect(&pose, &body);
+            self.graph.connect(&body, &coll);
+            graph::NodeRef::as_node(&body, &self.graph)
+        }
+
+        /// Add a body made of several colliders with [`insert_compound_colliders`].
+        pub fn add_compound(
+            &mut self,
+            colls: &[Collider],
+            body: Body,
+            position: m::Vec2,
+        ) -> (graph::Node<Body>, Vec<graph::Node<Collider>>) {
+            let pose = self.l_pose.insert(
+                m::Pose::new(position, m::Rotor2::identity()),
+                &mut self.graph,
+            );
+            let body = self.l_body.insert(body, &mut self.graph);
+            self.graph.connect(&pose, &body);
+            let body = graph::NodeRef::as_node(&body, &self.graph);
+            let colls = insert_compound_colliders(
+                &mut self.graph,
+                &mut self.l_collider,
+                &self.l_body.get_unchecked(body.pos()),
+                colls,
+            );
+            (body, colls)
+        }
+
+        /// Gather the events sent to a body or collider.
+        pub fn listen<T>(&mut self, node: graph::Node<T>) {
+            let sink = self.events.add_sink(
+                |log: &mut Vec<Event>, _, evt| log.push(evt),
+                &mut self.graph,
+            );
+            let sink = graph::NodeRef::as_node(&sink, &self.graph);
+            self.graph.connect(
+                &sink.check(&self.graph).unwrap(),
+                &node.check(&self.graph).unwrap(),
+            );
+        }
+
+        /// Run one frame and return the events it produced.
+        pub fn tick(&mut self, forcefield: &impl ForceField) -> Vec<Event> {
+            self.physics.tick(
+                &self.graph,
+                &mut self.l_pose,
+                &mut self.l_body,
+                &self.l_collider,
+                &mut self.events.sinks,
+                DT,
+                forcefield,
+            );
+            let mut log = Vec::new();
+            self.events.flush(&self.graph)(&mut log);
+            log
+        }
+
+        pub fn body(&self, body: graph::Node<Body>) -> graph::NodeRef<'_, Body> {
+            self.l_body.get_unchecked(body.pos())
+        }
+
+        pub fn body_mut(&mut self, body: graph::Node<Body>) -> graph::NodeRefMut<'_, Body> {
+            self.l_body.get_mut_unchecked(body.pos())
+        }
+
+        pub fn pose(&self, body: graph::Node<Body>) -> m::Pose {
+            *self
+                .graph
+                .get_neighbor(&self.body(body), &self.l_pose)
+                .unwrap()
+        }
+
+        pub fn pose_mut(&mut self, body: graph::Node<Body>) -> graph::NodeRefMut<'_, m::Pose> {
+            let body = self.l_body.get_unchecked(body.pos());
+            self.graph
+                .get_neighbor_mut(&body, &mut self.l_pose)
+                .unwrap()
+        }
+    }
+
+    #[test]
+    fn islands_only_join_touching_bodies() {
+        let mut world = TestWorld::new();
+        world.add_static(Collider::new_rect(20.0, 1.0), m::Vec2::new(0.0, -0.5));
+        // close enough for the broad phase to pair them, but not touching
+        let coll = Collider::new_square(1.0);
+        let left = world.add_body(
+            coll,
+            Body::new_dynamic(&coll, 1.0),
+            m::Vec2::new(-0.51, 0.5),
+        );
+        let right = world.add_body(coll, Body::new_dynamic(&coll, 1.0), m::Vec2::new(0.51, 0.5));
+
+        for _ in 0..300 {
+            world.tick(&GRAVITY);
+        }
+        assert!(world.body(left).is_sleeping());
+        assert!(world.body(right).is_sleeping());
+
+        world.body_mut(left).velocity.linear = m::Vec2::new(0.0, 1.0);
+        world.tick(&GRAVITY);
+        assert!(!world.body(left).is_sleeping());
+        assert!(world.body(right).is_sleeping());
+    }
+
+    #[test]
+    fn contact_events_begin_persist_and_end_in_order() {
+        let mut world = TestWorld::new();
+        world.physics.persisted_contact_events = true;
+        world.add_static(Collider::new_rect(10.0, 1.0), m::Vec2::new(0.0, -0.5));
+        let coll = Collider::new_square(1.0);
+        let body = world.add_body(coll, Body::new_dynamic(&coll, 1.0), m::Vec2::new(0.0, 1.0));
+        world.listen(body);
+
+        let mut log = Vec::new();
+        for frame in 0..60 {
+            if frame == 50 {
+                world.pose_mut(body).translation.y = 3.0;
+            }
+            log.extend(world.tick(&GRAVITY));
+        }
+
+        let kinds: Vec<&str> = log
+            .iter()
+            .map(|evt| match evt {
+                Event::ContactBegan(_) => "began",
+                Event::ContactPersisted(_) => "persisted",
+                Event::ContactEnded(_) => "ended",
+                _ => "other",
+            })
+            .collect();
+        assert_eq!(kinds.first(), Some(&"began"));
+        assert_eq!(kinds.last(), Some(&"ended"));
+        assert!(kinds[1..kinds.len() - 1].iter().all(|k| *k == "persisted"));
+        assert!(kinds.len() > 10);
+
+        // the normal points from the body to the ground it's resting on
+        if let Event::ContactBegan(contact) = log[0] {
+            assert!(contact.normal.y < -0.99);
+            assert!(contact.impulse > 0.0);
+        }
+    }
+
+    #[test]
+    fn triggers_report_enter_and_exit_without_stopping_bodies() {
+        let mut world = TestWorld::new();
+        let trigger = world.add_static(Collider::new_rect(4.0, 1.0).trigger(), m::Vec2::zero());
+        world.listen(trigger);
+        let coll = Collider::new_circle(0.2);
+        let body = world.add_body(
+            coll,
+            Body::new_dynamic(&coll, 1.0).with_velocity(Velocity {
+                linear: m::Vec2::new(0.0, -3.0),
+                angular: 0.0,
+            }),
+            m::Vec2::new(0.0, 1.0),
+        );
+
+        let mut log = Vec::new();
+        let mut was_inside = false;
+        for _ in 0..60 {
+            log.extend(world.tick(&forcefield::Gravity(m::Vec2::zero())));
+            was_inside |= world.physics.overlaps(trigger).count() == 1;
+        }
+        assert!(was_inside);
+        assert_eq!(world.physics.overlaps(trigger).count(), 0);
+
+        assert_eq!(log.len(), 2);
+        match (log[0], log[1]) {
+            (Event::TriggerEnter(enter), Event::TriggerExit(exit)) => {
+                assert_eq!(enter.trigger, trigger);
+                assert_eq!(enter.other_body, Some(body));
+                assert_eq!(exit.other_body, Some(body));
+            }
+            other => panic!("Expected enter and exit, got {:?}", other),
+        }
+        // passing through the trigger didn't slow the body down
+        assert!((world.body(body).velocity.linear.y + 3.0).abs() < 1e-9);
+        assert!((world.pose(body).translation.y - (1.0 - 3.0 * 60.0 * DT)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn collision_filters_let_bodies_pass_through() {
+        let mut world = TestWorld::new();
+        world.add_static(
+            Collider::new_rect(20.0, 1.0).with_collision_layers(0b01, 0b11),
+            m::Vec2::new(0.0, -0.5),
+        );
+        let platform_coll = Collider::new_rect(2.0, 0.2);
+        let platform = world.add_body(platform_coll, Body::new_kinematic(), m::Vec2::new(6.0, 1.0));
+
+        let ball = Collider::new_circle(0.25);
+        let add_ball = |world: &mut TestWorld, coll: Collider, x: f64| {
+            world.add_body(coll, Body::new_dynamic(&coll, 1.0), m::Vec2::new(x, 2.0))
+        };
+        let lands = add_ball(&mut world, ball, 0.0);
+        let same_layer = add_ball(&mut world, ball.with_collision_layers(0b10, 0b11), 2.0);
+        let masked = add_ball(&mut world, ball.with_collision_layers(0b10, 0b10), 4.0);
+        let excluding = add_ball(&mut world, ball.with_excluded_body(platform), 6.0);
+
+        for _ in 0..60 {
+            world.tick(&GRAVITY);
+        }
+        let height = |body| world.pose(body).translation.y;
+        assert!((height(lands) - 0.25).abs() < 0.01);
+        assert!((height(same_layer) - 0.25).abs() < 0.01);
+        assert!(height(masked) < -1.0);
+        // fell through the platform but not the ground
+        assert!((height(excluding) - 0.25).abs() < 0.01);
+    }
+
+    #[test]
+    fn jump_through_one_way_platform_and_land_on_it() {
+        let mut world = TestWorld::new();
+        world.add_static(
+            Collider::new_rect(4.0, 0.2).with_one_way(m::Vec2::unit_y()),
+            m::Vec2::new(0.0, 2.0),
+        );
+        let coll = Collider::new_circle(0.25);
+        let body = world.add_body(
+            coll,
+            Body::new_dynamic(&coll, 1.0).with_velocity(Velocity {
+                linear: m::Vec2::new(0.0, 8.0),
+                angular: 0.0,
+            }),
+            m::Vec2::new(0.0, 0.5),
+        );
+
+        let mut max_height: f64 = 0.0;
+        for _ in 0..120 {
+            world.tick(&GRAVITY);
+            max_height = max_height.max(world.pose(body).translation.y);
+        }
+        assert!(
+            max_height > 3.0,
+            "didn't get through, max height {}",
+            max_height
+        );
+        let height = world.pose(body).translation.y;
+        assert!(
+            (height - 2.35).abs() < 0.01,
+            "didn't land, height {}",
+            height
+        );
+    }
+
+    #[test]
+    fn bullets_dont_tunnel_through_thin_walls() {
+        // position after a few frames flying at a thin wall with its left side at x = 1
+        let shoot = |bullet: bool| {
+            let mut world = TestWorld::new();
+            // with one substep the body moves several meters at once,
+            // never overlapping the wall at the end of a step
+            world.physics = Physics::with_substeps(1);
+            world.add_static(Collider::new_rect(0.02, 4.0), m::Vec2::new(1.01, 0.0));
+            let coll = Collider::new_circle(0.05);
+            let mut body = Body::new_dynamic(&coll, 1.0).with_velocity(Velocity {
+                linear: m::Vec2::new(200.0, 0.0),
+                angular: 0.0,
+            });
+            if bullet {
+                body = body.as_bullet();
+            }
+            let body = world.add_body(coll, body, m::Vec2::zero());
+            for _ in 0..10 {
+                world.tick(&forcefield::Gravity(m::Vec2::zero()));
+            }
+            world.pose(body).translation.x
+        };
+
+        let x = shoot(false);
+        assert!(x > 1.0, "regular body didn't tunnel, x = {}", x);
+        let x = shoot(true);
+        assert!(x < 1.0, "bullet went through the wall, x = {}", x);
+    }
+
+    #[test]
+    fn material_combine_rules_and_pairs_set_friction() {
+        let ice = Material {
+            static_friction_coef: 0.05,
+            dynamic_friction_coef: 0.02,
+            id: 1,
+            ..Default::default()
+        };
+        let metal = Material {
+            static_friction_coef: 0.6,
+            dynamic_friction_coef: 0.5,
+            id: 2,
+            ..Default::default()
+        };
+        // distance a box sliding at 5 m/s travels in one second
+        let slide =
+            |floor_material: Material, box_material: Material, pair: Option<PairMaterial>| {
+                let mut world = TestWorld::new();
+                if let Some(pair) = pair {
+                    world.physics.set_material_pair(2, 1, pair);
+                }
+                world.add_static(
+                    Collider::new_rect(100.0, 1.0).with_material(floor_material),
+                    m::Vec2::new(0.0, -0.5),
+                );
+                let coll = Collider::new_square(1.0).with_material(box_material);
+                let body = world.add_body(
+                    coll,
+                    Body::new_dynamic(&coll, 1.0).with_velocity(Velocity {
+                        linear: m::Vec2::new(5.0, 0.0),
+                        angular: 0.0,
+                    }),
+                    m::Vec2::new(0.0, 0.5),
+                );
+                for _ in 0..60 {
+                    world.tick(&GRAVITY);
+                }
+                world.pose(body).translation.x
+            };
+
+        // combining with the rule gives the same result as both having the combined coefficients
+        let average = slide(ice, metal, None);
+        let both_average = Material {
+            static_friction_coef: 0.325,
+            dynamic_friction_coef: 0.26,
+            ..Default::default()
+        };
+        let expected = slide(both_average, both_average, None);
+        assert!(
+            (average - expected).abs() < 1e-6,
+            "{} {}",
+            average,
+            expected
+        );
+
+        let min_metal = Material {
+            friction_combine: CombineRule::Min,
+            ..metal
+        };
+        let min = slide(ice, min_metal, None);
+        let expected = slide(ice, ice, None);
+        assert!((min - expected).abs() < 1e-6, "{} {}", min, expected);
+        assert!(min > average);
+
+        // the pair table overrides combine rules
+        let frictionless = PairMaterial {
+            static_friction_coef: 0.0,
+            dynamic_friction_coef: 0.0,
+            restitution_coef: 0.0,
+        };
+        let paired = slide(ice, min_metal, Some(frictionless));
+        assert!((paired - 5.0).abs() < 0.01, "{}", paired);
+        assert!(paired > min);
+    }
+
+    #[test]
+    fn shape_cast_finds_first_hit_from_broad_phase() {
+        let mut world = TestWorld::new();
+        world.add_static(Collider::new_rect(10.0, 1.0), m::Vec2::new(0.0, -0.5));
+        let ball = Collider::new_circle(0.5);
+        let body = world.add_body(ball, Body::new_dynamic(&ball, 1.0), m::Vec2::new(0.0, 2.0));
+        world.tick(&forcefield::Gravity(m::Vec2::zero()));
+
+        let caster = Collider::new_square(0.2);
+        let start = m::Pose::new(m::Vec2::new(0.0, 5.0), m::Rotor2::identity());
+        let down = m::Vec2::new(0.0, -10.0);
+        let hit = world
+            .physics
+            .shape_cast(world.ctx(), &caster, start, down, |_, _| true)
+            .unwrap();
+        assert_eq!(
+            hit.body.map(|b| graph::NodeRef::as_node(&b, &world.graph)),
+            Some(body)
+        );
+        assert!((hit.time_of_impact - 0.24).abs() < 0.001);
+
+        let hit = world
+            .physics
+            .shape_cast(world.ctx(), &caster, start, down, |_, body| body.is_none())
+            .unwrap();
+        assert!(hit.body.is_none());
+        assert!((hit.time_of_impact - 0.49).abs() < 0.001);
+        assert!(hit.point.y.abs() < 0.001);
+        assert!((hit.normal.y - 1.0).abs() < 1e-6);
+
+        let sideways = m::Vec2::new(10.0, 0.0);
+        assert!(world
+            .physics
+            .shape_cast(world.ctx(), &caster, start, sideways, |_, _| true)
+            .is_none());
+    }
+
+    #[test]
+    fn region_queries_with_filters() {
+        let mut world = TestWorld::new();
+        world.add_static(Collider::new_rect(10.0, 1.0), m::Vec2::new(0.0, -0.5));
+        world.add_static(Collider::new_circle(1.0).trigger(), m::Vec2::new(3.0, 3.0));
+        let ball = Collider::new_circle(0.5);
+        world.add_body(ball, Body::new_dynamic(&ball, 1.0), m::Vec2::new(0.0, 2.0));
+        world.tick(&forcefield::Gravity(m::Vec2::zero()));
+
+        let count = |min: (f64, f64), max: (f64, f64), filter: QueryFilter| {
+            world
+                .physics
+                .query_aabb(world.ctx(), min.into(), max.into(), filter)
+                .len()
+        };
+        let all = QueryFilter::default();
+        assert_eq!(count((-1.0, -1.0), (1.0, 1.6), all), 2);
+        assert_eq!(count((-1.0, -1.0), (1.0, 1.4), all), 1);
+        // corners in the wrong order
+        assert_eq!(count((1.0, 1.4), (-1.0, -1.0), all), 1);
+        // the box's corner is near the ball but not touching it
+        assert_eq!(count((0.4, 2.4), (1.0, 3.0), all), 0);
+        assert_eq!(count((-5.0, -5.0), (5.0, 5.0), all), 3);
+        assert_eq!(count((-5.0, -5.0), (5.0, 5.0), QueryFilter::solids()), 2);
+        let static_solids = QueryFilter::solids().attached_to_body(false);
+        assert_eq!(count((-5.0, -5.0), (5.0, 5.0), static_solids), 1);
+        let triggers = QueryFilter {
+            solids: false,
+            ..Default::default()
+        };
+        assert_eq!(count((-5.0, -5.0), (5.0, 5.0), triggers), 1);
+
+        let shape = Collider::new_circle(1.0);
+        let pose = m::Pose::new(m::Vec2::new(1.4, 2.4), m::Rotor2::identity());
+        assert_eq!(
+            world
+                .physics
+                .query_shape(world.ctx(), &shape, pose, all)
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn deleting_a_compound_body_frees_every_collider() {
+        let mut world = TestWorld::new();
+        world.add_static(Collider::new_rect(10.0, 1.0), m::Vec2::new(0.0, -0.5));
+        let parts: Vec<Collider> = (0..3)
+            .map(|i| {
+                Collider::new_square(0.5).with_offset(m::Pose::new(
+                    m::Vec2::new(i as f64 * 0.5, 0.0),
+                    m::Rotor2::identity(),
+                ))
+            })
+            .collect();
+        let (body, colls) = world.add_compound(
+            &parts,
+            Body::new_dynamic_compound(&parts, 1.0),
+            m::Vec2::new(0.0, 1.0),
+        );
+        assert_eq!(world.l_collider.iter(&world.graph).count(), 4);
+        world.tick(&GRAVITY);
+
+        let body = body.check(&world.graph).unwrap();
+        world.graph.delete(body);
+        assert_eq!(world.l_collider.iter(&world.graph).count(), 1);
+        assert!(colls.iter().all(|c| c.check(&world.graph).is_none()));
+        // the solver copes with the colliders disappearing from the broad phase
+        world.tick(&GRAVITY);
+    }
+
+    #[test]
+    fn contact_events_name_the_touching_part_of_a_compound_body() {
+        let mut world = TestWorld::new();
+        world.add_static(Collider::new_rect(10.0, 1.0), m::Vec2::new(0.0, -0.5));
+        // a small square held up above the ground by a big one
+        let parts = [
+            Collider::new_square(0.5)
+                .with_offset(m::Pose::new(m::Vec2::new(-1.0, 0.5), m::Rotor2::identity())),
+            Collider::new_square(1.0)
+                .with_offset(m::Pose::new(m::Vec2::new(1.0, 0.0), m::Rotor2::identity())),
+        ];
+        let (body, colls) = world.add_compound(
+            &parts,
+            Body::new_dynamic_compound(&parts, 1.0),
+            m::Vec2::new(0.0, 0.55),
+        );
+        world.listen(body);
+
+        let mut contacts = Vec::new();
+        for _ in 0..60 {
+            for evt in world.tick(&GRAVITY) {
+                if let Event::ContactBegan(contact) = evt {
+                    contacts.push(contact);
+                }
+            }
+        }
+        assert!(!contacts.is_empty());
+        assert!(contacts.iter().all(|c| c.collider == colls[1]));
+    }
+
+    #[test]
+    fn deleting_the_support_wakes_a_resting_stack() {
+        let mut world = TestWorld::new();
+        let floor = world.add_static(Collider::new_rect(10.0, 1.0), m::Vec2::new(0.0, -0.5));
+        let coll = Collider::new_square(1.0);
+        let stack: Vec<graph::Node<Body>> = (0..3)
+            .map(|i| {
+                world.add_body(
+                    coll,
+                    Body::new_dynamic(&coll, 1.0),
+                    m::Vec2::new(0.0, 0.5 + i as f64),
+                )
+            })
+            .collect();
+        for _ in 0..300 {
+            world.tick(&GRAVITY);
+        }
+        assert!(stack.iter().all(|b| world.body(*b).is_sleeping()));
+        let heights: Vec<f64> = stack.iter().map(|b| world.pose(*b).translation.y).collect();
+
+        let floor = floor.check(&world.graph).unwrap();
+        world.graph.delete(floor);
+        for _ in 0..30 {
+            world.tick(&GRAVITY);
+        }
+        for (body, height) in stack.iter().zip(&heights) {
+            assert!(!world.body(*body).is_sleeping());
+            let y = world.pose(*body).translation.y;
+            assert!(y < height - 1.0, "stayed at {} instead of falling", y);
+        }
+    }
+}
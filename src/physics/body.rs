@@ -1,12 +1,51 @@
 use super::{Collider, Velocity};
+use crate::math as m;
 
 /// A body is something that moves, typically a physics-enabled rigid body or particle.
 /// Connect a Body with a Collider to make it collide with other things.
+///
+/// A body can also be made of several colliders positioned with
+/// [`Collider::with_offset`][crate::physics::Collider::with_offset].
+/// Since a node can only have one edge to a given layer, these need to be connected
+/// in a particular way, which [`insert_compound_colliders`][crate::physics::insert_compound_colliders]
+/// takes care of. Colliders attached to the same body never collide with each other.
+///
+/// Forces and torques can be applied to a body with [`apply_force`][self::Body::apply_force]
+/// and friends. They accumulate until the next [`Physics::tick`][crate::physics::Physics::tick],
+/// which applies them over the whole tick and then clears them,
+/// so a continuous force needs to be applied again before every tick.
+/// Impulses change the velocity right away.
+///
+/// The center of mass of a body doesn't need to be at the origin of its pose.
+/// [`MassProperties`][self::MassProperties] can be used to build bodies with an offset
+/// center of mass, such as a wheel with a weight on one side.
+///
+/// Bodies that come to rest fall asleep and stop being simulated until they're disturbed,
+/// see [`SleepSettings`][crate::physics::SleepSettings].
+///
+/// Small, fast bodies can pass through thin objects between substeps.
+/// Bodies marked as bullets with [`as_bullet`][self::Body::as_bullet] are prevented from doing so
+/// at some extra cost.
 #[derive(Clone, Copy, Debug)]
 pub struct Body {
     pub velocity: Velocity,
     pub mass: Mass,
+    /// Moment of inertia around the center of mass.
     pub moment_of_inertia: Mass,
+    /// Position of the center of mass relative to the body's pose, in local space.
+    /// The body rotates around this point.
+    pub center_of_mass: m::Vec2,
+    /// Whether to use continuous collision detection to stop the body
+    /// from tunnelling through things when it moves fast.
+    pub bullet: bool,
+    /// Multiplier for the effect of gravity fields on the body.
+    /// 1 by default, 0 makes the body float.
+    pub gravity_scale: f64,
+    /// Force accumulated since the last tick, applied during the next one.
+    pub(crate) force: m::Vec2,
+    /// Torque accumulated since the last tick, applied during the next one.
+    pub(crate) torque: f64,
+    pub(crate) sleep_state: SleepState,
 }
 
 impl Body {
@@ -16,6 +55,12 @@ impl Body {
             velocity: Velocity::default(),
             mass: Mass::from(mass),
             moment_of_inertia: Mass::Infinite,
+            center_of_mass: m::Vec2::zero(),
+            bullet: false,
+            gravity_scale: 1.0,
+            force: m::Vec2::zero(),
+            torque: 0.0,
+            sleep_state: SleepState::awake(),
         }
     }
 
@@ -23,16 +68,51 @@ impl Body {
     /// This constructor calculates mass and moment of inertia from the given density and
     /// collider shape.
     pub fn new_dynamic(collider: &Collider, density: f64) -> Self {
-        Self::new_dynamic_const_mass(collider, collider.area() * density)
+        Self::new_dynamic_compound(std::slice::from_ref(collider), density)
+    }
+
+    /// Create a dynamic body made of several colliders with the same density.
+    ///
+    /// Mass is the sum of the colliders' masses, and center of mass and moment of inertia
+    /// are combined from the colliders' shapes and offsets.
+    pub fn new_dynamic_compound(colliders: &[Collider], density: f64) -> Self {
+        Self::from_mass_properties(
+            colliders
+                .iter()
+                .map(|coll| MassProperties::from_collider(coll, density))
+                .sum(),
+        )
     }
 
     /// Create a dynamic body with the given mass instead of using density.
-    /// The collider is still required to compute moment of inertia.
+    /// The collider is still required to compute center of mass and moment of inertia.
     pub fn new_dynamic_const_mass(collider: &Collider, mass: f64) -> Self {
+        Self::from_mass_properties(MassProperties::from_collider(collider, 1.0).with_mass(mass))
+    }
+
+    /// Create a dynamic body with hand-picked mass properties,
+    /// e.g. ones combined from colliders and point masses.
+    ///
+    /// A mass or moment of inertia that isn't positive is treated as infinite,
+    /// so e.g. a body made of a single point mass doesn't rotate.
+    pub fn from_mass_properties(props: MassProperties) -> Self {
+        let positive_or_infinite = |value: f64| {
+            if value > 0.0 {
+                Mass::from(value)
+            } else {
+                Mass::Infinite
+            }
+        };
         Self {
             velocity: Velocity::default(),
-            mass: Mass::from(mass),
-            moment_of_inertia: Mass::from(collider.moment_of_inertia_coef() * mass),
+            mass: positive_or_infinite(props.mass),
+            moment_of_inertia: positive_or_infinite(props.moment_of_inertia),
+            center_of_mass: props.center_of_mass,
+            bullet: false,
+            gravity_scale: 1.0,
+            force: m::Vec2::zero(),
+            torque: 0.0,
+            sleep_state: SleepState::awake(),
         }
     }
 
@@ -42,6 +122,12 @@ impl Body {
             velocity: Velocity::default(),
             mass: Mass::Infinite,
             moment_of_inertia: Mass::Infinite,
+            center_of_mass: m::Vec2::zero(),
+            bullet: false,
+            gravity_scale: 1.0,
+            force: m::Vec2::zero(),
+            torque: 0.0,
+            sleep_state: SleepState::awake(),
         }
     }
 
@@ -51,6 +137,106 @@ impl Body {
         self
     }
 
+    /// Set the gravity scale of the body in a builder-like chain.
+    pub fn with_gravity_scale(mut self, scale: f64) -> Self {
+        self.gravity_scale = scale;
+        self
+    }
+
+    /// Turn on continuous collision detection for the body in a builder-like chain.
+    ///
+    /// The bullet stops at the first thing it would pass through during a substep.
+    /// Its rotation during the substep is not taken into account.
+    pub fn as_bullet(mut self) -> Self {
+        self.bullet = true;
+        self
+    }
+
+    /// Apply a force to the body's center of mass on the next tick.
+    ///
+    /// Wakes the body up if it's asleep.
+    pub fn apply_force(&mut self, force: m::Vec2) {
+        self.force += force;
+        self.wake_up();
+    }
+
+    /// Apply a torque to the body on the next tick.
+    ///
+    /// Wakes the body up if it's asleep.
+    pub fn apply_torque(&mut self, torque: f64) {
+        self.torque += torque;
+        self.wake_up();
+    }
+
+    /// Apply a force at a point in world space on the next tick,
+    /// which also rotates the body if the point isn't its center of mass.
+    ///
+    /// `pose` is the current pose of the body.
+    /// Wakes the body up if it's asleep.
+    pub fn apply_force_at_point(&mut self, force: m::Vec2, point: m::Vec2, pose: &m::Pose) {
+        self.apply_force_at_offset(force, point - *pose * self.center_of_mass);
+    }
+
+    /// Apply a force at a point in the body's local space on the next tick.
+    ///
+    /// The force is in world space, use `pose.rotation` to turn a local direction into one,
+    /// e.g. to push a rocket forward wherever it's pointing.
+    /// `pose` is the current pose of the body.
+    /// Wakes the body up if it's asleep.
+    pub fn apply_force_at_local_point(
+        &mut self,
+        force: m::Vec2,
+        local_point: m::Vec2,
+        pose: &m::Pose,
+    ) {
+        self.apply_force_at_offset(force, pose.rotation * (local_point - self.center_of_mass));
+    }
+
+    /// Apply a force at an offset from the body's center of mass in world space.
+    fn apply_force_at_offset(&mut self, force: m::Vec2, offset: m::Vec2) {
+        self.force += force;
+        self.torque += offset.wedge(force).xy;
+        self.wake_up();
+    }
+
+    /// Apply an impulse to the body's center of mass, changing its velocity immediately.
+    ///
+    /// Wakes the body up if it's asleep.
+    pub fn apply_impulse(&mut self, impulse: m::Vec2) {
+        self.velocity.linear += self.mass.inv() * impulse;
+        self.wake_up();
+    }
+
+    /// Apply an impulse at a point in world space, changing the body's velocity immediately.
+    ///
+    /// `pose` is the current pose of the body.
+    /// Wakes the body up if it's asleep.
+    pub fn apply_impulse_at_point(&mut self, impulse: m::Vec2, point: m::Vec2, pose: &m::Pose) {
+        let offset = point - *pose * self.center_of_mass;
+        self.velocity.linear += self.mass.inv() * impulse;
+        self.velocity.angular += self.moment_of_inertia.inv() * offset.wedge(impulse).xy;
+        self.wake_up();
+    }
+
+    /// Check whether the body is asleep, meaning it's not being simulated until something
+    /// touches it, moves it, or gives it a velocity.
+    pub fn is_sleeping(&self) -> bool {
+        matches!(self.sleep_state, SleepState::Asleep { .. })
+    }
+
+    /// Wake the body up. Everything in contact with it will also wake up on the next tick.
+    pub fn wake_up(&mut self) {
+        self.sleep_state = SleepState::awake();
+    }
+
+    /// Put the body to sleep right away, setting its velocity to zero.
+    ///
+    /// It will be woken up again on the next tick if it's in contact with something awake.
+    pub fn sleep(&mut self) {
+        self.velocity = Velocity::default();
+        self.sleep_state = SleepState::Asleep { pose: None };
+    }
+
     /// Check whether the body has finite mass or moment of inertia, allowing forces to have an
     /// effect on it.
     pub fn sees_forces(&self) -> bool {
@@ -61,6 +247,130 @@ impl Body {
     }
 }
 
+/// Whether a body is being simulated or resting until something disturbs it.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum SleepState {
+    /// Awake, keeping track of how long the body has been moving slowly.
+    Awake { still_time: f64 },
+    /// Asleep at the given pose, used to detect if the body gets moved.
+    /// `None` if the body was put to sleep manually and the pose isn't known yet.
+    Asleep { pose: Option<m::Pose> },
+}
+
+impl SleepState {
+    pub fn awake() -> Self {
+        SleepState::Awake { still_time: 0.0 }
+    }
+}
+
+/// Mass, center of mass and moment of inertia of a body or a part of one.
+///
+/// Parts can be added together to get the properties of the whole,
+/// for instance a wheel with a weight attached to its rim:
+///
+/// ```
+/// # use starframe::{math as m, physics::{Body, Collider, MassProperties}};
+/// let wheel = Collider::new_circle(1.0);
+/// let props = MassProperties::from_collider(&wheel, 1.0)
+///     + MassProperties::point(2.0, m::Vec2::new(0.0, -0.9));
+/// let body = Body::from_mass_properties(props);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MassProperties {
+    pub mass: f64,
+    /// Position of the center of mass in the body's local space.
+    pub center_of_mass: m::Vec2,
+    /// Moment of inertia around the center of mass.
+    pub moment_of_inertia: f64,
+}
+
+impl MassProperties {
+    pub fn new(mass: f64, center_of_mass: m::Vec2, moment_of_inertia: f64) -> Self {
+        Self {
+            mass,
+            center_of_mass,
+            moment_of_inertia,
+        }
+    }
+
+    /// Mass properties of a collider with uniform density, positioned with its offset.
+    pub fn from_collider(coll: &Collider, density: f64) -> Self {
+        let mass = coll.area() * density;
+        let centroid = coll.centroid();
+        // parallel axis theorem from the collider's origin to its centroid
+        let moment_of_inertia = mass * (coll.moment_of_inertia_coef() - centroid.mag_sq());
+        Self {
+            mass,
+            center_of_mass: coll.offset * centroid,
+            moment_of_inertia,
+        }
+    }
+
+    /// A point mass at a position in the body's local space.
+    pub fn point(mass: f64, position: m::Vec2) -> Self {
+        Self {
+            mass,
+            center_of_mass: position,
+            moment_of_inertia: 0.0,
+        }
+    }
+
+    /// Scale the properties to the given total mass, keeping the distribution of mass the same.
+    pub fn with_mass(self, mass: f64) -> Self {
+        let scale = if self.mass > 0.0 {
+            mass / self.mass
+        } else {
+            0.0
+        };
+        Self {
+            mass,
+            center_of_mass: self.center_of_mass,
+            moment_of_inertia: self.moment_of_inertia * scale,
+        }
+    }
+}
+
+impl Default for MassProperties {
+    fn default() -> Self {
+        Self::new(0.0, m::Vec2::zero(), 0.0)
+    }
+}
+
+impl std::ops::Add for MassProperties {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let mass = self.mass + other.mass;
+        if mass <= 0.0 {
+            return Self::default();
+        }
+        let center_of_mass =
+            (self.mass * self.center_of_mass + other.mass * other.center_of_mass) / mass;
+        // parallel axis theorem to move both parts' inertia to the common center
+        let moment_of_inertia = self.moment_of_inertia
+            + self.mass * (self.center_of_mass - center_of_mass).mag_sq()
+            + other.moment_of_inertia
+            + other.mass * (other.center_of_mass - center_of_mass).mag_sq();
+        Self {
+            mass,
+            center_of_mass,
+            moment_of_inertia,
+        }
+    }
+}
+
+impl std::ops::AddAssign for MassProperties {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl std::iter::Sum for MassProperties {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |acc, props| acc + props)
+    }
+}
+
 /// Mass or moment of inertia of a body, which can be infinite.
 ///
 /// This stores both a mass value and its inverse, because calculating inverse mass
@@ -89,3 +399,122 @@ impl Mass {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{
+        forcefield::Gravity,
+        tests::{TestWorld, DT, GRAVITY},
+    };
+
+    #[test]
+    fn forces_last_one_tick_and_impulses_apply_immediately() {
+        let mut world = TestWorld::new();
+        let coll = Collider::new_square(1.0);
+        let body = world.add_body(coll, Body::new_dynamic(&coll, 2.0), m::Vec2::zero());
+        let no_gravity = Gravity(m::Vec2::zero());
+        let inv_inertia = world.body(body).moment_of_inertia.inv();
+
+        world.body_mut(body).sleep();
+        world.body_mut(body).apply_force(m::Vec2::new(12.0, 0.0));
+        assert!(!world.body(body).is_sleeping());
+        world.tick(&no_gravity);
+        let vel = world.body(body).velocity;
+        assert!((vel.linear.x - 6.0 * DT).abs() < 1e-9, "{:?}", vel);
+
+        // forces are cleared after the tick
+        world.tick(&no_gravity);
+        let vel = world.body(body).velocity;
+        assert!((vel.linear.x - 6.0 * DT).abs() < 1e-9, "{:?}", vel);
+
+        // force at the right edge pushes the body up and spins it counterclockwise
+        let pose = world.pose(body);
+        let mut b = world.body_mut(body);
+        b.velocity = Velocity::default();
+        b.apply_force_at_point(
+            m::Vec2::new(0.0, 1.0),
+            pose.translation + m::Vec2::new(0.5, 0.0),
+            &pose,
+        );
+        world.tick(&no_gravity);
+        let vel = world.body(body).velocity;
+        assert!((vel.linear.y - 0.5 * DT).abs() < 1e-9, "{:?}", vel);
+        assert!(
+            (vel.angular - 0.5 * inv_inertia * DT).abs() < 1e-9,
+            "{:?}",
+            vel
+        );
+
+        let pose = world.pose(body);
+        let mut b = world.body_mut(body);
+        b.velocity = Velocity::default();
+        b.apply_impulse_at_point(
+            m::Vec2::new(0.0, 2.0),
+            pose.translation + m::Vec2::new(0.5, 0.0),
+            &pose,
+        );
+        let vel = b.velocity;
+        assert!((vel.linear.y - 1.0).abs() < 1e-9, "{:?}", vel);
+        assert!((vel.angular - inv_inertia).abs() < 1e-9, "{:?}", vel);
+    }
+
+    #[test]
+    fn zero_mass_properties_are_infinite() {
+        let body = Body::from_mass_properties(MassProperties::default());
+        assert!(matches!(body.mass, Mass::Infinite));
+        assert!(matches!(body.moment_of_inertia, Mass::Infinite));
+        assert!(!body.sees_forces());
+
+        // a point mass falls onto the floor without rotating or producing NaNs
+        let mut world = TestWorld::new();
+        world.add_static(Collider::new_rect(10.0, 1.0), m::Vec2::new(0.0, -0.5));
+        let coll = Collider::new_circle(0.25);
+        let body = world.add_body(
+            coll,
+            Body::from_mass_properties(MassProperties::point(1.0, m::Vec2::zero())),
+            m::Vec2::new(0.0, 1.0),
+        );
+        assert!(matches!(world.body(body).moment_of_inertia, Mass::Infinite));
+        world.body_mut(body).velocity.angular = 1.0;
+        for _ in 0..120 {
+            world.tick(&GRAVITY);
+        }
+        let pose = world.pose(body);
+        assert!((pose.translation.y - 0.25).abs() < 0.01, "{:?}", pose);
+        assert!(world.body(body).velocity.angular.is_finite());
+    }
+
+    #[test]
+    fn compound_mass_properties_combine_around_the_center_of_mass() {
+        // an L made of a 2x1 rect along the x axis and a 1x1 square on top of its left end
+        let at = |x: f64, y: f64| m::Pose::new(m::Vec2::new(x, y), m::Rotor2::identity());
+        let parts = [
+            Collider::new_rect(2.0, 1.0).with_offset(at(1.0, 0.5)),
+            Collider::new_square(1.0).with_offset(at(0.5, 1.5)),
+        ];
+        let body = Body::new_dynamic_compound(&parts, 1.0);
+
+        // masses 2 and 1, centered at (1, 0.5) and (0.5, 1.5)
+        let mass = match body.mass {
+            Mass::Finite { mass, .. } => mass,
+            Mass::Infinite => panic!("infinite mass"),
+        };
+        assert!((mass - 3.0).abs() < 1e-9);
+        let com = body.center_of_mass;
+        assert!((com.x - 5.0 / 6.0).abs() < 1e-9, "{:?}", com);
+        assert!((com.y - 5.0 / 6.0).abs() < 1e-9, "{:?}", com);
+        // each part's own inertia, m(w² + h²) / 12, plus m d² to the common center
+        let inertia = 2.0 * 5.0 / 12.0 + 2.0 * 5.0 / 36.0 + 2.0 / 12.0 + 5.0 / 9.0;
+        let moment_of_inertia = match body.moment_of_inertia {
+            Mass::Finite { mass, .. } => mass,
+            Mass::Infinite => panic!("infinite moment of inertia"),
+        };
+        assert!(
+            (moment_of_inertia - inertia).abs() < 1e-9,
+            "{} != {}",
+            moment_of_inertia,
+            inertia
+        );
+    }
+}
@@ -0,0 +1,697 @@
+//! A kinematic character controller for platformer-style movement.
+
+use super::{
+    collider_pose, collision::shape_shape::intersection_check, Body, Collider, Physics,
+    QueryContext, QueryFilter, Velocity,
+};
+use crate::{graph, math as m};
+
+/// Small distance used to look for ground right below the character
+/// and to absorb numerical error.
+const SKIN: f64 = 0.01;
+/// How many times per movement step the character is pushed out of the things it overlaps.
+const MAX_DEPENETRATION_ITERS: usize = 4;
+/// Upper limit for the number of steps a move is split into,
+/// so that tiny colliders and huge velocities can't stall the game.
+const MAX_MOVE_STEPS: usize = 64;
+
+/// Moves a body around the world by sliding along the things it hits
+/// instead of simulating it physically.
+///
+/// This is meant for player characters and other things that need precise,
+/// predictable movement. The body should be created with
+/// [`Body::new_kinematic`][crate::physics::Body::new_kinematic]
+/// so that the physics solver doesn't fight the controller.
+/// Dynamic bodies get pushed out of the character's way by the solver, but they can't push it.
+///
+/// The controller moves the body according to its [`velocity`][self::CharacterController::velocity]
+/// in [`move_and_slide`][self::CharacterController::move_and_slide],
+/// which should be called once per frame. Gravity, jumping and such are up to the user.
+/// Up is always the positive y axis.
+#[derive(Clone, Copy, Debug)]
+pub struct CharacterController {
+    /// Velocity the character is moving at. Parts of it going into obstacles
+    /// are removed when the character hits them.
+    pub velocity: m::Vec2,
+    /// Steepest slope in radians the character can stand on and walk up. Should be less than π/2.
+    pub max_slope: f64,
+    /// Height of steps the character climbs without jumping. Also the distance the character
+    /// snaps down to stay on the ground when walking down slopes and steps.
+    pub step_height: f64,
+    /// How long in seconds the character can still jump after walking off a ledge.
+    pub coyote_time: f64,
+    ground: Option<Ground>,
+    time_since_grounded: f64,
+    jumped: bool,
+    dropping: bool,
+}
+
+/// Information about the ground a [`CharacterController`][self::CharacterController]
+/// is standing on.
+#[derive(Clone, Copy, Debug)]
+pub struct Ground {
+    /// The collider the character is standing on.
+    pub collider: graph::Node<Collider>,
+    /// The body the ground collider is attached to, if any.
+    pub body: Option<graph::Node<Body>>,
+    /// Surface normal of the ground.
+    pub normal: m::Unit<m::Vec2>,
+    /// The point where the character touches the ground in world space.
+    pub point: m::Vec2,
+    /// Velocity of the ground at the contact point, measured from how far it moved
+    /// since the previous frame. The character moves along with it.
+    pub velocity: m::Vec2,
+    // contact point relative to the ground collider, used to follow the ground when it moves
+    local_point: m::Vec2,
+}
+
+/// The parts of the graph [`CharacterController::move_and_slide`][self::CharacterController::move_and_slide]
+/// reads and writes. Like a [`QueryContext`][crate::physics::QueryContext],
+/// but the character's pose and body can be changed through it.
+pub struct CharacterContext<'a> {
+    pub graph: &'a graph::Graph,
+    pub l_pose: &'a mut graph::Layer<m::Pose>,
+    pub l_body: &'a mut graph::Layer<Body>,
+    pub l_collider: &'a graph::Layer<Collider>,
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CharacterController {
+    /// Create a controller that can walk up 45-degree slopes and climb 0.1-unit steps,
+    /// with 0.1 seconds of coyote time.
+    pub fn new() -> Self {
+        CharacterController {
+            velocity: m::Vec2::zero(),
+            max_slope: std::f64::consts::FRAC_PI_4,
+            step_height: 0.1,
+            coyote_time: 0.1,
+            ground: None,
+            time_since_grounded: f64::INFINITY,
+            jumped: false,
+            dropping: false,
+        }
+    }
+
+    /// Set the steepest slope the character can stand on in a builder-like chain.
+    pub fn with_max_slope(mut self, angle: m::Angle) -> Self {
+        self.max_slope = angle.rad();
+        self
+    }
+
+    /// Set the height of steps the character can climb in a builder-like chain.
+    pub fn with_step_height(mut self, height: f64) -> Self {
+        self.step_height = height;
+        self
+    }
+
+    /// Set how long the character can still jump after leaving the ground in a builder-like chain.
+    pub fn with_coyote_time(mut self, time: f64) -> Self {
+        self.coyote_time = time;
+        self
+    }
+
+    /// Get the ground the character was standing on after the last move, if any.
+    pub fn ground(&self) -> Option<&Ground> {
+        self.ground.as_ref()
+    }
+
+    /// Check whether the character was standing on the ground after the last move.
+    pub fn is_grounded(&self) -> bool {
+        self.ground.is_some()
+    }
+
+    /// Time in seconds since the character last stood on the ground.
+    /// Zero while grounded and infinite if it never has been.
+    pub fn time_since_grounded(&self) -> f64 {
+        self.time_since_grounded
+    }
+
+    /// Check whether the character is grounded or left the ground less than
+    /// [`coyote_time`][self::CharacterController::coyote_time] ago,
+    /// without having jumped since.
+    pub fn can_jump(&self) -> bool {
+        !self.jumped && self.time_since_grounded <= self.coyote_time
+    }
+
+    /// Tell the controller the character jumped, so it leaves the ground
+    /// and coyote time doesn't allow another jump before landing.
+    ///
+    /// This doesn't change the velocity, that's up to the caller.
+    pub fn jump(&mut self) {
+        self.jumped = true;
+        self.ground = None;
+    }
+
+    /// Let the character fall through the one-way colliders it's standing on.
+    /// One-way colliders are ignored until the character no longer overlaps any of them.
+    pub fn drop_through_one_way(&mut self) {
+        self.dropping = true;
+    }
+
+    /// Move the body by the controller's velocity over a timestep of `dt`,
+    /// sliding along everything solid in the way, climbing steps and following moving ground.
+    ///
+    /// Only the first collider attached to the body is used for collision.
+    /// The body's collision filter is respected.
+    /// The body's linear velocity is set to the distance it actually moved divided by `dt`,
+    /// so that it wakes up sleeping bodies it touches.
+    ///
+    /// Obstacles are found with the broad phase of `physics` as it was on the last tick,
+    /// so colliders added since then are passed through until the next tick.
+    pub fn move_and_slide(
+        &mut self,
+        physics: &Physics,
+        ctx: CharacterContext,
+        body: graph::Node<Body>,
+        dt: f64,
+    ) {
+        let CharacterContext {
+            graph,
+            l_pose,
+            l_body,
+            l_collider,
+        } = ctx;
+        let checked = match body.check(graph) {
+            Some(checked) => checked,
+            None => return,
+        };
+        let (coll, start_pose) = match (
+            graph.get_neighbor(&checked, l_collider),
+            graph.get_neighbor(&checked, l_pose),
+        ) {
+            (Some(coll), Some(pose)) => (*coll, *pose),
+            _ => return,
+        };
+        let min_normal_y = self.max_slope.cos().max(SKIN);
+        let mut pose = start_pose;
+
+        // move along with the ground if it moved since the last frame
+
+        let prev_ground = self.ground.take();
+        let mut ground_motion = m::Vec2::zero();
+        if let Some(ground) = &prev_ground {
+            if let Some(ground_coll) = ground.collider.check(graph) {
+                let ground_coll = l_collider.get(ground_coll);
+                let ground_pose = collider_pose(graph, l_pose, l_body, &ground_coll);
+                ground_motion = ground_pose * ground.local_point - ground.point;
+            }
+        }
+        pose.translation += ground_motion;
+
+        // gather everything that could be touched during the move
+
+        let displacement = self.velocity * dt;
+        let reach = displacement.mag() + 2.0 * self.step_height + SKIN;
+        let bounds = coll.aabb(&(pose * coll.offset)).padded(reach);
+        let query_ctx = QueryContext {
+            graph,
+            l_pose,
+            l_collider,
+            l_body,
+        };
+        let obstacles: Vec<Obstacle> = physics
+            .query_aabb(query_ctx, bounds.min, bounds.max, QueryFilter::solids())
+            .into_iter()
+            .filter_map(|other| {
+                if !coll.filter.allows(&other.filter) {
+                    return None;
+                }
+                let other_body = graph.get_neighbor(&other, l_body);
+                let other_body_node = other_body
+                    .as_ref()
+                    .map(|b| graph::NodeRef::as_node(b, graph));
+                if other_body_node == Some(body)
+                    || (other_body_node.is_some() && coll.filter.excluded_body == other_body_node)
+                    || other.filter.excluded_body == Some(body)
+                {
+                    return None;
+                }
+                Some(Obstacle {
+                    node: graph::NodeRef::as_node(&other, graph),
+                    collider: *other,
+                    pose: collider_pose(graph, l_pose, l_body, &other),
+                    body: other_body_node,
+                    body_motion: other_body.map(|b| {
+                        let center = graph
+                            .get_neighbor(&b, l_pose)
+                            .map(|p| *p * b.center_of_mass)
+                            .unwrap_or_else(m::Vec2::zero);
+                        (b.velocity, center)
+                    }),
+                })
+            })
+            .collect();
+
+        // move in steps short enough that we can't go through anything
+
+        let size = {
+            let aabb = coll.aabb(&(pose * coll.offset));
+            aabb.max - aabb.min
+        };
+        let max_step_length = (0.25 * size.x.min(size.y)).max(SKIN);
+        let step_count =
+            ((displacement.mag() / max_step_length).ceil() as usize).clamp(1, MAX_MOVE_STEPS);
+        let can_step_up = prev_ground.is_some() && self.step_height > 0.0;
+        let mut hit_ground = false;
+        for _ in 0..step_count {
+            let step = self.velocity * (dt / step_count as f64);
+            let before = pose;
+            pose.translation += step;
+            let hits = self.depenetrate(&mut pose, &coll, &obstacles, step.mag() + SKIN);
+
+            let hit_wall = hits.iter().any(|hit| hit.normal.y.abs() < min_normal_y);
+            if (can_step_up || hit_ground) && hit_wall {
+                if let Some(stepped) = self.step_up(before, step, &coll, &obstacles) {
+                    pose = stepped;
+                    continue;
+                }
+            }
+            for hit in &hits {
+                hit_ground |= hit.normal.y >= min_normal_y;
+                self.slide(hit.normal);
+            }
+        }
+
+        // look for ground below, snapping down onto it
+        // if we were already on the ground and aren't moving up
+
+        let mut ground_hit: Option<Hit> = None;
+        if self.velocity.y <= 0.0 || hit_ground {
+            ground_hit = self.find_ground(&mut pose, &coll, &obstacles);
+            if ground_hit.is_none() && (prev_ground.is_some() || hit_ground) {
+                let mut snapped = self.drop_down(pose, self.step_height, &coll, &obstacles);
+                ground_hit = self.find_ground(&mut snapped, &coll, &obstacles);
+                if ground_hit.is_some() {
+                    pose = snapped;
+                }
+            }
+        }
+
+        self.ground = ground_hit.map(|hit| {
+            let obstacle = &obstacles[hit.obstacle];
+            let velocity = match &prev_ground {
+                Some(prev) if prev.collider == obstacle.node && dt > 0.0 => ground_motion / dt,
+                _ => obstacle
+                    .body_motion
+                    .map(|(vel, center)| vel.point_velocity(hit.point - center))
+                    .unwrap_or_else(m::Vec2::zero),
+            };
+            Ground {
+                collider: obstacle.node,
+                body: obstacle.body,
+                normal: m::Unit::new_unchecked(hit.normal),
+                point: hit.point,
+                velocity,
+                local_point: obstacle.pose.inversed() * hit.point,
+            }
+        });
+        if self.ground.is_some() {
+            self.time_since_grounded = 0.0;
+            self.jumped = false;
+        } else {
+            self.time_since_grounded += dt;
+        }
+
+        // keep ignoring one-way colliders until we're out of them
+        if self.dropping {
+            let coll_pose = pose * coll.offset;
+            self.dropping = obstacles.iter().any(|obs| {
+                obs.collider.one_way.is_some()
+                    && intersection_check(&coll_pose, &coll, &obs.pose, &obs.collider)
+                        .iter()
+                        .next()
+                        .is_some()
+            });
+        }
+
+        // write the results back into the graph
+
+        if let Some(mut body_pose) = graph.get_neighbor_mut(&checked, l_pose) {
+            *body_pose = pose;
+        }
+        let mut body = l_body.get_mut(checked);
+        body.velocity = Velocity {
+            linear: if dt > 0.0 {
+                (pose.translation - start_pose.translation) / dt
+            } else {
+                m::Vec2::zero()
+            },
+            angular: 0.0,
+        };
+    }
+
+    /// Push the character out of everything it overlaps.
+    /// Returns the hits it was pushed out of.
+    ///
+    /// One-way colliders only count if the character could have gotten at most
+    /// `max_one_way_depth` into them during the move.
+    fn depenetrate(
+        &self,
+        pose: &mut m::Pose,
+        coll: &Collider,
+        obstacles: &[Obstacle],
+        max_one_way_depth: f64,
+    ) -> Vec<Hit> {
+        let min_normal_y = self.max_slope.cos().max(SKIN);
+        let mut hits = Vec::new();
+        for _ in 0..MAX_DEPENETRATION_ITERS {
+            let hit_count = hits.len();
+            for (idx, obs) in obstacles.iter().enumerate() {
+                let coll_pose = *pose * coll.offset;
+                let contacts = intersection_check(&coll_pose, coll, &obs.pose, &obs.collider);
+                // pushing out of the deepest contact is enough to get rid of the others
+                let deepest = contacts
+                    .iter()
+                    .map(|c| {
+                        let point = obs.pose * c.offsets[1];
+                        let depth = (coll_pose * c.offsets[0] - point).dot(*c.normal);
+                        (depth, point, -*c.normal)
+                    })
+                    .fold(
+                        None,
+                        |deepest: Option<(f64, m::Vec2, m::Vec2)>, c| match deepest {
+                            Some(d) if d.0 >= c.0 => Some(d),
+                            _ => Some(c),
+                        },
+                    );
+                let (depth, point, normal) = match deepest {
+                    Some(deepest) if deepest.0 > 0.0 => deepest,
+                    _ => continue,
+                };
+                if let Some(dir) = obs.collider.one_way {
+                    let dir = obs.pose.rotation * *dir;
+                    if self.dropping
+                        || normal.dot(dir) < 0.5
+                        || self.velocity.dot(dir) > 0.0
+                        || depth > max_one_way_depth
+                    {
+                        continue;
+                    }
+                }
+                // push straight up out of the ground so we don't slide down slopes
+                pose.translation += if normal.y >= min_normal_y {
+                    m::Vec2::new(0.0, depth / normal.y)
+                } else {
+                    depth * normal
+                };
+                hits.push(Hit {
+                    obstacle: idx,
+                    normal,
+                    point,
+                });
+            }
+            if hits.len() == hit_count {
+                break;
+            }
+        }
+        hits
+    }
+
+    /// Try to climb a step that blocked a move from `from` by `step`.
+    /// Returns the pose on top of the step if there's room for the character there.
+    fn step_up(
+        &self,
+        from: m::Pose,
+        step: m::Vec2,
+        coll: &Collider,
+        obstacles: &[Obstacle],
+    ) -> Option<m::Pose> {
+        let mut pose = from;
+        pose.translation.y += self.step_height;
+        if !self.fits(pose, coll, obstacles, 0.0) {
+            return None;
+        }
+        pose.translation.x += step.x;
+        if !self.fits(pose, coll, obstacles, 0.0) {
+            return None;
+        }
+        let mut pose = self.drop_down(pose, self.step_height, coll, obstacles);
+        if pose.translation.y > from.translation.y
+            && self.find_ground(&mut pose, coll, obstacles).is_some()
+        {
+            Some(pose)
+        } else {
+            None
+        }
+    }
+
+    /// Move the character down until it hits something or has moved `max_dist`.
+    /// The character should not be overlapping anything at the start.
+    fn drop_down(
+        &self,
+        pose: m::Pose,
+        max_dist: f64,
+        coll: &Collider,
+        obstacles: &[Obstacle],
+    ) -> m::Pose {
+        let moved = |dist: f64| {
+            let mut moved = pose;
+            moved.translation.y -= dist;
+            moved
+        };
+        if self.fits(moved(max_dist), coll, obstacles, max_dist + SKIN) {
+            return moved(max_dist);
+        }
+        // collision detection finds the shallowest way out, which for a big move
+        // could be to the side, so search for the surface instead of pushing out
+        let (mut free, mut blocked) = (0.0, max_dist);
+        while blocked - free > SKIN / 4.0 {
+            let mid = (free + blocked) / 2.0;
+            if self.fits(moved(mid), coll, obstacles, mid + SKIN) {
+                free = mid;
+            } else {
+                blocked = mid;
+            }
+        }
+        moved(free)
+    }
+
+    /// Look for ground to stand on right below the character,
+    /// pushing the character out of it if it's overlapping.
+    fn find_ground(
+        &self,
+        pose: &mut m::Pose,
+        coll: &Collider,
+        obstacles: &[Obstacle],
+    ) -> Option<Hit> {
+        let min_normal_y = self.max_slope.cos().max(SKIN);
+        let mut probe = *pose;
+        probe.translation.y -= SKIN;
+        let hits = self.depenetrate(&mut probe, coll, obstacles, 2.0 * SKIN);
+        let ground_hit = hits
+            .iter()
+            .rev()
+            .find(|hit| hit.normal.y >= min_normal_y)
+            .copied();
+        if ground_hit.is_some() {
+            *pose = probe;
+        }
+        ground_hit
+    }
+
+    /// Check whether the character can be at the given pose without overlapping anything.
+    fn fits(
+        &self,
+        pose: m::Pose,
+        coll: &Collider,
+        obstacles: &[Obstacle],
+        max_one_way_depth: f64,
+    ) -> bool {
+        let mut pose = pose;
+        self.depenetrate(&mut pose, coll, obstacles, max_one_way_depth)
+            .is_empty()
+    }
+
+    /// Remove the part of the velocity going into a surface with the given normal.
+    fn slide(&mut self, normal: m::Vec2) {
+        let min_normal_y = self.max_slope.cos().max(SKIN);
+        let normal = if normal.y >= min_normal_y {
+            // standing on the ground, only stop falling
+            m::Vec2::unit_y()
+        } else if normal.y > 0.0 && normal.x != 0.0 {
+            // slopes too steep to stand on act like walls
+            m::Vec2::new(normal.x.signum(), 0.0)
+        } else {
+            normal
+        };
+        let into = self.velocity.dot(normal);
+        if into < 0.0 {
+            self.velocity -= into * normal;
+        }
+    }
+}
+
+/// Something the character can collide with,
+/// copied out of the graph so we can move the character freely.
+struct Obstacle {
+    node: graph::Node<Collider>,
+    collider: Collider,
+    pose: m::Pose,
+    body: Option<graph::Node<Body>>,
+    // velocity and center of mass of the body, if any
+    body_motion: Option<(Velocity, m::Vec2)>,
+}
+
+/// A collision of the character with an obstacle.
+#[derive(Clone, Copy, Debug)]
+struct Hit {
+    obstacle: usize,
+    /// Surface normal pointing towards the character.
+    normal: m::Vec2,
+    /// Point on the obstacle's surface.
+    point: m::Vec2,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::tests::{TestWorld, DT, GRAVITY};
+
+    const WIDTH: f64 = 0.2;
+    const HEIGHT: f64 = 0.4;
+
+    struct Character {
+        controller: CharacterController,
+        body: graph::Node<Body>,
+    }
+
+    impl Character {
+        /// Add a character to the world and tick once so the broad phase knows about everything.
+        fn spawn(world: &mut TestWorld, position: m::Vec2) -> Self {
+            let body = world.add_body(
+                Collider::new_rect(WIDTH, HEIGHT),
+                Body::new_kinematic(),
+                position,
+            );
+            world.tick(&GRAVITY);
+            Character {
+                controller: CharacterController::new(),
+                body,
+            }
+        }
+
+        /// Move with the given horizontal velocity for a number of frames, falling with gravity.
+        fn walk(&mut self, world: &mut TestWorld, vel_x: f64, frames: usize) {
+            for _ in 0..frames {
+                self.controller.velocity.x = vel_x;
+                self.controller.velocity += GRAVITY.0 * DT;
+                self.controller.move_and_slide(
+                    &world.physics,
+                    CharacterContext {
+                        graph: &world.graph,
+                        l_pose: &mut world.l_pose,
+                        l_body: &mut world.l_body,
+                        l_collider: &world.l_collider,
+                    },
+                    self.body,
+                    DT,
+                );
+            }
+        }
+
+        fn position(&self, world: &TestWorld) -> m::Vec2 {
+            world.pose(self.body).translation
+        }
+    }
+
+    fn add_floor(world: &mut TestWorld) -> graph::Node<Collider> {
+        world.add_static(Collider::new_rect(20.0, 1.0), m::Vec2::new(0.0, -0.5))
+    }
+
+    #[test]
+    fn walks_on_flat_ground() {
+        let mut world = TestWorld::new();
+        let floor = add_floor(&mut world);
+        let mut character = Character::spawn(&mut world, m::Vec2::new(0.0, 0.5));
+
+        character.walk(&mut world, 0.0, 60);
+        assert_eq!(
+            character.controller.ground().map(|g| g.collider),
+            Some(floor)
+        );
+        let start = character.position(&world);
+        assert!((start.y - HEIGHT / 2.0).abs() < 2.0 * SKIN, "{:?}", start);
+
+        character.walk(&mut world, 2.0, 60);
+        let end = character.position(&world);
+        assert!(character.controller.is_grounded());
+        assert!((end.x - start.x - 2.0).abs() < 0.01, "{:?}", end);
+        assert!((end.y - start.y).abs() < SKIN, "{:?}", end);
+        assert!(character.controller.velocity.y.abs() < 0.5);
+    }
+
+    #[test]
+    fn steps_up_a_small_ledge() {
+        let mut world = TestWorld::new();
+        add_floor(&mut world);
+        // a ledge lower than the default step height, starting at x = 0.5
+        let ledge_height = 0.06;
+        let ledge = world.add_static(
+            Collider::new_rect(4.0, ledge_height),
+            m::Vec2::new(2.5, ledge_height / 2.0),
+        );
+        let mut character = Character::spawn(&mut world, m::Vec2::new(0.0, HEIGHT / 2.0));
+
+        character.walk(&mut world, 0.0, 10);
+        character.walk(&mut world, 1.0, 60);
+        let pos = character.position(&world);
+        assert_eq!(
+            character.controller.ground().map(|g| g.collider),
+            Some(ledge)
+        );
+        assert!(pos.x > 0.9, "{:?}", pos);
+        assert!(
+            (pos.y - (ledge_height + HEIGHT / 2.0)).abs() < 2.0 * SKIN,
+            "{:?}",
+            pos
+        );
+    }
+
+    #[test]
+    fn slides_along_a_wall() {
+        let mut world = TestWorld::new();
+        // wall with its left side at x = 1
+        world.add_static(Collider::new_rect(0.5, 10.0), m::Vec2::new(1.25, 0.0));
+        let mut character = Character::spawn(&mut world, m::Vec2::zero());
+
+        // jump diagonally into the wall
+        character.controller.velocity = m::Vec2::new(3.0, 6.0);
+        character.walk(&mut world, 3.0, 30);
+        let pos = character.position(&world);
+        assert!((pos.x - (1.0 - WIDTH / 2.0)).abs() < SKIN, "{:?}", pos);
+        // still moving up along the wall after hitting it
+        assert!(pos.y > 1.5, "{:?}", pos);
+        assert!(!character.controller.is_grounded());
+    }
+
+    #[test]
+    fn drops_through_one_way_platform() {
+        let mut world = TestWorld::new();
+        let floor = add_floor(&mut world);
+        let platform = world.add_static(
+            Collider::new_rect(4.0, 0.2).with_one_way(m::Vec2::unit_y()),
+            m::Vec2::new(0.0, 1.0),
+        );
+        let mut character = Character::spawn(&mut world, m::Vec2::new(0.0, 1.5));
+
+        character.walk(&mut world, 0.0, 60);
+        assert_eq!(
+            character.controller.ground().map(|g| g.collider),
+            Some(platform)
+        );
+        assert!((character.position(&world).y - (1.1 + HEIGHT / 2.0)).abs() < 2.0 * SKIN);
+
+        character.controller.drop_through_one_way();
+        character.walk(&mut world, 0.0, 60);
+        assert_eq!(
+            character.controller.ground().map(|g| g.collider),
+            Some(floor)
+        );
+        assert!((character.position(&world).y - HEIGHT / 2.0).abs() < 2.0 * SKIN);
+    }
+}
@@ -1,8 +1,12 @@
 mod spatialindex;
 pub(crate) use spatialindex::SpatialIndex;
+pub use spatialindex::{BroadPhase, AABB};
 
 mod collider;
-pub use collider::{Collider, ColliderShape, ColliderType, Material};
+pub use collider::{
+    Collider, ColliderShape, ColliderType, CollisionFilter, CombineRule, ConvexPolygon, Material,
+    PairMaterial, MAX_POLYGON_POINTS,
+};
 
 pub mod shape_shape;
 pub use shape_shape::{Contact, ContactIterator, ContactResult};
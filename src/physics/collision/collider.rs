@@ -1,20 +1,45 @@
+use super::AABB;
+use crate::{
+    graph, math as m,
+    physics::{Body, Fluid},
+};
+
 /// A component that allows a game object to collide with others
 /// or act as a trigger.
 #[derive(Clone, Copy, Debug)]
 pub struct Collider {
     pub shape: ColliderShape,
     pub ty: ColliderType,
+    /// Position and orientation relative to the body (or pose, if there's no body)
+    /// the collider is attached to.
+    ///
+    /// Several colliders with different offsets can be attached to one body
+    /// to build a compound body. See [`Body`][crate::physics::Body] for details.
+    pub offset: m::Pose,
+    /// Which other colliders this one can collide with.
+    pub filter: CollisionFilter,
+    /// If set, the collider is a one-way platform that only blocks things
+    /// coming from this direction (relative to the collider's orientation).
+    /// Things moving the other way pass through it.
+    pub one_way: Option<m::Unit<m::Vec2>>,
 }
 
 impl Collider {
-    /// Create a solid circle collider from a radius.
-    pub fn new_circle(radius: f64) -> Self {
+    fn from_shape(shape: ColliderShape) -> Self {
         Collider {
-            shape: ColliderShape::Circle { r: radius },
+            shape,
             ty: ColliderType::default(),
+            offset: m::Pose::identity(),
+            filter: CollisionFilter::default(),
+            one_way: None,
         }
     }
 
+    /// Create a solid circle collider from a radius.
+    pub fn new_circle(radius: f64) -> Self {
+        Collider::from_shape(ColliderShape::Circle { r: radius })
+    }
+
     /// Create a solid rect collider with both sides set to the same length.
     pub fn new_square(side_length: f64) -> Self {
         Collider::new_rect(side_length, side_length)
@@ -24,21 +49,32 @@ impl Collider {
     pub fn new_rect(width: f64, height: f64) -> Self {
         let hw = width / 2.0;
         let hh = height / 2.0;
-        Collider {
-            shape: ColliderShape::Rect { hw, hh },
-            ty: ColliderType::default(),
-        }
+        Collider::from_shape(ColliderShape::Rect { hw, hh })
     }
 
     /// Create a solid capsule collider (a rectangle with semicircles at the ends on the x-axis).
     pub fn new_capsule(length: f64, radius: f64) -> Self {
-        Collider {
-            shape: ColliderShape::Capsule {
-                hl: length / 2.0,
-                r: radius,
-            },
-            ty: ColliderType::default(),
-        }
+        Collider::from_shape(ColliderShape::Capsule {
+            hl: length / 2.0,
+            r: radius,
+        })
+    }
+
+    /// Create a solid convex polygon collider from points around the origin.
+    ///
+    /// Bodies created from this collider get their center of mass at the polygon's centroid,
+    /// which doesn't need to be at the origin.
+    ///
+    /// # Panics
+    /// Panics under the same conditions as [`ConvexPolygon::new`][self::ConvexPolygon::new].
+    pub fn new_polygon(points: &[m::Vec2]) -> Self {
+        Collider::from_shape(ColliderShape::Polygon(ConvexPolygon::new(points)))
+    }
+
+    /// Set the offset of the collider from the body or pose it's attached to.
+    pub fn with_offset(mut self, offset: m::Pose) -> Self {
+        self.offset = offset;
+        self
     }
 
     /// Set the collider to be solid with the given surface material.
@@ -53,22 +89,116 @@ impl Collider {
         self
     }
 
+    /// Turn the collider into a volume of fluid that bodies float in.
+    pub fn fluid(mut self, fluid: Fluid) -> Self {
+        self.ty = ColliderType::Fluid(fluid);
+        self
+    }
+
+    /// Make the collider a one-way platform that only blocks things coming
+    /// from the given direction, e.g. `Vec2::unit_y()` for a platform
+    /// that can be jumped through from below and stood on.
+    ///
+    /// See [`Physics::drop_through_one_way`][crate::physics::Physics::drop_through_one_way]
+    /// for letting bodies fall through these.
+    pub fn with_one_way(mut self, direction: m::Vec2) -> Self {
+        self.one_way = Some(m::Unit::new_normalize(direction));
+        self
+    }
+
+    /// Set the collision categories the collider belongs to
+    /// and the categories it collides with. See [`CollisionFilter`][self::CollisionFilter].
+    pub fn with_collision_layers(mut self, category: u32, mask: u32) -> Self {
+        self.filter.category = category;
+        self.filter.mask = mask;
+        self
+    }
+
+    /// Prevent the collider from colliding with anything attached to the given body.
+    pub fn with_excluded_body(mut self, body: graph::Node<Body>) -> Self {
+        self.filter.excluded_body = Some(body);
+        self
+    }
+
     pub fn area(&self) -> f64 {
         match self.shape {
             ColliderShape::Circle { r } => std::f64::consts::PI * r * r,
             ColliderShape::Rect { hw, hh } => 4.0 * hw * hh,
             ColliderShape::Capsule { hl, r } => (std::f64::consts::PI * r * r) + (4.0 * hl * r),
+            ColliderShape::Polygon(poly) => poly.area(),
         }
     }
 
+    /// The center of mass of the collider's shape in its own local space,
+    /// not taking its offset into account.
+    pub fn centroid(&self) -> m::Vec2 {
+        match self.shape {
+            ColliderShape::Polygon(poly) => poly.centroid(),
+            _ => m::Vec2::zero(),
+        }
+    }
+
+    /// Moment of inertia divided by mass around the collider's origin.
     pub fn moment_of_inertia_coef(&self) -> f64 {
         // from https://en.wikipedia.org/wiki/List_of_moments_of_inertia
         match self.shape {
             ColliderShape::Circle { r } => r * r / 2.0,
             ColliderShape::Rect { hw, hh } => (hw * hw + hh * hh) / 3.0,
-            // rough estimation as a rectangle, since an accurate formula is not on wikipedia.
-            // TODO: calculate a formula by hand
-            ColliderShape::Capsule { hl, r } => (hl * hl + r * r) / 3.0,
+            ColliderShape::Capsule { hl, r } => {
+                // a rectangle plus two half-disks moved to the ends with the parallel axis theorem.
+                // a half-disk's center of mass is 4r/3pi away from its flat side
+                let rect_area = 4.0 * hl * r;
+                let circle_area = std::f64::consts::PI * r * r;
+                let rect_coef = (hl * hl + r * r) / 3.0;
+                let half_disks_coef =
+                    r * r / 2.0 + hl * hl + 8.0 * hl * r / (3.0 * std::f64::consts::PI);
+                (rect_area * rect_coef + circle_area * half_disks_coef) / (rect_area + circle_area)
+            }
+            ColliderShape::Polygon(poly) => poly.moment_of_inertia_coef(),
+        }
+    }
+
+    /// Radius of the largest circle centered at the collider's origin that fits inside it.
+    ///
+    /// Zero if the origin of a polygon is outside the polygon.
+    pub(crate) fn inner_radius(&self) -> f64 {
+        match self.shape {
+            ColliderShape::Circle { r } => r,
+            ColliderShape::Rect { hw, hh } => hw.min(hh),
+            ColliderShape::Capsule { r, .. } => r,
+            ColliderShape::Polygon(poly) => (0..poly.len)
+                .map(|i| poly.points[i].dot(poly.normal(i)))
+                .fold(f64::INFINITY, f64::min)
+                .max(0.0),
+        }
+    }
+
+    /// Compute the axis-aligned bounding box of the collider in the given pose.
+    pub fn aabb(&self, pose: &m::Pose) -> AABB {
+        let half_extents = match self.shape {
+            ColliderShape::Circle { r } => m::Vec2::new(r, r),
+            ColliderShape::Rect { hw, hh } => {
+                let x_axis = (pose.rotation * m::Vec2::unit_x()).abs();
+                let y_axis = (pose.rotation * m::Vec2::unit_y()).abs();
+                hw * x_axis + hh * y_axis
+            }
+            ColliderShape::Capsule { hl, r } => {
+                let x_axis = (pose.rotation * m::Vec2::unit_x()).abs();
+                hl * x_axis + m::Vec2::new(r, r)
+            }
+            ColliderShape::Polygon(poly) => {
+                let mut points = poly.points().iter().map(|p| *pose * *p);
+                // polygons always have at least 3 points
+                let first = points.next().unwrap();
+                let (min, max) = points.fold((first, first), |(min, max), p| {
+                    (min.min_by_component(p), max.max_by_component(p))
+                });
+                return AABB { min, max };
+            }
+        };
+        AABB {
+            min: pose.translation - half_extents,
+            max: pose.translation + half_extents,
         }
     }
 
@@ -93,14 +223,161 @@ pub enum ColliderShape {
         hl: f64,
         r: f64,
     },
+    /// A convex polygon with up to [`MAX_POLYGON_POINTS`][self::MAX_POLYGON_POINTS] points.
+    Polygon(ConvexPolygon),
+}
+
+/// The maximum number of points a [`ConvexPolygon`][self::ConvexPolygon] can have.
+pub const MAX_POLYGON_POINTS: usize = 8;
+
+/// A convex polygon with counterclockwise winding.
+///
+/// Points are stored inline in a fixed-size array rather than a `Vec`
+/// so that colliders can stay `Copy`.
+#[derive(Clone, Copy, Debug)]
+pub struct ConvexPolygon {
+    points: [m::Vec2; MAX_POLYGON_POINTS],
+    len: usize,
+}
+
+impl ConvexPolygon {
+    /// Create a convex polygon from its points in order.
+    /// Clockwise winding is accepted and reversed.
+    ///
+    /// # Panics
+    /// Panics if there are fewer than 3 or more than [`MAX_POLYGON_POINTS`][self::MAX_POLYGON_POINTS]
+    /// points, or if the polygon isn't convex.
+    pub fn new(points: &[m::Vec2]) -> Self {
+        assert!(
+            points.len() >= 3 && points.len() <= MAX_POLYGON_POINTS,
+            "A polygon must have between 3 and {} points, got {}",
+            MAX_POLYGON_POINTS,
+            points.len()
+        );
+        let mut poly = ConvexPolygon {
+            points: [m::Vec2::zero(); MAX_POLYGON_POINTS],
+            len: points.len(),
+        };
+        poly.points[..points.len()].copy_from_slice(points);
+        if poly.signed_area() < 0.0 {
+            poly.points[..points.len()].reverse();
+        }
+
+        for i in 0..poly.len {
+            let edge = poly.edge(i);
+            let next_edge = poly.edge((i + 1) % poly.len);
+            assert!(
+                edge.wedge(next_edge).xy >= 0.0,
+                "Attempted to create a concave polygon"
+            );
+        }
+
+        poly
+    }
+
+    /// Create a polygon with the same shape as a `Rect` collider.
+    pub(crate) fn from_rect(hw: f64, hh: f64) -> Self {
+        Self::new(&[
+            m::Vec2::new(hw, hh),
+            m::Vec2::new(-hw, hh),
+            m::Vec2::new(-hw, -hh),
+            m::Vec2::new(hw, -hh),
+        ])
+    }
+
+    /// Get the points of the polygon in counterclockwise order.
+    pub fn points(&self) -> &[m::Vec2] {
+        &self.points[..self.len]
+    }
+
+    /// Get the vector from point `i` to the next one.
+    pub(crate) fn edge(&self, i: usize) -> m::Vec2 {
+        self.points[(i + 1) % self.len] - self.points[i]
+    }
+
+    /// Get the outward-facing unit normal of the edge from point `i` to the next one.
+    pub(crate) fn normal(&self, i: usize) -> m::Vec2 {
+        m::right_normal(self.edge(i)).normalized()
+    }
+
+    fn signed_area(&self) -> f64 {
+        (0..self.len)
+            .map(|i| self.points[i].wedge(self.points[(i + 1) % self.len]).xy)
+            .sum::<f64>()
+            / 2.0
+    }
+
+    pub fn area(&self) -> f64 {
+        self.signed_area()
+    }
+
+    /// The center of mass of the polygon, assuming uniform density.
+    pub fn centroid(&self) -> m::Vec2 {
+        let sum = (0..self.len).fold(m::Vec2::zero(), |sum, i| {
+            let p0 = self.points[i];
+            let p1 = self.points[(i + 1) % self.len];
+            sum + p0.wedge(p1).xy * (p0 + p1)
+        });
+        sum / (6.0 * self.signed_area())
+    }
+
+    /// Moment of inertia divided by mass around the origin.
+    pub fn moment_of_inertia_coef(&self) -> f64 {
+        // sum over triangles formed by each edge and the origin,
+        // see e.g. http://paulbourke.net/geometry/polygonmesh/
+        let (numer, denom) = (0..self.len).fold((0.0, 0.0), |(numer, denom), i| {
+            let p0 = self.points[i];
+            let p1 = self.points[(i + 1) % self.len];
+            let cross = p0.wedge(p1).xy;
+            (
+                numer + cross * (p0.mag_sq() + p0.dot(p1) + p1.mag_sq()),
+                denom + cross,
+            )
+        });
+        numer / (6.0 * denom)
+    }
+}
+
+/// Rules for which colliders can collide with each other.
+///
+/// Colliders belong to the categories set in `category` and collide with the ones set in `mask`.
+/// Two colliders only collide if both of their masks contain a category of the other.
+/// By default colliders are in the first category and collide with everything.
+#[derive(Clone, Copy, Debug)]
+pub struct CollisionFilter {
+    /// Bits for the categories the collider belongs to.
+    pub category: u32,
+    /// Bits for the categories the collider collides with.
+    pub mask: u32,
+    /// A body this collider never collides with.
+    pub excluded_body: Option<graph::Node<Body>>,
+}
+
+impl CollisionFilter {
+    /// Check whether the category and mask bits of two filters allow them to collide.
+    pub fn allows(&self, other: &CollisionFilter) -> bool {
+        self.mask & other.category != 0 && other.mask & self.category != 0
+    }
+}
+
+impl Default for CollisionFilter {
+    fn default() -> Self {
+        CollisionFilter {
+            category: 1,
+            mask: u32::MAX,
+            excluded_body: None,
+        }
+    }
 }
 
 /// Type of a collider. Solid ones respond to collisions when attached to bodies.
 /// Triggers only cause an event to be sent.
+/// Fluids send events like triggers and also push on bodies inside them, see [`Fluid`].
 #[derive(Clone, Copy, Debug)]
 pub enum ColliderType {
     Solid(Material),
     Trigger,
+    Fluid(Fluid),
 }
 
 impl Default for ColliderType {
@@ -111,14 +388,23 @@ impl Default for ColliderType {
 
 /// Determines how the surface of a collider affects collisions.
 ///
-/// Using a simplified friction model where each material has its own friction
-/// coefficients (rather than the realistic model where every pair of materials
-/// would have its own coefficients).
+/// Each material has its own coefficients, which are combined with the other material's
+/// according to the materials' [`CombineRule`]s when two colliders touch.
+/// Specific pairs of materials can also be given their own coefficients
+/// by giving them an `id` and registering the pair with
+/// [`Physics::set_material_pair`][crate::physics::Physics::set_material_pair].
 #[derive(Clone, Copy, Debug)]
 pub struct Material {
     pub static_friction_coef: f64,
     pub dynamic_friction_coef: f64,
     pub restitution_coef: f64,
+    /// How friction coefficients are combined with the other material's.
+    pub friction_combine: CombineRule,
+    /// How the restitution coefficient is combined with the other material's.
+    pub restitution_combine: CombineRule,
+    /// User-defined identifier used to look up coefficients for specific pairs of materials.
+    /// 0 by default.
+    pub id: u32,
 }
 
 impl Default for Material {
@@ -127,6 +413,9 @@ impl Default for Material {
             static_friction_coef: 1.6,
             dynamic_friction_coef: 1.5,
             restitution_coef: 0.0,
+            friction_combine: CombineRule::Average,
+            restitution_combine: CombineRule::Max,
+            id: 0,
         }
     }
 }
@@ -134,22 +423,81 @@ impl Default for Material {
 impl Material {
     /// Get the static friction coefficient between this material and another.
     ///
-    /// It is computed as the average between the two materials' friction coefficients.
+    /// It is computed from the two materials' coefficients with their `friction_combine` rule.
     pub fn static_friction_with(&self, other: &Self) -> f64 {
-        (self.static_friction_coef + other.static_friction_coef) / 2.0
+        CombineRule::pick(self.friction_combine, other.friction_combine)
+            .combine(self.static_friction_coef, other.static_friction_coef)
     }
 
     /// Get the dynamic friction coefficient between this material and another.
     ///
-    /// It is computed as the average between the two materials' friction coefficients.
+    /// It is computed from the two materials' coefficients with their `friction_combine` rule.
     pub fn dynamic_friction_with(&self, other: &Self) -> f64 {
-        (self.dynamic_friction_coef + other.dynamic_friction_coef) / 2.0
+        CombineRule::pick(self.friction_combine, other.friction_combine)
+            .combine(self.dynamic_friction_coef, other.dynamic_friction_coef)
     }
 
     /// Get the restitution coefficient between this material and another.
     ///
-    /// It is computed as the largest coefficient between the two bodies.
+    /// It is computed from the two materials' coefficients with their `restitution_combine` rule.
     pub fn restitution_with(&self, other: &Self) -> f64 {
-        self.restitution_coef.max(other.restitution_coef)
+        CombineRule::pick(self.restitution_combine, other.restitution_combine)
+            .combine(self.restitution_coef, other.restitution_coef)
+    }
+
+    /// Get all the coefficients between this material and another using the combine rules.
+    pub fn combined_with(&self, other: &Self) -> PairMaterial {
+        PairMaterial {
+            static_friction_coef: self.static_friction_with(other),
+            dynamic_friction_coef: self.dynamic_friction_with(other),
+            restitution_coef: self.restitution_with(other),
+        }
+    }
+}
+
+/// Rule for combining a coefficient of two materials into one used for their collision.
+///
+/// If the two materials have different rules, the one that comes last in this list is used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CombineRule {
+    /// The arithmetic mean of the two coefficients.
+    Average,
+    /// The square root of the product of the two coefficients.
+    GeometricMean,
+    /// The smaller of the two coefficients.
+    Min,
+    /// The product of the two coefficients.
+    Multiply,
+    /// The larger of the two coefficients.
+    Max,
+}
+
+impl CombineRule {
+    /// Combine two coefficients with the rule.
+    pub fn combine(self, a: f64, b: f64) -> f64 {
+        match self {
+            CombineRule::Average => (a + b) / 2.0,
+            CombineRule::GeometricMean => (a * b).sqrt(),
+            CombineRule::Min => a.min(b),
+            CombineRule::Multiply => a * b,
+            CombineRule::Max => a.max(b),
+        }
+    }
+
+    /// Choose which of two materials' rules to use.
+    fn pick(a: Self, b: Self) -> Self {
+        a.max(b)
     }
 }
+
+/// Coefficients used for a collision between two materials.
+///
+/// Either combined from the two materials with [`Material::combined_with`]
+/// or set for a specific pair with
+/// [`Physics::set_material_pair`][crate::physics::Physics::set_material_pair].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PairMaterial {
+    pub static_friction_coef: f64,
+    pub dynamic_friction_coef: f64,
+    pub restitution_coef: f64,
+}
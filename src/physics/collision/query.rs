@@ -1,7 +1,10 @@
 //! Intersection queries for points, rays, etc. vs. colliders.
 
-use super::{Collider, ColliderShape};
-use crate::math as m;
+use super::{
+    shape_shape::{intersection_check, ContactResult},
+    Collider, ColliderShape, ConvexPolygon,
+};
+use crate::math::{self as m, Unit};
 
 /// Check whether or not a point intersects with a collider.
 pub fn point_collider_bool(point: m::Vec2, pose: &m::Pose, coll: &Collider) -> bool {
@@ -14,5 +17,453 @@ pub fn point_collider_bool(point: m::Vec2, pose: &m::Pose, coll: &Collider) -> b
             let y_dist = p_wrt_c.y.abs();
             x_dist * x_dist + y_dist * y_dist < r * r
         }
+        ColliderShape::Polygon(poly) => {
+            (0..poly.points().len()).all(|i| poly.normal(i).dot(p_wrt_c - poly.points()[i]) < 0.0)
+        }
+    }
+}
+
+/// A half-line with a starting point and a direction.
+#[derive(Clone, Copy, Debug)]
+pub struct Ray {
+    pub start: m::Vec2,
+    pub dir: Unit<m::Vec2>,
+}
+
+impl Ray {
+    /// Create a ray, normalizing the given direction.
+    pub fn new(start: m::Vec2, dir: m::Vec2) -> Self {
+        Ray {
+            start,
+            dir: Unit::new_normalize(dir),
+        }
+    }
+
+    /// Get the point at the given distance along the ray.
+    pub fn point_at(&self, t: f64) -> m::Vec2 {
+        self.start + t * *self.dir
+    }
+}
+
+/// The point where a ray hit the surface of a collider.
+#[derive(Clone, Copy, Debug)]
+pub struct RayIntersection {
+    /// Distance from the start of the ray to the hit point.
+    pub t: f64,
+    /// Surface normal at the hit point, in world space.
+    pub normal: Unit<m::Vec2>,
+}
+
+/// Find the first point where a ray hits a collider, if it does so within `max_t`.
+///
+/// A ray starting inside the collider hits it immediately at `t = 0`,
+/// with the normal pointing against the ray.
+pub fn ray_collider(
+    ray: &Ray,
+    max_t: f64,
+    pose: &m::Pose,
+    coll: &Collider,
+) -> Option<RayIntersection> {
+    if point_collider_bool(ray.start, pose, coll) {
+        return Some(RayIntersection {
+            t: 0.0,
+            normal: -ray.dir,
+        });
+    }
+
+    let local_ray = Ray {
+        start: pose.inversed() * ray.start,
+        dir: pose.rotation.reversed() * ray.dir,
+    };
+    let (t, local_normal) = match coll.shape {
+        ColliderShape::Circle { r } => ray_circle(&local_ray, r)?,
+        ColliderShape::Rect { hw, hh } => ray_rect(&local_ray, hw, hh)?,
+        ColliderShape::Capsule { hl, r } => {
+            // capsule is the union of a rectangle and two circles,
+            // the first of those that we hit is the first hit on the capsule
+            let circle_hit = |offset: f64| {
+                let ray_wrt_circle = Ray {
+                    start: local_ray.start - m::Vec2::new(offset, 0.0),
+                    dir: local_ray.dir,
+                };
+                ray_circle(&ray_wrt_circle, r)
+            };
+            [ray_rect(&local_ray, hl, r), circle_hit(hl), circle_hit(-hl)]
+                .iter()
+                .flatten()
+                .min_by(|(t1, _), (t2, _)| t1.partial_cmp(t2).expect("There was a NaN somewhere"))
+                .copied()?
+        }
+        ColliderShape::Polygon(poly) => ray_polygon(&local_ray, &poly)?,
+    };
+
+    if t > max_t {
+        return None;
+    }
+    Some(RayIntersection {
+        t,
+        normal: pose.rotation * local_normal,
+    })
+}
+
+// ray-shape functions below take the ray in shape-local space
+// and assume the ray starts outside the shape
+
+fn ray_circle(ray: &Ray, r: f64) -> Option<(f64, Unit<m::Vec2>)> {
+    // solve |start + t * dir| = r for t
+    let b = ray.start.dot(*ray.dir);
+    let c = ray.start.mag_sq() - r * r;
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let t = -b - discriminant.sqrt();
+    if t < 0.0 {
+        // circle is behind the ray
+        return None;
+    }
+    Some((t, Unit::new_normalize(ray.point_at(t))))
+}
+
+fn ray_rect(ray: &Ray, hw: f64, hh: f64) -> Option<(f64, Unit<m::Vec2>)> {
+    // intersect the slabs between parallel edges, keeping track of
+    // the latest entry and the earliest exit
+    let mut t_enter = f64::NEG_INFINITY;
+    let mut enter_normal = -ray.dir;
+    let mut t_exit = f64::INFINITY;
+    for (start, dir, half_extent, axis) in [
+        (ray.start.x, ray.dir.x, hw, m::Vec2::unit_x()),
+        (ray.start.y, ray.dir.y, hh, m::Vec2::unit_y()),
+    ]
+    .iter()
+    {
+        if *dir == 0.0 {
+            if start.abs() > *half_extent {
+                // parallel to the slab and outside it
+                return None;
+            }
+            continue;
+        }
+        let ts = [(-half_extent - start) / dir, (half_extent - start) / dir];
+        let (near, far) = if ts[0] < ts[1] {
+            (ts[0], ts[1])
+        } else {
+            (ts[1], ts[0])
+        };
+        if near > t_enter {
+            t_enter = near;
+            enter_normal = Unit::new_unchecked(-dir.signum() * *axis);
+        }
+        t_exit = t_exit.min(far);
+    }
+
+    if t_enter > t_exit || t_enter < 0.0 {
+        None
+    } else {
+        Some((t_enter, enter_normal))
+    }
+}
+
+fn ray_polygon(ray: &Ray, poly: &ConvexPolygon) -> Option<(f64, Unit<m::Vec2>)> {
+    // same idea as with rects, but with an arbitrary number of half-planes
+    let mut t_enter = f64::NEG_INFINITY;
+    let mut enter_normal = -ray.dir;
+    let mut t_exit = f64::INFINITY;
+    for (i, point) in poly.points().iter().enumerate() {
+        let normal = poly.normal(i);
+        let dist = normal.dot(ray.start - *point);
+        let normal_dot_dir = normal.dot(*ray.dir);
+        if normal_dot_dir == 0.0 {
+            if dist > 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = -dist / normal_dot_dir;
+        if normal_dot_dir < 0.0 {
+            if t > t_enter {
+                t_enter = t;
+                enter_normal = Unit::new_unchecked(normal);
+            }
+        } else {
+            t_exit = t_exit.min(t);
+        }
+    }
+
+    if t_enter > t_exit || t_enter < 0.0 {
+        None
+    } else {
+        Some((t_enter, enter_normal))
+    }
+}
+
+/// The point where a collider moving in a straight line hit another collider.
+#[derive(Clone, Copy, Debug)]
+pub struct ShapeCastIntersection {
+    /// Fraction of the translation the moving collider can travel before hitting,
+    /// between 0 and 1.
+    ///
+    /// The collider doesn't overlap the other one yet at this point,
+    /// unless they were already overlapping at the start.
+    pub t: f64,
+    /// Point of contact in world space, on the surface of the collider that was hit.
+    pub point: m::Vec2,
+    /// Surface normal of the collider that was hit at the contact point, in world space.
+    pub normal: Unit<m::Vec2>,
+}
+
+/// Distance within which [`shape_cast_collider`][self::shape_cast_collider]
+/// finds the exact time of impact.
+const SHAPE_CAST_TOLERANCE: f64 = 0.00001;
+
+/// Find the first point where `caster` moving by `translation` from `start_pose`
+/// hits a collider, if it does so before the end of the translation.
+///
+/// A caster starting inside the collider hits it immediately at `t = 0`.
+/// Rotation isn't taken into account, the caster keeps the orientation of `start_pose`.
+///
+/// The path is checked in discrete steps, so a thin collider that only grazes the edge
+/// of the swept shape can be missed.
+pub fn shape_cast_collider(
+    caster: &Collider,
+    start_pose: &m::Pose,
+    translation: m::Vec2,
+    pose: &m::Pose,
+    coll: &Collider,
+) -> Option<ShapeCastIntersection> {
+    let pose_at = |t: f64| {
+        let mut caster_pose = *start_pose * caster.offset;
+        caster_pose.translation += t * translation;
+        caster_pose
+    };
+    let check = |t: f64| intersection_check(&pose_at(t), caster, pose, coll);
+    let to_intersection = |t: f64, contact: ContactResult| {
+        let contact = contact.iter().next()?;
+        Some(ShapeCastIntersection {
+            t,
+            point: *pose * contact.offsets[1],
+            normal: -contact.normal,
+        })
+    };
+
+    let start_contact = check(0.0);
+    if !matches!(start_contact, ContactResult::Zero) {
+        return to_intersection(0.0, start_contact);
+    }
+    let distance = translation.mag();
+    if distance == 0.0 {
+        return None;
+    }
+
+    // only look at the part of the path where the bounding boxes overlap
+    let caster_aabb = caster.aabb(&pose_at(0.0));
+    let coll_aabb = coll.aabb(pose);
+    let mut t_enter: f64 = 0.0;
+    let mut t_exit: f64 = 1.0;
+    for (c_min, c_max, o_min, o_max, dir) in [
+        (
+            caster_aabb.min.x,
+            caster_aabb.max.x,
+            coll_aabb.min.x,
+            coll_aabb.max.x,
+            translation.x,
+        ),
+        (
+            caster_aabb.min.y,
+            caster_aabb.max.y,
+            coll_aabb.min.y,
+            coll_aabb.max.y,
+            translation.y,
+        ),
+    ]
+    .iter()
+    {
+        if *dir == 0.0 {
+            if c_max < o_min || o_max < c_min {
+                return None;
+            }
+            continue;
+        }
+        let ts = [(o_min - c_max) / dir, (o_max - c_min) / dir];
+        t_enter = t_enter.max(ts[0].min(ts[1]));
+        t_exit = t_exit.min(ts[0].max(ts[1]));
+    }
+    if t_enter > t_exit {
+        return None;
+    }
+
+    // advance in steps no longer than the radius r of a circle that fits inside the caster.
+    // the circles at consecutive steps overlap, so anything reaching more than
+    // r * (1 - √3/2) into the path swept by that circle is found,
+    // but thin things clipping the edges of the path between steps can be missed
+    let max_step = match caster.inner_radius() {
+        r if r > 0.0 => r,
+        // origin outside of a polygon, the box around it will have to do
+        // with no such guarantee
+        _ => {
+            let half_extents = (caster_aabb.max - caster_aabb.min) / 2.0;
+            half_extents.x.min(half_extents.y)
+        }
+    };
+    let step_count = (((t_exit - t_enter) * distance / max_step).ceil() as usize).max(1);
+    let step_t = (t_exit - t_enter) / step_count as f64;
+    let mut free_t = t_enter;
+    let (mut hit_t, mut hit_contact) = (1..=step_count)
+        .map(|i| t_enter + i as f64 * step_t)
+        .find_map(|t| match check(t) {
+            ContactResult::Zero => {
+                free_t = t;
+                None
+            }
+            contact => Some((t, contact)),
+        })?;
+
+    // narrow down the time of impact
+    while (hit_t - free_t) * distance > SHAPE_CAST_TOLERANCE {
+        let mid = (free_t + hit_t) / 2.0;
+        match check(mid) {
+            ContactResult::Zero => free_t = mid,
+            contact => {
+                hit_t = mid;
+                hit_contact = contact;
+            }
+        }
+    }
+    to_intersection(free_t, hit_contact)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_hit(hit: Option<RayIntersection>, t: f64, normal: m::Vec2) {
+        let hit = hit.expect("Ray missed");
+        assert!(
+            (hit.t - t).abs() < 0.0001,
+            "t was {}, expected {}",
+            hit.t,
+            t
+        );
+        assert!(
+            (*hit.normal - normal).mag() < 0.0001,
+            "normal was {:?}, expected {:?}",
+            *hit.normal,
+            normal
+        );
+    }
+
+    #[test]
+    fn rays_vs_shapes() {
+        let pose = m::Pose::new(m::Vec2::new(1.0, 1.0), m::Rotor2::identity());
+        let ray = Ray::new(m::Vec2::new(-2.0, 1.0), m::Vec2::unit_x());
+
+        let circle = Collider::new_circle(0.5);
+        assert_hit(
+            ray_collider(&ray, 10.0, &pose, &circle),
+            2.5,
+            -m::Vec2::unit_x(),
+        );
+        // too short
+        assert!(ray_collider(&ray, 2.0, &pose, &circle).is_none());
+        // pointing away
+        let away = Ray::new(ray.start, -m::Vec2::unit_x());
+        assert!(ray_collider(&away, 10.0, &pose, &circle).is_none());
+
+        let rect = Collider::new_rect(2.0, 1.0);
+        assert_hit(
+            ray_collider(&ray, 10.0, &pose, &rect),
+            2.0,
+            -m::Vec2::unit_x(),
+        );
+        let down = Ray::new(m::Vec2::new(1.5, 3.0), -m::Vec2::unit_y());
+        assert_hit(
+            ray_collider(&down, 10.0, &pose, &rect),
+            1.5,
+            m::Vec2::unit_y(),
+        );
+
+        // capsule rotated to stand upright, hit on the round end from above
+        let upright = m::Pose::new(
+            pose.translation,
+            m::Rotor2::from_angle(std::f64::consts::FRAC_PI_2),
+        );
+        let capsule = Collider::new_capsule(2.0, 0.5);
+        let down = Ray::new(m::Vec2::new(1.0, 4.0), -m::Vec2::unit_y());
+        assert_hit(
+            ray_collider(&down, 10.0, &upright, &capsule),
+            1.5,
+            m::Vec2::unit_y(),
+        );
+        // and on the flat side
+        assert_hit(
+            ray_collider(&ray, 10.0, &upright, &capsule),
+            2.5,
+            -m::Vec2::unit_x(),
+        );
+
+        let wedge = Collider::new_polygon(&[
+            m::Vec2::new(-1.0, -0.5),
+            m::Vec2::new(1.0, -0.5),
+            m::Vec2::new(1.0, 0.5),
+        ]);
+        let slope_normal = m::Vec2::new(-0.5, 1.0).normalized();
+        assert_hit(ray_collider(&down, 10.0, &pose, &wedge), 3.0, slope_normal);
+        assert_hit(ray_collider(&ray, 10.0, &pose, &wedge), 3.0, slope_normal);
+
+        // starting inside
+        let inside = Ray::new(pose.translation, m::Vec2::unit_y());
+        assert_hit(
+            ray_collider(&inside, 10.0, &pose, &rect),
+            0.0,
+            -m::Vec2::unit_y(),
+        );
+    }
+
+    #[test]
+    fn shape_casts() {
+        let wall_pose = m::Pose::new(m::Vec2::new(3.0, 0.0), m::Rotor2::identity());
+        let wall = Collider::new_rect(0.1, 4.0);
+        let start = m::Pose::default();
+
+        let circle = Collider::new_circle(0.5);
+        let hit = shape_cast_collider(&circle, &start, m::Vec2::new(10.0, 0.0), &wall_pose, &wall)
+            .expect("Cast missed");
+        assert!((hit.t - 0.245).abs() < 0.0001, "t was {}", hit.t);
+        assert!((*hit.normal + m::Vec2::unit_x()).mag() < 0.0001);
+        assert!((hit.point - m::Vec2::new(2.95, 0.0)).mag() < 0.001);
+        // too short
+        assert!(
+            shape_cast_collider(&circle, &start, m::Vec2::new(2.0, 0.0), &wall_pose, &wall)
+                .is_none()
+        );
+        // passing by
+        assert!(
+            shape_cast_collider(&circle, &start, m::Vec2::new(10.0, 10.0), &wall_pose, &wall)
+                .is_none()
+        );
+
+        // an upright capsule moving down hits with its round end
+        let capsule = Collider::new_capsule(2.0, 0.2);
+        let upright = m::Pose::new(
+            m::Vec2::zero(),
+            m::Rotor2::from_angle(std::f64::consts::FRAC_PI_2),
+        );
+        let floor_pose = m::Pose::new(m::Vec2::new(0.0, -3.0), m::Rotor2::identity());
+        let floor = Collider::new_rect(10.0, 1.0);
+        let hit = shape_cast_collider(
+            &capsule,
+            &upright,
+            m::Vec2::new(0.0, -4.0),
+            &floor_pose,
+            &floor,
+        )
+        .expect("Cast missed");
+        assert!((hit.t - 0.325).abs() < 0.0001, "t was {}", hit.t);
+        assert!((*hit.normal - m::Vec2::unit_y()).mag() < 0.0001);
+
+        // starting inside
+        let hit = shape_cast_collider(&circle, &wall_pose, m::Vec2::unit_y(), &wall_pose, &wall)
+            .expect("Cast missed");
+        assert_eq!(hit.t, 0.0);
     }
 }
@@ -1,4 +1,4 @@
-use super::collider::ColliderShape;
+use super::collider::{ColliderShape, ConvexPolygon};
 use crate::math::{self as m, Pose, Unit};
 use crate::physics::Collider;
 
@@ -84,6 +84,21 @@ pub fn intersection_check(
         (Capsule { hl: hl1, r: r1 }, Capsule { hl: hl2, r: r2 }) => {
             capsule_capsule(pose1, hl1, r1, pose2, hl2, r2)
         }
+        (Polygon(poly1), Polygon(poly2)) => polygon_polygon(pose1, &poly1, pose2, &poly2),
+        (Polygon(poly), Rect { hw, hh }) => {
+            polygon_polygon(pose1, &poly, pose2, &ConvexPolygon::from_rect(hw, hh))
+        }
+        (Rect { hw, hh }, Polygon(poly)) => {
+            polygon_polygon(pose1, &ConvexPolygon::from_rect(hw, hh), pose2, &poly)
+        }
+        (Polygon(poly), Circle { r }) => polygon_capsule(pose1, &poly, pose2, 0.0, r),
+        (Circle { r }, Polygon(poly)) => {
+            flip_contacts(polygon_capsule(pose2, &poly, pose1, 0.0, r))
+        }
+        (Polygon(poly), Capsule { hl, r }) => polygon_capsule(pose1, &poly, pose2, hl, r),
+        (Capsule { hl, r }, Polygon(poly)) => {
+            flip_contacts(polygon_capsule(pose2, &poly, pose1, hl, r))
+        }
     }
 }
 
@@ -866,6 +881,326 @@ fn capsule_capsule(
     }
 }
 
+//
+// POLYGON <-> POLYGON
+//
+
+fn polygon_polygon(
+    pose1: &m::Pose,
+    poly1: &ConvexPolygon,
+    pose2: &m::Pose,
+    poly2: &ConvexPolygon,
+) -> ContactResult {
+    let pose2_wrt_pose1 = pose1.inversed() * *pose2;
+    let (sep1, face1) = polygon_max_separation(poly1, poly2, &pose2_wrt_pose1);
+    if sep1 > 0.0 {
+        return ContactResult::Zero;
+    }
+    let (sep2, face2) = polygon_max_separation(poly2, poly1, &pose2_wrt_pose1.inversed());
+    if sep2 > 0.0 {
+        return ContactResult::Zero;
+    }
+
+    // prefer the first polygon as the reference so that we don't flip-flop
+    // between two almost equally good faces from one frame to the next
+    if sep2 > sep1 + 0.0005 {
+        flip_contacts(polygon_face_contacts(pose2, poly2, face2, pose1, poly1))
+    } else {
+        polygon_face_contacts(pose1, poly1, face1, pose2, poly2)
+    }
+}
+
+/// Find the face of `poly1` with the largest separation from `poly2`,
+/// returning the separation and the index of the face.
+fn polygon_max_separation(
+    poly1: &ConvexPolygon,
+    poly2: &ConvexPolygon,
+    pose2_wrt_pose1: &m::Pose,
+) -> (f64, usize) {
+    let points1 = poly1.points();
+    (0..points1.len())
+        .map(|i| {
+            let normal = poly1.normal(i);
+            let sep = poly2
+                .points()
+                .iter()
+                .map(|p| normal.dot(*pose2_wrt_pose1 * *p - points1[i]))
+                .fold(f64::MAX, f64::min);
+            (sep, i)
+        })
+        .max_by(|(sep1, _), (sep2, _)| sep1.partial_cmp(sep2).expect("There was a NaN somewhere"))
+        .unwrap()
+}
+
+/// Clip the face of the incident polygon that faces the reference face
+/// against the reference face, and generate contacts from points
+/// that end up behind the reference face.
+fn polygon_face_contacts(
+    pose_ref: &m::Pose,
+    poly_ref: &ConvexPolygon,
+    face_ref: usize,
+    pose_inc: &m::Pose,
+    poly_inc: &ConvexPolygon,
+) -> ContactResult {
+    let pose_inc_wrt_ref = pose_ref.inversed() * *pose_inc;
+    let normal = poly_ref.normal(face_ref);
+
+    // incident face is the one most opposite to the reference normal
+    let points_inc = poly_inc.points();
+    let normal_dot = |i: usize| (pose_inc_wrt_ref.rotation * poly_inc.normal(i)).dot(normal);
+    let face_inc = (0..points_inc.len())
+        .min_by(|&i, &j| {
+            normal_dot(i)
+                .partial_cmp(&normal_dot(j))
+                .expect("There was a NaN somewhere")
+        })
+        .unwrap();
+    let edge_inc = [
+        pose_inc_wrt_ref * points_inc[face_inc],
+        pose_inc_wrt_ref * points_inc[(face_inc + 1) % points_inc.len()],
+    ];
+
+    let face_start = poly_ref.points()[face_ref];
+    let face_length = poly_ref.edge(face_ref).mag();
+    let tangent = poly_ref.edge(face_ref) / face_length;
+    let start_t = tangent.dot(face_start);
+    let clipped = match clip_segment(edge_inc, tangent, start_t, start_t + face_length) {
+        Some(clipped) => clipped,
+        None => return ContactResult::Zero,
+    };
+
+    let normal_worldspace = Unit::new_unchecked(pose_ref.rotation * normal);
+    let pose_ref_wrt_inc = pose_inc_wrt_ref.inversed();
+    collect_contacts(clipped.iter().filter_map(|p| {
+        let sep = normal.dot(*p - face_start);
+        if sep < 0.0 {
+            Some(Contact {
+                normal: normal_worldspace,
+                offsets: [*p - sep * normal, pose_ref_wrt_inc * *p],
+            })
+        } else {
+            None
+        }
+    }))
+}
+
+//
+// POLYGON <-> CAPSULE
+//
+
+/// The capsule is treated as a line segment with a radius.
+/// Circles are capsules with a zero length segment.
+fn polygon_capsule(
+    pose_poly: &m::Pose,
+    poly: &ConvexPolygon,
+    pose_cap: &m::Pose,
+    hl: f64,
+    r: f64,
+) -> ContactResult {
+    let pose_cap_wrt_poly = pose_poly.inversed() * *pose_cap;
+    let pose_poly_wrt_cap = pose_cap_wrt_poly.inversed();
+    let seg = [
+        pose_cap_wrt_poly * m::Vec2::new(-hl, 0.0),
+        pose_cap_wrt_poly * m::Vec2::new(hl, 0.0),
+    ];
+    let points = poly.points();
+
+    // separation of the segment along the polygon's faces
+    let (face_sep, face) = (0..points.len())
+        .map(|i| {
+            let normal = poly.normal(i);
+            let sep = seg
+                .iter()
+                .map(|s| normal.dot(*s - points[i]))
+                .fold(f64::MAX, f64::min);
+            (sep, i)
+        })
+        .max_by(|(sep1, _), (sep2, _)| sep1.partial_cmp(sep2).expect("There was a NaN somewhere"))
+        .unwrap();
+    if face_sep >= r {
+        return ContactResult::Zero;
+    }
+
+    // separation of the polygon along the segment's normal,
+    // with the normal pointing away from the polygon
+    let cap_dir = pose_cap_wrt_poly.rotation * m::Vec2::unit_x();
+    let seg_normal = m::left_normal(cap_dir);
+    let seg_offset = seg_normal.dot(seg[0]);
+    let (proj_min, proj_max) = points
+        .iter()
+        .map(|p| seg_normal.dot(*p))
+        .fold((f64::MAX, f64::MIN), |(min, max), p| {
+            (min.min(p), max.max(p))
+        });
+    let (seg_sep, seg_normal) = if seg_offset - proj_max > proj_min - seg_offset {
+        (seg_offset - proj_max, seg_normal)
+    } else {
+        (proj_min - seg_offset, -seg_normal)
+    };
+    if seg_sep >= r {
+        return ContactResult::Zero;
+    }
+
+    if face_sep > 0.0 || seg_sep > 0.0 {
+        // the segment is outside the polygon, so the contact is between the closest points
+        let [poly_point, seg_point] = (0..points.len())
+            .map(|i| closest_points_on_segments([points[i], points[(i + 1) % points.len()]], seg))
+            .min_by(|[p1, s1], [p2, s2]| {
+                (*s1 - *p1)
+                    .mag_sq()
+                    .partial_cmp(&(*s2 - *p2).mag_sq())
+                    .expect("There was a NaN somewhere")
+            })
+            .unwrap();
+        let dist = (seg_point - poly_point).mag();
+        if dist >= r {
+            return ContactResult::Zero;
+        }
+        let normal = (seg_point - poly_point) / dist;
+
+        let face_normal = poly.normal(face);
+        if hl > 0.0 && face_normal.dot(normal) > 0.999 && cap_dir.dot(face_normal).abs() < 0.05 {
+            // lying flat on a face, two contacts are needed to keep it stable
+            return polygon_face_capsule_contacts(
+                pose_poly,
+                poly,
+                face,
+                seg,
+                r,
+                &pose_poly_wrt_cap,
+            );
+        }
+
+        return ContactResult::One(Contact {
+            normal: Unit::new_unchecked(pose_poly.rotation * normal),
+            offsets: [poly_point, pose_poly_wrt_cap * (seg_point - r * normal)],
+        });
+    }
+
+    // the segment is at least partially inside the polygon,
+    // push it out along whichever axis has the least penetration
+    if hl == 0.0 || face_sep + 0.0005 >= seg_sep {
+        return polygon_face_capsule_contacts(pose_poly, poly, face, seg, r, &pose_poly_wrt_cap);
+    }
+
+    // polygon face most aligned with the segment normal
+    let face = (0..points.len())
+        .max_by(|&i, &j| {
+            poly.normal(i)
+                .dot(seg_normal)
+                .partial_cmp(&poly.normal(j).dot(seg_normal))
+                .expect("There was a NaN somewhere")
+        })
+        .unwrap();
+    let edge = [points[face], points[(face + 1) % points.len()]];
+    let clipped = match clip_segment(edge, cap_dir, cap_dir.dot(seg[0]), cap_dir.dot(seg[1])) {
+        Some(clipped) => clipped,
+        None => return ContactResult::Zero,
+    };
+    let surface_offset = seg_normal.dot(seg[0]) - r;
+    let normal_worldspace = Unit::new_unchecked(pose_poly.rotation * seg_normal);
+    collect_contacts(clipped.iter().filter_map(|p| {
+        let depth = seg_normal.dot(*p) - surface_offset;
+        if depth > 0.0 {
+            Some(Contact {
+                normal: normal_worldspace,
+                offsets: [*p, pose_poly_wrt_cap * (*p - depth * seg_normal)],
+            })
+        } else {
+            None
+        }
+    }))
+}
+
+/// Contacts between a polygon face and a capsule segment (in polygon space) pushed out along it.
+fn polygon_face_capsule_contacts(
+    pose_poly: &m::Pose,
+    poly: &ConvexPolygon,
+    face: usize,
+    seg: [m::Vec2; 2],
+    r: f64,
+    pose_poly_wrt_cap: &m::Pose,
+) -> ContactResult {
+    let normal = poly.normal(face);
+    let normal_worldspace = Unit::new_unchecked(pose_poly.rotation * normal);
+    let face_start = poly.points()[face];
+    let contact = |s: m::Vec2| {
+        let dist = normal.dot(s - face_start);
+        if dist < r {
+            Some(Contact {
+                normal: normal_worldspace,
+                offsets: [s - dist * normal, *pose_poly_wrt_cap * (s - r * normal)],
+            })
+        } else {
+            None
+        }
+    };
+
+    let face_length = poly.edge(face).mag();
+    let tangent = poly.edge(face) / face_length;
+    let start_t = tangent.dot(face_start);
+    let contacts = match clip_segment(seg, tangent, start_t, start_t + face_length) {
+        Some(clipped) if (seg[1] - seg[0]).mag_sq() > 0.0 => {
+            collect_contacts(clipped.iter().filter_map(|s| contact(*s)))
+        }
+        _ => ContactResult::Zero,
+    };
+    match contacts {
+        // circles and segments that were clipped away entirely
+        // get a single contact at the deepest point
+        ContactResult::Zero => {
+            let deepest = if normal.dot(seg[0]) < normal.dot(seg[1]) {
+                seg[0]
+            } else {
+                seg[1]
+            };
+            match contact(deepest) {
+                Some(c) => ContactResult::One(c),
+                None => ContactResult::Zero,
+            }
+        }
+        contacts => contacts,
+    }
+}
+
+/// Closest points between two line segments, in the same order as the segments.
+/// The first segment must have a nonzero length.
+fn closest_points_on_segments(seg1: [m::Vec2; 2], seg2: [m::Vec2; 2]) -> [m::Vec2; 2] {
+    // from Real-Time Collision Detection by Christer Ericson, section 5.1.9
+    let dir1 = seg1[1] - seg1[0];
+    let dir2 = seg2[1] - seg2[0];
+    let start_dist = seg1[0] - seg2[0];
+    let len_sq1 = dir1.mag_sq();
+    let len_sq2 = dir2.mag_sq();
+    let f = dir2.dot(start_dist);
+    let c = dir1.dot(start_dist);
+    let clamp = |t: f64| t.clamp(0.0, 1.0);
+
+    let (t1, t2) = if len_sq2 == 0.0 {
+        // second segment is a point
+        (clamp(-c / len_sq1), 0.0)
+    } else {
+        let b = dir1.dot(dir2);
+        let denom = len_sq1 * len_sq2 - b * b;
+        // denom is 0 if the segments are parallel, in which case any t1 will do
+        let t1 = if denom != 0.0 {
+            clamp((b * f - c * len_sq2) / denom)
+        } else {
+            0.0
+        };
+        let t2 = (b * t1 + f) / len_sq2;
+        if t2 < 0.0 {
+            (clamp(-c / len_sq1), 0.0)
+        } else if t2 > 1.0 {
+            (clamp((b - c) / len_sq1), 1.0)
+        } else {
+            (t1, t2)
+        }
+    };
+
+    [seg1[0] + t1 * dir1, seg2[0] + t2 * dir2]
+}
+
 //
 // EDGE CLIP
 //
@@ -921,6 +1256,36 @@ fn clip_edge(target: Edge, edge: Edge) -> EdgeClipResult {
     }
 }
 
+/// Clip a line segment to the slab where `min <= axis.dot(p) <= max`.
+fn clip_segment(seg: [m::Vec2; 2], axis: m::Vec2, min: f64, max: f64) -> Option<[m::Vec2; 2]> {
+    let proj = [axis.dot(seg[0]), axis.dot(seg[1])];
+    if (proj[0] < min && proj[1] < min) || (proj[0] > max && proj[1] > max) {
+        return None;
+    }
+    let clip_point = |i: usize| {
+        let bound = if proj[i] < min {
+            min
+        } else if proj[i] > max {
+            max
+        } else {
+            return seg[i];
+        };
+        let other = 1 - i;
+        let t = (bound - proj[i]) / (proj[other] - proj[i]);
+        seg[i] + t * (seg[other] - seg[i])
+    };
+    Some([clip_point(0), clip_point(1)])
+}
+
+/// Turn up to two contacts from an iterator into a ContactResult.
+fn collect_contacts(mut contacts: impl Iterator<Item = Contact>) -> ContactResult {
+    match (contacts.next(), contacts.next()) {
+        (Some(c1), Some(c2)) => ContactResult::Two(c1, c2),
+        (Some(c), _) => ContactResult::One(c),
+        _ => ContactResult::Zero,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -984,4 +1349,71 @@ mod tests {
             _ => panic!("Intersected but shouldn't have"),
         }
     }
+
+    fn assert_normal(contacts: &ContactResult, normal: m::Vec2) {
+        for c in contacts.iter() {
+            assert!(
+                (*c.normal - normal).mag() < 0.0001,
+                "normal was {:?}, expected {:?}",
+                *c.normal,
+                normal
+            );
+        }
+    }
+
+    #[test]
+    fn polygon_contacts() {
+        let ground = Collider::new_rect(10.0, 1.0);
+        let ground_pose = m::Pose::identity();
+        let hexagon = Collider::new_polygon(
+            &(0..6)
+                .map(|i| m::Rotor2::from_angle(i as f64 * PI / 3.0) * m::Vec2::new(0.5, 0.0))
+                .collect::<Vec<_>>(),
+        );
+
+        // resting on a flat side, slightly sunk into the ground
+        let hex_pose = m::Pose::new(
+            m::Vec2::new(0.0, 0.5 + 0.5 * (PI / 3.0).sin() - 0.01),
+            m::Rotor2::identity(),
+        );
+        let contacts = intersection_check(&ground_pose, &ground, &hex_pose, &hexagon);
+        assert!(matches!(contacts, ContactResult::Two(_, _)));
+        assert_normal(&contacts, m::Vec2::unit_y());
+        // same thing the other way around
+        let contacts = intersection_check(&hex_pose, &hexagon, &ground_pose, &ground);
+        assert!(matches!(contacts, ContactResult::Two(_, _)));
+        assert_normal(&contacts, -m::Vec2::unit_y());
+        // lifted off the ground
+        let hex_pose = m::Pose::new(m::Vec2::new(0.0, 1.0), m::Rotor2::identity());
+        let contacts = intersection_check(&ground_pose, &ground, &hex_pose, &hexagon);
+        assert!(matches!(contacts, ContactResult::Zero));
+
+        // circle touching a corner
+        let circle = Collider::new_circle(0.2);
+        let circle_pose = m::Pose::new(m::Vec2::new(0.65, 0.0), m::Rotor2::identity());
+        let contacts = intersection_check(&m::Pose::identity(), &hexagon, &circle_pose, &circle);
+        assert!(matches!(contacts, ContactResult::One(_)));
+        assert_normal(&contacts, m::Vec2::unit_x());
+        let circle_pose = m::Pose::new(m::Vec2::new(0.8, 0.0), m::Rotor2::identity());
+        let contacts = intersection_check(&m::Pose::identity(), &hexagon, &circle_pose, &circle);
+        assert!(matches!(contacts, ContactResult::Zero));
+
+        // capsule lying flat on top
+        let capsule = Collider::new_capsule(0.4, 0.1);
+        let cap_pose = m::Pose::new(
+            m::Vec2::new(0.0, 0.5 * (PI / 3.0).sin() + 0.09),
+            m::Rotor2::identity(),
+        );
+        let contacts = intersection_check(&m::Pose::identity(), &hexagon, &cap_pose, &capsule);
+        assert!(matches!(contacts, ContactResult::Two(_, _)));
+        assert_normal(&contacts, m::Vec2::unit_y());
+        // and standing upright on its end
+        let cap_pose = m::Pose::new(
+            m::Vec2::new(0.0, 0.5 * (PI / 3.0).sin() + 0.29),
+            m::Rotor2::from_angle(PI / 2.0),
+        );
+        let contacts = intersection_check(&m::Pose::identity(), &hexagon, &cap_pose, &capsule);
+        assert!(matches!(contacts, ContactResult::One(_)));
+        assert_normal(&contacts, m::Vec2::unit_y());
+    }
 }
@@ -1,29 +1,294 @@
 use crate::{
-    graph::{Graph, Layer, Node, NodeRef},
-    physics::Collider,
+    graph::{Graph, Layer, Node, NodeRef, UnsafeNode},
+    math as m,
+    physics::{Body, Collider},
 };
 
+/// An axis-aligned bounding box.
+#[derive(Clone, Copy, Debug)]
+pub struct AABB {
+    pub min: m::Vec2,
+    pub max: m::Vec2,
+}
+
+impl AABB {
+    /// Check whether this box overlaps with another one.
+    pub fn overlaps(&self, other: &AABB) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+    }
+
+    /// Get the smallest box containing both this one and another one.
+    pub fn union(&self, other: &AABB) -> AABB {
+        AABB {
+            min: self.min.min_by_component(other.min),
+            max: self.max.max_by_component(other.max),
+        }
+    }
+
+    /// Grow the box by the given amount in every direction.
+    pub fn padded(&self, amount: f64) -> AABB {
+        let pad = m::Vec2::new(amount, amount);
+        AABB {
+            min: self.min - pad,
+            max: self.max + pad,
+        }
+    }
+}
+
+/// The algorithm a [`SpatialIndex`][self::SpatialIndex] uses to find pairs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BroadPhase {
+    /// Pair every collider with every other collider.
+    ///
+    /// This is O(n²) and mostly useful as a reference when debugging or benchmarking.
+    BruteForce,
+    /// Keep bounding boxes sorted along the x axis between frames
+    /// and only pair colliders whose boxes overlap.
+    ///
+    /// Because things don't move very far in one frame, the ordering
+    /// from the previous frame is almost correct and re-sorting is close to linear.
+    #[default]
+    SweepAndPrune,
+}
+
+/// A collider tracked by the spatial index along with its bounding box for the current frame.
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    node: Node<Collider>,
+    aabb: AABB,
+}
+
 /// The spatial index is responsible for detecting pairs of possibly
 /// intersecting objects for further, more accurate narrow phase inspection.
 ///
-/// For now, this is just brute force pairing every collider with every other collider.
+/// It keeps track of every collider between frames, so it should be updated
+/// once per frame with [`update`][self::SpatialIndex::update] before querying pairs.
 pub struct SpatialIndex {
-    // TODO
+    method: BroadPhase,
+    /// Extra space added around bounding boxes to account for
+    /// accelerations and other motion we can't predict from velocity.
+    pub margin: f64,
+    // sorted by aabb.min.x when using sweep and prune
+    entries: Vec<Entry>,
 }
 
 impl SpatialIndex {
+    pub fn new(method: BroadPhase) -> Self {
+        SpatialIndex {
+            method,
+            margin: 0.05,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn method(&self) -> BroadPhase {
+        self.method
+    }
+
+    /// Add new colliders to the index, remove deleted ones,
+    /// and recompute bounding boxes to cover movement over the next `dt` seconds.
+    pub fn update(
+        &mut self,
+        graph: &Graph,
+        l_pose: &Layer<m::Pose>,
+        l_body: &Layer<Body>,
+        l_collider: &Layer<Collider>,
+        dt: f64,
+    ) {
+        // remove deleted colliders.
+        // the bounds check is needed in case the index outlived the graph it was used with
+        let coll_count = l_collider.content.len();
+        self.entries
+            .retain(|e| e.node.pos().item_idx < coll_count && e.node.check(graph).is_some());
+
+        // add new colliders
+        let mut present = vec![false; coll_count];
+        for entry in &self.entries {
+            present[entry.node.pos().item_idx] = true;
+        }
+        for coll in l_collider.iter(graph) {
+            if !present[coll.pos().item_idx] {
+                self.entries.push(Entry {
+                    node: NodeRef::as_node(&coll, graph),
+                    aabb: AABB {
+                        min: m::Vec2::zero(),
+                        max: m::Vec2::zero(),
+                    },
+                });
+            }
+        }
+
+        // recompute bounding boxes, sweeping them along the path
+        // moving bodies will take during the frame
+        for entry in &mut self.entries {
+            let coll = l_collider.get_unchecked(entry.node.pos());
+            entry.aabb = match graph.get_neighbor_unchecked(&coll, l_body) {
+                Some(body) => {
+                    let pose = match graph.get_neighbor_unchecked(&body, l_pose) {
+                        Some(pose) => *pose,
+                        None => m::Pose::default(),
+                    };
+                    let end_pose = body.velocity.apply_to_pose(dt, pose);
+                    coll.aabb(&(pose * coll.offset))
+                        .union(&coll.aabb(&(end_pose * coll.offset)))
+                }
+                None => {
+                    let pose = match graph.get_neighbor_unchecked(&coll, l_pose) {
+                        Some(pose) => *pose,
+                        None => m::Pose::default(),
+                    };
+                    coll.aabb(&(pose * coll.offset))
+                }
+            }
+            .padded(self.margin);
+        }
+
+        if self.method == BroadPhase::SweepAndPrune {
+            // insertion sort, which is close to O(n) for nearly sorted data
+            for i in 1..self.entries.len() {
+                let mut j = i;
+                while j > 0 && self.entries[j - 1].aabb.min.x > self.entries[j].aabb.min.x {
+                    self.entries.swap(j - 1, j);
+                    j -= 1;
+                }
+            }
+        }
+    }
+
     /// Return all collider pairs that *might* intersect according to the spatial structure.
-    pub fn pairs(l_collider: &Layer<Collider>, graph: &Graph) -> Vec<[Node<Collider>; 2]> {
+    pub fn pairs(&self) -> Vec<[Node<Collider>; 2]> {
         let mut pairs = Vec::new();
-        let mut iter = l_collider
-            .iter(graph)
-            .map(|cref| NodeRef::as_node(&cref, graph));
-        while let Some(c0) = iter.next() {
-            let rest_iter = iter.clone();
-            for c1 in rest_iter {
-                pairs.push([c0, c1]);
+        match self.method {
+            BroadPhase::BruteForce => {
+                for (i, e0) in self.entries.iter().enumerate() {
+                    for e1 in &self.entries[i + 1..] {
+                        pairs.push([e0.node, e1.node]);
+                    }
+                }
+            }
+            BroadPhase::SweepAndPrune => {
+                for (i, e0) in self.entries.iter().enumerate() {
+                    // entries are sorted by min x,
+                    // so we can stop as soon as one starts past the end of this one
+                    for e1 in self.entries[i + 1..]
+                        .iter()
+                        .take_while(|e1| e1.aabb.min.x <= e0.aabb.max.x)
+                    {
+                        if e0.aabb.min.y <= e1.aabb.max.y && e1.aabb.min.y <= e0.aabb.max.y {
+                            pairs.push([e0.node, e1.node]);
+                        }
+                    }
+                }
             }
         }
         pairs
     }
+
+    /// Return the colliders whose bounding boxes overlap the given box.
+    ///
+    /// The bounding boxes are the ones computed in the last [`update`][self::SpatialIndex::update],
+    /// so colliders added since then aren't found, and neither are ones moved
+    /// further than their box covers. Deleted colliders may still be returned.
+    pub fn query(&self, aabb: AABB) -> impl Iterator<Item = Node<Collider>> + '_ {
+        // with sweep and prune, nothing after the first box starting past the end of this one
+        // can overlap it
+        let end = match self.method {
+            BroadPhase::BruteForce => self.entries.len(),
+            BroadPhase::SweepAndPrune => {
+                self.entries.partition_point(|e| e.aabb.min.x <= aabb.max.x)
+            }
+        };
+        self.entries[..end]
+            .iter()
+            .filter(move |e| e.aabb.overlaps(&aabb))
+            .map(|e| e.node)
+    }
+}
+
+impl Default for SpatialIndex {
+    fn default() -> Self {
+        Self::new(BroadPhase::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sweep_and_prune_matches_brute_force() {
+        let mut graph = Graph::new();
+        let mut l_pose: Layer<m::Pose> = graph.create_layer();
+        let mut l_collider: Layer<Collider> = graph.create_layer();
+        let l_body: Layer<Body> = graph.create_layer();
+
+        let positions = [
+            (0.0, 0.0),
+            (0.5, 0.5),
+            (3.0, 0.0),
+            (3.2, 5.0),
+            (-0.8, -0.2),
+            (10.0, 10.0),
+        ];
+        for (x, y) in positions.iter() {
+            let pose = l_pose.insert(
+                m::Pose::new(m::Vec2::new(*x, *y), m::Rotor2::identity()),
+                &mut graph,
+            );
+            let coll = l_collider.insert(Collider::new_square(1.0), &mut graph);
+            graph.connect(&pose, &coll);
+        }
+
+        let mut sap = SpatialIndex::new(BroadPhase::SweepAndPrune);
+        sap.update(&graph, &l_pose, &l_body, &l_collider, 1.0 / 60.0);
+        let mut brute = SpatialIndex::new(BroadPhase::BruteForce);
+        brute.update(&graph, &l_pose, &l_body, &l_collider, 1.0 / 60.0);
+
+        let sorted_idxs = |pairs: Vec<[Node<Collider>; 2]>| {
+            let mut idxs: Vec<(usize, usize)> = pairs
+                .iter()
+                .map(|[c0, c1]| {
+                    let (i0, i1) = (c0.pos().item_idx, c1.pos().item_idx);
+                    (i0.min(i1), i0.max(i1))
+                })
+                .collect();
+            idxs.sort_unstable();
+            idxs
+        };
+
+        let brute_overlapping: Vec<[Node<Collider>; 2]> = brute
+            .pairs()
+            .into_iter()
+            .filter(|[c0, c1]| {
+                let aabb = |c: &Node<Collider>| {
+                    let coll = l_collider.get_unchecked(c.pos());
+                    let pose = graph.get_neighbor(&coll, &l_pose).unwrap();
+                    coll.aabb(&pose).padded(sap.margin)
+                };
+                aabb(c0).overlaps(&aabb(c1))
+            })
+            .collect();
+
+        assert_eq!(
+            brute.pairs().len(),
+            positions.len() * (positions.len() - 1) / 2
+        );
+        assert_eq!(sorted_idxs(sap.pairs()), sorted_idxs(brute_overlapping));
+        assert_eq!(sorted_idxs(sap.pairs()), vec![(0, 1), (0, 4)]);
+
+        let query_idxs = |index: &SpatialIndex, aabb: AABB| {
+            let mut idxs: Vec<usize> = index.query(aabb).map(|c| c.pos().item_idx).collect();
+            idxs.sort_unstable();
+            idxs
+        };
+        let region = AABB {
+            min: m::Vec2::new(0.4, -1.0),
+            max: m::Vec2::new(2.6, 0.2),
+        };
+        assert_eq!(query_idxs(&sap, region), vec![0, 1, 2]);
+        assert_eq!(query_idxs(&brute, region), vec![0, 1, 2]);
+    }
 }
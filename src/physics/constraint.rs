@@ -22,12 +22,38 @@ pub struct Constraint {
     pub linear_damping: f64,
     /// Damping coefficient for angular velocity.
     pub angular_damping: f64,
-    /// Offsets from each body's center of mass (or world origin).
+    /// Offsets from each body's pose in its local space (or from the world origin).
     pub offsets: [m::Vec2; 2],
     /// Which directions to enforce the constraint in.
     pub limit: ConstraintLimit,
     /// Type of the constraint.
     pub ty: ConstraintType,
+    /// Motor driving the constraint, if any.
+    pub motor: Option<Motor>,
+    /// If true, the owner and target bodies don't collide with each other.
+    pub disable_collision: bool,
+    /// Angle in radians of the owner relative to the target (or the world)
+    /// that the angle limits of a revolute joint are measured from,
+    /// and that a prismatic joint keeps the owner at.
+    ///
+    /// If `None`, it's set to the angle between the bodies
+    /// on the first tick after the constraint is added.
+    /// The axis of a prismatic joint is converted from world space
+    /// to the target's local space at the same time.
+    pub reference_angle: Option<f64>,
+    /// Force above which the constraint breaks and is removed, if it can break.
+    ///
+    /// The force is averaged over the frame and only includes the force holding the points
+    /// in place, not torque (see [`break_torque`][self::Constraint::break_torque])
+    /// or forces from motors.
+    /// See [`ConstraintBrokenEvent`][crate::physics::ConstraintBrokenEvent].
+    pub break_force: Option<f64>,
+    /// Torque above which the constraint breaks and is removed, if it can break.
+    ///
+    /// The torque is averaged over the frame and includes the torque from the angle limits
+    /// of a revolute joint and the rotation lock of a prismatic joint,
+    /// but not torque from motors.
+    pub break_torque: Option<f64>,
 }
 
 /// Type-specific variables for constraints.
@@ -38,6 +64,83 @@ pub enum ConstraintType {
         /// The desired distance.
         distance: f64,
     },
+    /// A revolute joint, or hinge, keeps two points together
+    /// while letting the bodies rotate around them.
+    ///
+    /// Ignores the constraint's [`limit`][self::Constraint::limit].
+    Revolute {
+        /// Minimum and maximum angle in radians of the owner relative to the target
+        /// (or the world, if there's no target), if rotation is limited.
+        /// The angles are measured from the constraint's
+        /// [`reference_angle`][self::Constraint::reference_angle]
+        /// and should be between -π and π.
+        angle_limits: Option<[f64; 2]>,
+    },
+    /// A prismatic joint, or slider, lets the owner's origin point move along an axis
+    /// going through the target's origin point, and keeps the owner's orientation
+    /// relative to the target (or the world, if there's no target)
+    /// at the constraint's [`reference_angle`][self::Constraint::reference_angle].
+    ///
+    /// Ignores the constraint's [`limit`][self::Constraint::limit].
+    Prismatic {
+        /// Direction the owner is allowed to move in,
+        /// in the target's local space (or the world, if there's no target).
+        /// See [`reference_angle`][self::Constraint::reference_angle]
+        /// for how it's given before the constraint is first solved.
+        axis: m::Unit<m::Vec2>,
+        /// Minimum and maximum distance of the owner's origin point from the target's
+        /// along the axis, if translation is limited.
+        /// The minimum is enforced like [`ConstraintLimit::Gt`][self::ConstraintLimit::Gt]
+        /// and the maximum like [`ConstraintLimit::Lt`][self::ConstraintLimit::Lt].
+        translation_limits: Option<[f64; 2]>,
+    },
+}
+
+/// A motor drives the relative motion of a constrained pair of bodies,
+/// applying up to a maximum force or torque to do it.
+///
+/// Angular velocity motors drive revolute joints, and the other kinds drive
+/// distance constraints and prismatic joints.
+/// [`ConstraintBuilder`][self::ConstraintBuilder] refuses to build a constraint
+/// with a motor that doesn't match its type,
+/// and motors set to the wrong type of constraint afterwards are ignored.
+#[derive(Clone, Copy, Debug)]
+pub enum Motor {
+    /// Drive the relative angular velocity of the bodies in a revolute joint.
+    AngularVelocity {
+        /// Target angular velocity of the owner relative to the target in radians per second.
+        velocity: f64,
+        /// Maximum torque the motor can apply.
+        max_torque: f64,
+    },
+    /// Drive the speed at which the length of a distance constraint
+    /// or the position of a prismatic joint changes.
+    LinearVelocity {
+        /// Target velocity in metres per second.
+        velocity: f64,
+        /// Maximum force the motor can apply.
+        max_force: f64,
+    },
+    /// Drive the length of a distance constraint or the position of a prismatic joint
+    /// towards a target value.
+    Length {
+        /// Target length in metres.
+        length: f64,
+        /// Maximum force the motor can apply.
+        max_force: f64,
+    },
+}
+
+impl Motor {
+    /// Check whether this motor does anything on the given type of constraint.
+    pub fn can_drive(&self, ty: &ConstraintType) -> bool {
+        match self {
+            Motor::AngularVelocity { .. } => matches!(ty, ConstraintType::Revolute { .. }),
+            Motor::LinearVelocity { .. } | Motor::Length { .. } => {
+                !matches!(ty, ConstraintType::Revolute { .. })
+            }
+        }
+    }
 }
 
 /// Some constraints can be set to only work in one direction,
@@ -52,6 +155,18 @@ pub enum ConstraintLimit {
     Gt,
 }
 
+impl ConstraintLimit {
+    /// Check whether a constraint with this limit should be corrected,
+    /// given the difference between the target and actual constraint value.
+    pub(crate) fn applies_to(&self, error: f64) -> bool {
+        match self {
+            ConstraintLimit::Eq => true,
+            ConstraintLimit::Lt => error < 0.0,
+            ConstraintLimit::Gt => error > 0.0,
+        }
+    }
+}
+
 /// A builder that allows ergonomic construction of different constraints.
 #[derive(Clone, Copy, Debug)]
 pub struct ConstraintBuilder {
@@ -59,9 +174,16 @@ pub struct ConstraintBuilder {
     target: Option<graph::Node<Body>>,
     offsets: [m::Vec2; 2],
     limit: ConstraintLimit,
+    angle_limits: Option<[f64; 2]>,
+    translation_limits: Option<[f64; 2]>,
+    motor: Option<Motor>,
+    disable_collision: bool,
+    reference_angle: Option<f64>,
     compliance: f64,
     linear_damping: f64,
     angular_damping: f64,
+    break_force: Option<f64>,
+    break_torque: Option<f64>,
 }
 
 impl ConstraintBuilder {
@@ -76,9 +198,16 @@ impl ConstraintBuilder {
             target: None,
             offsets: [m::Vec2::zero(); 2],
             limit: ConstraintLimit::Eq,
+            angle_limits: None,
+            translation_limits: None,
+            motor: None,
+            disable_collision: false,
+            reference_angle: None,
             compliance: 0.0,
             linear_damping: 0.1,
             angular_damping: 0.0,
+            break_force: None,
+            break_torque: None,
         }
     }
 
@@ -89,7 +218,7 @@ impl ConstraintBuilder {
     }
 
     /// Set the origin point of the constraint on the owning body
-    /// relative to the center of mass.
+    /// relative to its pose.
     ///
     /// This has no effect on angular-only constraints.
     pub fn with_origin(mut self, point: m::Vec2) -> Self {
@@ -98,7 +227,7 @@ impl ConstraintBuilder {
     }
 
     /// Set the origin point of the constraint on the target body
-    /// relative to the center of mass,
+    /// relative to its pose,
     /// or in the world if the target is None.
     ///
     /// This has no effect on angular-only constraints.
@@ -136,6 +265,76 @@ impl ConstraintBuilder {
         self
     }
 
+    /// Limit the angle of the owner relative to the target
+    /// (or the world, if there's no target).
+    ///
+    /// The limits are measured from the angle the bodies are at when the constraint is added,
+    /// or the one given with [`with_reference_angle`][self::ConstraintBuilder::with_reference_angle].
+    ///
+    /// This only has an effect on revolute joints.
+    pub fn with_angle_limits(mut self, min: m::Angle, max: m::Angle) -> Self {
+        self.angle_limits = Some([min.rad(), max.rad()]);
+        self
+    }
+
+    /// Set the angle of the owner relative to the target (or the world)
+    /// that angle limits are measured from and that prismatic joints hold,
+    /// instead of using the angle the bodies are at when the constraint is added.
+    ///
+    /// The axis of a prismatic joint is then given in the target's local space.
+    pub fn with_reference_angle(mut self, angle: m::Angle) -> Self {
+        self.reference_angle = Some(angle.rad());
+        self
+    }
+
+    /// Limit how far along the axis the owner can move from the target.
+    ///
+    /// This only has an effect on prismatic joints.
+    pub fn with_translation_limits(mut self, min: f64, max: f64) -> Self {
+        self.translation_limits = Some([min, max]);
+        self
+    }
+
+    /// Drive the constraint with a motor.
+    ///
+    /// A distance constraint with a linear motor no longer holds its distance by itself,
+    /// instead the length is controlled by the motor.
+    /// A one-sided [`limit`][self::ConstraintBuilder::with_limit] still applies,
+    /// so e.g. [`ConstraintLimit::Lt`][self::ConstraintLimit::Lt] keeps the length
+    /// below the distance no matter what the motor does.
+    ///
+    /// # Panics
+    /// Building the constraint panics if the motor doesn't match its type
+    /// as described in [`Motor`][self::Motor].
+    pub fn with_motor(mut self, motor: Motor) -> Self {
+        self.motor = Some(motor);
+        self
+    }
+
+    /// Stop the owner and target bodies from colliding with each other.
+    pub fn disable_collision(mut self) -> Self {
+        self.disable_collision = true;
+        self
+    }
+
+    /// Make the constraint break if it has to apply more than the given force
+    /// to hold the bodies together.
+    ///
+    /// Units of force are N.
+    pub fn with_break_force(mut self, max_force: f64) -> Self {
+        self.break_force = Some(max_force);
+        self
+    }
+
+    /// Make the constraint break if it has to apply more than the given torque
+    /// to hold the bodies at an angle.
+    ///
+    /// Units of torque are Nm.
+    pub fn with_break_torque(mut self, max_torque: f64) -> Self {
+        self.break_torque = Some(max_torque);
+        self
+    }
+
     /// Build a distance constraint.
     pub fn build_distance(self, distance: f64) -> Constraint {
         self.build(ConstraintType::Distance { distance })
@@ -147,7 +346,36 @@ impl ConstraintBuilder {
         self.build(ConstraintType::Distance { distance: 0.0 })
     }
 
+    /// Build a revolute joint connecting the origin points
+    /// while allowing rotation around them.
+    pub fn build_revolute(self) -> Constraint {
+        self.build(ConstraintType::Revolute {
+            angle_limits: self.angle_limits,
+        })
+    }
+
+    /// Build a prismatic joint letting the owner slide along the given axis
+    /// relative to the target, without rotating relative to it.
+    ///
+    /// The axis is in world space, as the bodies are when the constraint is added,
+    /// unless a reference angle was set with
+    /// [`with_reference_angle`][self::ConstraintBuilder::with_reference_angle].
+    pub fn build_prismatic(self, axis: m::Vec2) -> Constraint {
+        self.build(ConstraintType::Prismatic {
+            axis: m::Unit::new_normalize(axis),
+            translation_limits: self.translation_limits,
+        })
+    }
+
     fn build(self, ty: ConstraintType) -> Constraint {
+        if let Some(motor) = self.motor {
+            assert!(
+                motor.can_drive(&ty),
+                "A {:?} motor can't drive a {:?} constraint",
+                motor,
+                ty
+            );
+        }
         Constraint {
             owner: self.owner,
             target: self.target,
@@ -157,6 +385,290 @@ impl ConstraintBuilder {
             offsets: self.offsets,
             limit: self.limit,
             ty,
+            motor: self.motor,
+            disable_collision: self.disable_collision,
+            reference_angle: self.reference_angle,
+            break_force: self.break_force,
+            break_torque: self.break_torque,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        event::Event,
+        physics::{
+            forcefield::Gravity,
+            tests::{TestWorld, GRAVITY},
+            Collider, ConstraintBrokenEvent, ConstraintHandle,
+        },
+    };
+
+    fn angle(world: &TestWorld, body: graph::Node<Body>) -> f64 {
+        m::Angle::from(world.pose(body).rotation).rad()
+    }
+
+    #[test]
+    fn revolute_limits_are_measured_from_reference_angle() {
+        let mut world = TestWorld::new();
+        let coll = Collider::new_square(1.0);
+        let body = world.add_body(coll, Body::new_dynamic(&coll, 1.0), m::Vec2::zero());
+        world.pose_mut(body).rotation = m::Rotor2::from_angle(1.0);
+        world.physics.add_constraint(
+            ConstraintBuilder::new(body)
+                .with_angle_limits(m::Angle::Rad(-0.2), m::Angle::Rad(0.2))
+                .build_revolute(),
+        );
+        let no_gravity = Gravity(m::Vec2::zero());
+
+        world.tick(&no_gravity);
+        assert!((angle(&world, body) - 1.0).abs() < 1e-6);
+
+        for &spin in &[10.0, -10.0] {
+            world.body_mut(body).velocity.angular = spin;
+            for _ in 0..30 {
+                world.tick(&no_gravity);
+            }
+            let expected = 1.0 + 0.2 * f64::signum(spin);
+            assert!(
+                (angle(&world, body) - expected).abs() < 0.01,
+                "angle {} should be at the limit {}",
+                angle(&world, body),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn prismatic_keeps_starting_orientation_and_world_axis() {
+        let mut world = TestWorld::new();
+        let coll = Collider::new_square(1.0);
+        let target = world.add_body(coll, Body::new_kinematic(), m::Vec2::new(0.0, 0.0));
+        world.pose_mut(target).rotation = m::Rotor2::from_angle(std::f64::consts::FRAC_PI_2);
+        let owner = world.add_body(coll, Body::new_dynamic(&coll, 1.0), m::Vec2::new(2.0, 0.0));
+        world.pose_mut(owner).rotation = m::Rotor2::from_angle(0.5);
+        world.physics.add_constraint(
+            ConstraintBuilder::new(owner)
+                .with_target(target)
+                .build_prismatic(m::Vec2::unit_x()),
+        );
+        world.body_mut(owner).velocity = crate::physics::Velocity {
+            linear: m::Vec2::new(1.0, 1.0),
+            angular: 1.0,
+        };
+
+        for _ in 0..30 {
+            world.tick(&Gravity(m::Vec2::zero()));
+        }
+        let pose = world.pose(owner);
+        assert!(pose.translation.x > 2.2, "{:?}", pose.translation);
+        assert!(pose.translation.y.abs() < 1e-3, "{:?}", pose.translation);
+        assert!((angle(&world, owner) - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn angular_motor_spins_revolute_joint() {
+        let mut world = TestWorld::new();
+        let coll = Collider::new_square(1.0);
+        let body = world.add_body(coll, Body::new_dynamic(&coll, 1.0), m::Vec2::zero());
+        world.physics.add_constraint(
+            ConstraintBuilder::new(body)
+                .with_motor(Motor::AngularVelocity {
+                    velocity: 2.0,
+                    max_torque: 100.0,
+                })
+                .build_revolute(),
+        );
+        for _ in 0..30 {
+            world.tick(&Gravity(m::Vec2::zero()));
+        }
+        assert!((world.body(body).velocity.angular - 2.0).abs() < 0.01);
+        assert!(world.pose(body).translation.mag() < 1e-6);
+    }
+
+    #[test]
+    fn length_motor_respects_one_sided_limit() {
+        let mut world = TestWorld::new();
+        let coll = Collider::new_circle(0.1);
+        let body = world.add_body(
+            coll,
+            Body::new_dynamic_const_mass(&coll, 1.0),
+            m::Vec2::new(1.5, 0.0),
+        );
+        let handle = world.physics.add_constraint(
+            ConstraintBuilder::new(body)
+                .with_limit(ConstraintLimit::Lt)
+                .with_motor(Motor::Length {
+                    length: 3.0,
+                    max_force: 100.0,
+                })
+                .build_distance(2.0),
+        );
+        let no_gravity = Gravity(m::Vec2::zero());
+        for _ in 0..60 {
+            world.tick(&no_gravity);
+        }
+        // the motor pulls outwards, but the limit is a maximum length
+        let length = world.pose(body).translation.mag();
+        assert!((length - 2.0).abs() < 0.01, "length was {}", length);
+
+        world.physics.get_constraint_mut(handle).unwrap().motor = Some(Motor::Length {
+            length: 1.0,
+            max_force: 100.0,
+        });
+        for _ in 0..60 {
+            world.tick(&no_gravity);
+        }
+        let length = world.pose(body).translation.mag();
+        assert!((length - 1.0).abs() < 0.01, "length was {}", length);
+    }
+
+    #[test]
+    #[should_panic]
+    fn mismatched_motor_is_rejected() {
+        let mut graph = graph::Graph::new();
+        let mut l_body: graph::Layer<Body> = graph.create_layer();
+        let body = l_body.insert(Body::new_particle(1.0), &mut graph);
+        ConstraintBuilder::new(graph::NodeRef::as_node(&body, &graph))
+            .with_motor(Motor::AngularVelocity {
+                velocity: 1.0,
+                max_torque: 1.0,
+            })
+            .build_prismatic(m::Vec2::unit_x());
+    }
+
+    fn broken(events: &[Event]) -> Vec<ConstraintBrokenEvent> {
+        events
+            .iter()
+            .filter_map(|evt| match evt {
+                Event::ConstraintBroken(brk) => Some(*brk),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// A 1 kg particle hanging from the world by a constraint with the given break force.
+    fn hang_particle(break_force: f64) -> (TestWorld, graph::Node<Body>, ConstraintHandle) {
+        let mut world = TestWorld::new();
+        let particle = world.add_body(
+            Collider::new_circle(0.05),
+            Body::new_particle(1.0),
+            m::Vec2::new(0.0, -1.0),
+        );
+        world.listen(particle);
+        let handle = world.physics.add_constraint(
+            ConstraintBuilder::new(particle)
+                .with_break_force(break_force)
+                .build_distance(1.0),
+        );
+        (world, particle, handle)
+    }
+
+    #[test]
+    fn loaded_constraint_breaks_with_force() {
+        let (mut world, particle, handle) = hang_particle(5.0);
+        let events = broken(&world.tick(&GRAVITY));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].handle, handle);
+        assert!((events[0].force - 9.81).abs() < 0.5, "{}", events[0].force);
+        assert!(world.physics.get_constraint(handle).is_none());
+        for _ in 0..30 {
+            world.tick(&GRAVITY);
         }
+        assert!(world.pose(particle).translation.y < -2.0);
+
+        let (mut world, particle, handle) = hang_particle(12.0);
+        for _ in 0..60 {
+            assert!(broken(&world.tick(&GRAVITY)).is_empty());
+        }
+        assert!(world.physics.get_constraint(handle).is_some());
+        assert!((world.pose(particle).translation.y + 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn angle_limits_break_from_torque() {
+        // a 2 m bar of 1 kg held horizontally by a hinge at its left end,
+        // which takes a torque of 9.81 Nm
+        let hinge = |break_torque: f64| {
+            let mut world = TestWorld::new();
+            let coll = Collider::new_rect(2.0, 0.1);
+            let bar = world.add_body(
+                coll,
+                Body::new_dynamic_const_mass(&coll, 1.0),
+                m::Vec2::new(1.0, 0.0),
+            );
+            world.listen(bar);
+            world.physics.add_constraint(
+                ConstraintBuilder::new(bar)
+                    .with_origin(m::Vec2::new(-1.0, 0.0))
+                    .with_angle_limits(m::Angle::Rad(0.0), m::Angle::Rad(0.0))
+                    .with_break_torque(break_torque)
+                    .build_revolute(),
+            );
+            let mut events = Vec::new();
+            for _ in 0..30 {
+                events.extend(broken(&world.tick(&GRAVITY)));
+            }
+            (events, world.pose(bar))
+        };
+
+        // the whole hinge breaks, so the bar falls
+        let (events, pose) = hinge(5.0);
+        assert_eq!(events.len(), 1);
+        assert!(
+            (events[0].torque - 9.81).abs() < 1.0,
+            "{}",
+            events[0].torque
+        );
+        assert!(pose.translation.y < -0.5, "{:?}", pose);
+
+        let (events, pose) = hinge(15.0);
+        assert!(events.is_empty());
+        assert!(pose.translation.y.abs() < 0.01, "{:?}", pose);
+        assert!(
+            m::Angle::from(pose.rotation).rad().abs() < 0.01,
+            "{:?}",
+            pose
+        );
+    }
+
+    #[test]
+    fn one_break_per_island_is_opt_in() {
+        // two particles hanging in a chain, with both links over their limit
+        let chain = |one_break_per_island: bool| {
+            let mut world = TestWorld::new();
+            world.physics.one_break_per_island = one_break_per_island;
+            let coll = Collider::new_circle(0.05);
+            let upper = world.add_body(coll, Body::new_particle(1.0), m::Vec2::new(0.0, -1.0));
+            let lower = world.add_body(coll, Body::new_particle(1.0), m::Vec2::new(0.0, -2.0));
+            world.listen(upper);
+            world.listen(lower);
+            let top = world.physics.add_constraint(
+                ConstraintBuilder::new(upper)
+                    .with_break_force(1.0)
+                    .build_distance(1.0),
+            );
+            world.physics.add_constraint(
+                ConstraintBuilder::new(lower)
+                    .with_target(upper)
+                    .with_break_force(1.0)
+                    .build_distance(1.0),
+            );
+            (broken(&world.tick(&GRAVITY)), top)
+        };
+
+        let (events, _) = chain(false);
+        assert_eq!(events.len(), 2);
+        // the top link holds twice the weight, so it's the most overloaded
+        let (events, top) = chain(true);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].handle, top);
+        assert!(
+            (events[0].force - 2.0 * 9.81).abs() < 1.0,
+            "{}",
+            events[0].force
+        );
     }
 }
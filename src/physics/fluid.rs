@@ -0,0 +1,227 @@
+//! Buoyancy and drag for bodies inside fluid colliders.
+
+use super::{Collider, ColliderShape};
+use crate::math as m;
+
+/// Properties of a fluid volume, given to a collider with
+/// [`Collider::fluid`][crate::physics::Collider::fluid].
+///
+/// Bodies overlapping a fluid are pushed against gravity with a force proportional to
+/// the submerged area of their colliders, and slowed down by drag.
+/// The buoyant force is the opposite of the force the [`ForceField`][crate::physics::ForceField]
+/// given to [`Physics::tick`][crate::physics::Physics::tick] applies to the displaced fluid,
+/// which it sees as a body at rest without a collider.
+/// Otherwise fluids act like triggers.
+///
+/// Submerged areas are exact for rectangles and polygons in rectangular or polygonal fluids.
+/// Circles and capsules are approximated with polygons.
+#[derive(Clone, Copy, Debug)]
+pub struct Fluid {
+    /// Mass per unit of area. Bodies with a lower density than the fluid float.
+    pub density: f64,
+    /// Fraction of its linear velocity a fully submerged body
+    /// with the same density as the fluid loses per second.
+    pub linear_drag: f64,
+    /// Fraction of its angular velocity a fully submerged body
+    /// with the same density as the fluid loses per second.
+    pub angular_drag: f64,
+}
+
+impl Default for Fluid {
+    fn default() -> Self {
+        Fluid {
+            density: 1.0,
+            linear_drag: 1.0,
+            angular_drag: 1.0,
+        }
+    }
+}
+
+/// Number of points used to approximate a circle, split between the ends of a capsule.
+const CIRCLE_POINTS: usize = 24;
+
+/// Find the area and centroid in world space of the part of a collider inside a fluid collider.
+///
+/// Poses are the world space poses of the colliders themselves, offsets included.
+pub(crate) fn submerged_part(
+    coll: &Collider,
+    pose: &m::Pose,
+    fluid_coll: &Collider,
+    fluid_pose: &m::Pose,
+) -> Option<(f64, m::Vec2)> {
+    let fluid_poly = collider_polygon(fluid_coll, fluid_pose);
+    let submerged = clip_convex(collider_polygon(coll, pose), &fluid_poly);
+    if submerged.len() < 3 {
+        return None;
+    }
+
+    // shoelace formula for area and centroid
+    let (area_2, centroid_6a) =
+        (0..submerged.len()).fold((0.0, m::Vec2::zero()), |(area_2, centroid_6a), i| {
+            let p0 = submerged[i];
+            let p1 = submerged[(i + 1) % submerged.len()];
+            let cross = p0.wedge(p1).xy;
+            (area_2 + cross, centroid_6a + cross * (p0 + p1))
+        });
+    if area_2 <= 0.0 {
+        return None;
+    }
+    Some((area_2 / 2.0, centroid_6a / (3.0 * area_2)))
+}
+
+/// Points of a convex polygon in counterclockwise order with the shape of the collider,
+/// in world space.
+fn collider_polygon(coll: &Collider, pose: &m::Pose) -> Vec<m::Vec2> {
+    let arc = |center: m::Vec2, r: f64, start_angle: f64, points: usize| {
+        let step = 2.0 * std::f64::consts::PI / CIRCLE_POINTS as f64;
+        (0..points).map(move |i| {
+            let angle = start_angle + i as f64 * step;
+            center + r * m::Vec2::new(angle.cos(), angle.sin())
+        })
+    };
+    let local_points: Vec<m::Vec2> = match coll.shape {
+        ColliderShape::Circle { r } => arc(m::Vec2::zero(), r, 0.0, CIRCLE_POINTS).collect(),
+        ColliderShape::Rect { hw, hh } => vec![
+            m::Vec2::new(-hw, -hh),
+            m::Vec2::new(hw, -hh),
+            m::Vec2::new(hw, hh),
+            m::Vec2::new(-hw, hh),
+        ],
+        ColliderShape::Capsule { hl, r } => {
+            let half_turn = std::f64::consts::PI;
+            let arc_points = CIRCLE_POINTS / 2 + 1;
+            arc(m::Vec2::new(hl, 0.0), r, -half_turn / 2.0, arc_points)
+                .chain(arc(m::Vec2::new(-hl, 0.0), r, half_turn / 2.0, arc_points))
+                .collect()
+        }
+        ColliderShape::Polygon(poly) => poly.points().to_vec(),
+    };
+    local_points.into_iter().map(|p| *pose * p).collect()
+}
+
+/// Clip a convex polygon to the inside of another one with the Sutherland-Hodgman algorithm.
+/// Both polygons must have counterclockwise winding.
+fn clip_convex(mut subject: Vec<m::Vec2>, clip: &[m::Vec2]) -> Vec<m::Vec2> {
+    for (i, &edge_start) in clip.iter().enumerate() {
+        let edge = clip[(i + 1) % clip.len()] - edge_start;
+        // positive on the inside of the edge
+        let dist = |p: m::Vec2| edge.wedge(p - edge_start).xy;
+
+        let input = std::mem::take(&mut subject);
+        for (j, &p0) in input.iter().enumerate() {
+            let p1 = input[(j + 1) % input.len()];
+            let (d0, d1) = (dist(p0), dist(p1));
+            if d0 >= 0.0 {
+                subject.push(p0);
+            }
+            if (d0 >= 0.0) != (d1 >= 0.0) {
+                subject.push(p0 + (d0 / (d0 - d1)) * (p1 - p0));
+            }
+        }
+        if subject.is_empty() {
+            break;
+        }
+    }
+    subject
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{
+        forcefield::FieldBody,
+        tests::{TestWorld, GRAVITY},
+        Body, ForceField, Mass,
+    };
+
+    #[test]
+    fn submerged_areas() {
+        let water = Collider::new_rect(10.0, 4.0);
+        let water_pose = m::Pose::new(m::Vec2::new(0.0, -2.0), m::Rotor2::identity());
+
+        // a box a quarter underwater
+        let rect = Collider::new_rect(2.0, 1.0);
+        let pose = m::Pose::new(m::Vec2::new(1.0, 0.25), m::Rotor2::identity());
+        let (area, centroid) = submerged_part(&rect, &pose, &water, &water_pose).unwrap();
+        assert!((area - 0.5).abs() < 0.0001, "area was {}", area);
+        assert!((centroid - m::Vec2::new(1.0, -0.125)).mag() < 0.0001);
+
+        // a diamond halfway in, where the submerged part is a triangle
+        let diamond = Collider::new_square(1.0);
+        let pose = m::Pose::new(
+            m::Vec2::zero(),
+            m::Rotor2::from_angle(std::f64::consts::FRAC_PI_4),
+        );
+        let (area, centroid) = submerged_part(&diamond, &pose, &water, &water_pose).unwrap();
+        assert!((area - 0.5).abs() < 0.0001, "area was {}", area);
+        let depth = std::f64::consts::FRAC_1_SQRT_2;
+        assert!((centroid - m::Vec2::new(0.0, -depth / 3.0)).mag() < 0.0001);
+
+        // circles are close enough
+        let circle = Collider::new_circle(1.0);
+        let pose = m::Pose::new(m::Vec2::new(0.0, -3.0), m::Rotor2::identity());
+        let (area, _) = submerged_part(&circle, &pose, &water, &water_pose).unwrap();
+        assert!(
+            (area - std::f64::consts::PI).abs() < 0.05,
+            "area was {}",
+            area
+        );
+
+        // out of the water
+        let pose = m::Pose::new(m::Vec2::new(0.0, 1.5), m::Rotor2::identity());
+        assert!(submerged_part(&circle, &pose, &water, &water_pose).is_none());
+    }
+
+    #[test]
+    fn bodies_float_at_the_depth_of_their_density() {
+        let mut world = TestWorld::new();
+        // water with its surface at y = 0
+        world.add_static(
+            Collider::new_rect(20.0, 10.0).fluid(Fluid::default()),
+            m::Vec2::new(0.0, -5.0),
+        );
+        let coll = Collider::new_square(1.0);
+        let half = world.add_body(coll, Body::new_dynamic(&coll, 0.5), m::Vec2::new(-3.0, 1.0));
+        let quarter = world.add_body(coll, Body::new_dynamic(&coll, 0.25), m::Vec2::new(0.0, 1.0));
+        let heavy = world.add_body(coll, Body::new_dynamic(&coll, 2.0), m::Vec2::new(3.0, 1.0));
+
+        for _ in 0..600 {
+            world.tick(&GRAVITY);
+        }
+        let height = |body| world.pose(body).translation.y;
+        assert!(height(half).abs() < 0.01, "{}", height(half));
+        assert!((height(quarter) - 0.25).abs() < 0.01, "{}", height(quarter));
+        assert!(height(heavy) < -5.0, "{}", height(heavy));
+    }
+
+    #[test]
+    fn buoyancy_comes_from_force_on() {
+        // a field whose force doesn't come from value_at still pushes bodies up
+        struct Weight(f64);
+        impl ForceField for Weight {
+            fn value_at(&self, _pos: m::Vec2) -> m::Vec2 {
+                m::Vec2::zero()
+            }
+
+            fn force_on(&self, body: &FieldBody) -> m::Vec2 {
+                match body.mass {
+                    Mass::Finite { mass, .. } => m::Vec2::new(0.0, -self.0 * mass),
+                    Mass::Infinite => m::Vec2::zero(),
+                }
+            }
+        }
+
+        let mut world = TestWorld::new();
+        world.add_static(
+            Collider::new_rect(20.0, 10.0).fluid(Fluid::default()),
+            m::Vec2::new(0.0, -5.0),
+        );
+        let coll = Collider::new_square(1.0);
+        let body = world.add_body(coll, Body::new_dynamic(&coll, 0.5), m::Vec2::new(0.0, 1.0));
+        for _ in 0..600 {
+            world.tick(&Weight(9.81));
+        }
+        let height = world.pose(body).translation.y;
+        assert!(height.abs() < 0.01, "{}", height);
+    }
+}
@@ -1,9 +1,50 @@
+use super::{Collider, Mass, Velocity};
 use crate::math as m;
 
 /// A (possibly) position-dependent force that is typically
 /// fed to a physics solver and applied to all rigid bodies each frame.
+///
+/// Simple fields like gravity only need to implement [`value_at`][self::ForceField::value_at],
+/// which gives an acceleration that's the same for every body at a given position.
+/// Fields that depend on the properties of the body, like drag,
+/// also override [`force_on`][self::ForceField::force_on].
+///
+/// The physics system only calls `force_on`. Besides bodies, it's used for
+/// the fluid displaced by bodies in a [`Fluid`][crate::physics::Fluid],
+/// which is pushed back as buoyancy, and for the particles of a
+/// [`ParticleFluid`][crate::physics::ParticleFluid].
+/// Neither of those have a collider.
 pub trait ForceField {
+    /// The acceleration caused by the field at a position.
     fn value_at(&self, position: m::Vec2) -> m::Vec2;
+
+    /// The force the field applies to a specific body.
+    ///
+    /// By default this is the acceleration from [`value_at`][self::ForceField::value_at]
+    /// multiplied by the body's mass.
+    fn force_on(&self, body: &FieldBody) -> m::Vec2 {
+        match body.mass {
+            Mass::Finite { mass, .. } => mass * self.value_at(body.pose.translation),
+            Mass::Infinite => m::Vec2::zero(),
+        }
+    }
+}
+
+/// The properties of a body a force field can use to decide what force to apply to it.
+#[derive(Clone, Copy, Debug)]
+pub struct FieldBody<'a> {
+    /// Pose of the body, moved to its center of mass.
+    pub pose: &'a m::Pose,
+    pub velocity: &'a Velocity,
+    pub mass: Mass,
+    /// Multiplier for the effect of gravity on this body,
+    /// see [`Body::gravity_scale`][crate::physics::Body::gravity_scale].
+    pub gravity_scale: f64,
+    /// The collider attached to the body, if any.
+    ///
+    /// Fields can use the collider's [`CollisionFilter`][crate::physics::CollisionFilter]
+    /// category to only affect some bodies.
+    pub collider: Option<&'a Collider>,
 }
 
 pub struct NoneField;
@@ -19,6 +60,10 @@ impl<F1: ForceField, F2: ForceField> ForceField for Sum<F1, F2> {
     fn value_at(&self, pos: m::Vec2) -> m::Vec2 {
         self.0.value_at(pos) + self.1.value_at(pos)
     }
+
+    fn force_on(&self, body: &FieldBody) -> m::Vec2 {
+        self.0.force_on(body) + self.1.force_on(body)
+    }
 }
 
 /// Constant gravity field over all of space.
@@ -27,6 +72,10 @@ impl ForceField for Gravity {
     fn value_at(&self, _pos: m::Vec2) -> m::Vec2 {
         self.0
     }
+
+    fn force_on(&self, body: &FieldBody) -> m::Vec2 {
+        gravity_force(self.value_at(body.pose.translation), body)
+    }
 }
 
 /// Gravity that pulls towards a specific point in space.
@@ -47,4 +96,139 @@ impl ForceField for PointGravity {
         let strength = self.strength / ((dist.mag_sq() + 1.0) * self.falloff);
         strength * dist.normalized()
     }
+
+    fn force_on(&self, body: &FieldBody) -> m::Vec2 {
+        gravity_force(self.value_at(body.pose.translation), body)
+    }
+}
+
+fn gravity_force(accel: m::Vec2, body: &FieldBody) -> m::Vec2 {
+    match body.mass {
+        Mass::Finite { mass, .. } => body.gravity_scale * mass * accel,
+        Mass::Infinite => m::Vec2::zero(),
+    }
+}
+
+/// Air resistance that slows bodies down in proportion to their size
+/// and the square of their speed relative to the air.
+///
+/// The air can also be moving, which makes this a wind
+/// that pushes bodies with a large cross-section harder than small ones.
+/// Bodies without a collider aren't affected.
+pub struct Drag {
+    /// How strongly the air resists motion.
+    pub coef: f64,
+    /// The velocity of the air.
+    pub air_velocity: m::Vec2,
+}
+impl ForceField for Drag {
+    /// Drag depends on the body, so there's no acceleration without one.
+    fn value_at(&self, _pos: m::Vec2) -> m::Vec2 {
+        m::Vec2::zero()
+    }
+
+    fn force_on(&self, body: &FieldBody) -> m::Vec2 {
+        let coll = match body.collider {
+            Some(coll) => coll,
+            None => return m::Vec2::zero(),
+        };
+        let rel_vel = body.velocity.linear - self.air_velocity;
+        let speed = rel_vel.mag();
+        if speed == 0.0 {
+            return m::Vec2::zero();
+        }
+        // approximate the cross-section with the bounding box
+        // seen from the direction of motion
+        let aabb = coll.aabb(&(*body.pose * coll.offset));
+        let extents = aabb.max - aabb.min;
+        let dir = rel_vel / speed;
+        let cross_section = dir.y.abs() * extents.x + dir.x.abs() * extents.y;
+        -self.coef * cross_section * speed * rel_vel
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{tests::TestWorld, Body};
+
+    /// Add the given bodies as unit squares far enough apart not to collide.
+    fn add_squares(world: &mut TestWorld, bodies: &[Body]) -> Vec<crate::graph::Node<Body>> {
+        let coll = Collider::new_square(1.0);
+        bodies
+            .iter()
+            .enumerate()
+            .map(|(i, body)| world.add_body(coll, *body, m::Vec2::new(3.0 * i as f64, 0.0)))
+            .collect()
+    }
+
+    #[test]
+    fn gravity_accelerates_everything_equally_unless_scaled() {
+        let mut world = TestWorld::new();
+        let coll = Collider::new_square(1.0);
+        let bodies = add_squares(
+            &mut world,
+            &[
+                Body::new_dynamic(&coll, 1.0),
+                Body::new_dynamic(&coll, 5.0),
+                Body::new_dynamic(&coll, 1.0).with_gravity_scale(0.5),
+            ],
+        );
+        let gravity = Gravity(m::Vec2::new(0.0, -10.0));
+        for _ in 0..60 {
+            world.tick(&gravity);
+        }
+        let vel_y = |i: usize| world.body(bodies[i]).velocity.linear.y;
+        assert!((vel_y(0) + 10.0).abs() < 1e-6, "{}", vel_y(0));
+        assert!((vel_y(1) + 10.0).abs() < 1e-6, "{}", vel_y(1));
+        assert!((vel_y(2) + 5.0).abs() < 1e-6, "{}", vel_y(2));
+    }
+
+    #[test]
+    fn drag_depends_on_mass_and_velocity() {
+        let mut world = TestWorld::new();
+        let coll = Collider::new_square(1.0);
+        let bodies = add_squares(
+            &mut world,
+            &[Body::new_dynamic(&coll, 1.0), Body::new_dynamic(&coll, 4.0)],
+        );
+        let field = Sum(
+            Gravity(m::Vec2::new(0.0, -10.0)),
+            Drag {
+                coef: 0.5,
+                air_velocity: m::Vec2::zero(),
+            },
+        );
+        for _ in 0..600 {
+            world.tick(&field);
+        }
+        // terminal velocity is where drag 0.5 * 1 * v² equals the weight
+        let vel_y = |i: usize| world.body(bodies[i]).velocity.linear.y;
+        assert!(
+            (vel_y(0) + (10.0f64 / 0.5).sqrt()).abs() < 0.01,
+            "{}",
+            vel_y(0)
+        );
+        assert!(
+            (vel_y(1) + (40.0f64 / 0.5).sqrt()).abs() < 0.01,
+            "{}",
+            vel_y(1)
+        );
+
+        // wind pushes bodies towards its own velocity but not past it
+        let mut world = TestWorld::new();
+        let bodies = add_squares(&mut world, &[Body::new_dynamic(&coll, 1.0)]);
+        let wind = Drag {
+            coef: 0.5,
+            air_velocity: m::Vec2::new(3.0, 0.0),
+        };
+        let mut prev_x = 0.0;
+        for _ in 0..120 {
+            world.tick(&wind);
+            let vel_x = world.body(bodies[0]).velocity.linear.x;
+            assert!(vel_x > prev_x && vel_x < 3.0, "{}", vel_x);
+            prev_x = vel_x;
+        }
+        assert!(prev_x > 2.0, "{}", prev_x);
+    }
 }
@@ -0,0 +1,756 @@
+//! Liquids simulated as many small particles with
+//! [Position Based Fluids](https://mmacklin.com/pbf_sig_preprint.pdf).
+
+use super::{
+    collision::shape_shape::intersection_check, forcefield::FieldBody, Collider, ForceField, Mass,
+    Velocity, AABB,
+};
+use crate::math::{self as m, Angle};
+
+use itertools::izip;
+
+use std::f64::consts::PI;
+
+/// Strength of the artificial pressure that keeps particles from clumping together,
+/// relative to the density constraint.
+const ARTIFICIAL_PRESSURE: f64 = 0.1;
+/// Distance where the artificial pressure is at full strength, relative to the kernel radius.
+const ARTIFICIAL_PRESSURE_DIST: f64 = 0.2;
+/// Relaxation added to the density constraint to keep it stable when particles have few
+/// neighbors, relative to the constraint gradient of a particle at rest.
+const RELAXATION: f64 = 0.01;
+
+/// A liquid made of particles that keep a constant density.
+///
+/// Particle fluids are added to the physics system with
+/// [`Physics::add_particle_fluid`][crate::physics::Physics::add_particle_fluid]
+/// and simulated as part of [`Physics::tick`][crate::physics::Physics::tick].
+/// The particles aren't entities in the graph, which lets there be thousands of them.
+/// Instead, they're created with the spawning methods on this type or a
+/// [`ParticleEmitter`][self::ParticleEmitter], and their positions can be read with
+/// [`positions`][Self::positions] for rendering.
+///
+/// Particles collide with solid colliders and push dynamic bodies around.
+/// They're moved by the force field given to the physics system like bodies without a collider
+/// and with the mass of a particle would be, see [`ForceField`][crate::physics::ForceField].
+#[derive(Clone, Debug)]
+pub struct ParticleFluid {
+    particle_radius: f64,
+    density: f64,
+    viscosity: f64,
+    surface_tension: f64,
+    max_particles: usize,
+
+    positions: Vec<m::Vec2>,
+    velocities: Vec<m::Vec2>,
+
+    // constants derived from the particle radius
+    kernel_radius: f64,
+    rest_density: f64,
+    relaxation: f64,
+    pressure_scale: f64,
+
+    // working buffers reused between substeps
+    prev_positions: Vec<m::Vec2>,
+    lambdas: Vec<f64>,
+    corrections: Vec<m::Vec2>,
+    hash: SpatialHash,
+    neighbors: Vec<usize>,
+    // index into `neighbors` where each particle's neighbors begin, plus one at the end
+    neighbor_starts: Vec<usize>,
+}
+
+impl ParticleFluid {
+    /// Create an empty fluid whose particles have the given radius.
+    ///
+    /// Particles are spaced twice their radius apart at rest.
+    /// Smaller particles make a more detailed fluid but take more of them to fill a volume.
+    pub fn new(particle_radius: f64) -> Self {
+        let spacing = 2.0 * particle_radius;
+        let kernel_radius = 2.0 * spacing;
+
+        // density and constraint gradient for a particle in the middle of a resting fluid
+        let reach = 2;
+        let lattice_offsets: Vec<m::Vec2> = (-reach..=reach)
+            .flat_map(|x| (-reach..=reach).map(move |y| spacing * m::Vec2::new(x as f64, y as f64)))
+            .filter(|offset| offset.mag() < kernel_radius)
+            .collect();
+        let rest_density: f64 = lattice_offsets
+            .iter()
+            .map(|offset| poly6(offset.mag_sq(), kernel_radius))
+            .sum();
+        let rest_gradient_sq: f64 = lattice_offsets
+            .iter()
+            .map(|offset| (spiky_gradient(*offset, kernel_radius) / rest_density).mag_sq())
+            .sum();
+
+        Self {
+            particle_radius,
+            density: 1.0,
+            viscosity: 1.0,
+            surface_tension: 0.01,
+            max_particles: usize::MAX,
+            positions: Vec::new(),
+            velocities: Vec::new(),
+            kernel_radius,
+            rest_density,
+            relaxation: RELAXATION * rest_gradient_sq,
+            pressure_scale: ARTIFICIAL_PRESSURE / rest_gradient_sq,
+            prev_positions: Vec::new(),
+            lambdas: Vec::new(),
+            corrections: Vec::new(),
+            hash: SpatialHash::new(kernel_radius),
+            neighbors: Vec::new(),
+            neighbor_starts: Vec::new(),
+        }
+    }
+
+    /// Set the mass per unit of area of the fluid. 1 by default.
+    ///
+    /// This decides how hard particles push the bodies they hit.
+    pub fn with_density(mut self, density: f64) -> Self {
+        self.density = density;
+        self
+    }
+
+    /// Set the fraction of the velocity difference to its neighbors a particle loses per second.
+    /// Higher values make the fluid flow more like honey. 1 by default.
+    pub fn with_viscosity(mut self, viscosity: f64) -> Self {
+        self.viscosity = viscosity;
+        self
+    }
+
+    /// Set how strongly particles on the surface of the fluid are pulled towards their neighbors,
+    /// between 0 and 1. Higher values make the fluid hold together in drops. 0.01 by default.
+    pub fn with_surface_tension(mut self, surface_tension: f64) -> Self {
+        self.surface_tension = surface_tension;
+        self
+    }
+
+    /// Set the maximum number of particles. Spawning more than this does nothing.
+    /// Unlimited by default.
+    pub fn with_max_particles(mut self, max_particles: usize) -> Self {
+        self.max_particles = max_particles;
+        self
+    }
+
+    /// Get the radius of the particles.
+    pub fn particle_radius(&self) -> f64 {
+        self.particle_radius
+    }
+
+    /// Get the distance between particles at rest.
+    pub fn spacing(&self) -> f64 {
+        2.0 * self.particle_radius
+    }
+
+    /// Get the mass of a single particle.
+    pub fn particle_mass(&self) -> f64 {
+        self.density * self.spacing().powi(2)
+    }
+
+    /// Get the positions of all the particles in world space.
+    pub fn positions(&self) -> &[m::Vec2] {
+        &self.positions
+    }
+
+    /// Get the velocities of all the particles, in the same order as the positions.
+    pub fn velocities(&self) -> &[m::Vec2] {
+        &self.velocities
+    }
+
+    /// Get the number of particles.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Check whether there are no particles.
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Add a particle to the fluid. Returns false if the fluid was already full.
+    pub fn spawn(&mut self, position: m::Vec2, velocity: m::Vec2) -> bool {
+        if self.positions.len() >= self.max_particles {
+            return false;
+        }
+        self.positions.push(position);
+        self.velocities.push(velocity);
+        true
+    }
+
+    /// Fill a rectangle with particles at their resting density.
+    pub fn spawn_rect(&mut self, min: m::Vec2, max: m::Vec2, velocity: m::Vec2) {
+        let spacing = self.spacing();
+        let columns = ((max.x - min.x) / spacing).floor().max(0.0) as usize;
+        let rows = ((max.y - min.y) / spacing).floor().max(0.0) as usize;
+        for row in 0..rows {
+            for col in 0..columns {
+                let offset = m::Vec2::new(col as f64 + 0.5, row as f64 + 0.5) * spacing;
+                if !self.spawn(min + offset, velocity) {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Keep only the particles for which the closure returns true,
+    /// e.g. to get rid of ones that have fallen out of the world.
+    ///
+    /// The closure is given the position and velocity of each particle.
+    pub fn retain(&mut self, mut f: impl FnMut(m::Vec2, m::Vec2) -> bool) {
+        let keep: Vec<bool> = izip!(&self.positions, &self.velocities)
+            .map(|(pos, vel)| f(*pos, *vel))
+            .collect();
+        let mut keep_positions = keep.iter();
+        self.positions.retain(|_| *keep_positions.next().unwrap());
+        let mut keep_velocities = keep.iter();
+        self.velocities.retain(|_| *keep_velocities.next().unwrap());
+    }
+
+    /// Remove all particles.
+    pub fn clear(&mut self) {
+        self.positions.clear();
+        self.velocities.clear();
+    }
+
+    //
+    // Simulation, called from Physics::tick
+    //
+
+    /// Bounding box of the particles, including their radius.
+    pub(crate) fn aabb(&self) -> Option<AABB> {
+        let first = *self.positions.first()?;
+        let (min, max) = self.positions.iter().fold((first, first), |(min, max), p| {
+            (min.min_by_component(*p), max.max_by_component(*p))
+        });
+        Some(AABB { min, max }.padded(self.particle_radius))
+    }
+
+    /// The largest speed of any particle.
+    pub(crate) fn max_speed(&self) -> f64 {
+        self.velocities.iter().map(|v| v.mag()).fold(0.0, f64::max)
+    }
+
+    /// Apply external forces and move particles to their predicted positions.
+    pub(crate) fn predict(&mut self, dt: f64, forcefield: &impl ForceField) {
+        self.prev_positions.clone_from(&self.positions);
+        let mass = self.particle_mass();
+        for (pos, vel) in self.positions.iter_mut().zip(&mut self.velocities) {
+            let force = forcefield.force_on(&FieldBody {
+                pose: &m::Pose::new(*pos, m::Rotor2::identity()),
+                velocity: &Velocity {
+                    linear: *vel,
+                    angular: 0.0,
+                },
+                mass: Mass::from(mass),
+                gravity_scale: 1.0,
+                collider: None,
+            });
+            *vel += force / mass * dt;
+            *pos += *vel * dt;
+        }
+    }
+
+    /// Find the neighbors of each particle and move them to keep the density constant.
+    pub(crate) fn solve_density(&mut self) {
+        let count = self.positions.len();
+        let h = self.kernel_radius;
+        let h_sq = h * h;
+
+        self.hash.build(&self.positions);
+        self.neighbors.clear();
+        self.neighbor_starts.clear();
+        for (i, &pos) in self.positions.iter().enumerate() {
+            self.neighbor_starts.push(self.neighbors.len());
+            let positions = &self.positions;
+            let neighbors = &mut self.neighbors;
+            self.hash
+                .query(pos - m::Vec2::new(h, h), pos + m::Vec2::new(h, h), |j| {
+                    if j != i && (positions[j] - pos).mag_sq() < h_sq {
+                        neighbors.push(j);
+                    }
+                });
+        }
+        self.neighbor_starts.push(self.neighbors.len());
+
+        // lambda is how far along its gradient each particle's density constraint needs to move
+        self.lambdas.resize(count, 0.0);
+        for i in 0..count {
+            let pos = self.positions[i];
+            let mut density = poly6(0.0, h);
+            let mut grad_self = m::Vec2::zero();
+            let mut grad_sq_sum = 0.0;
+            for &j in self.neighbors_of(i) {
+                let offset = pos - self.positions[j];
+                density += poly6(offset.mag_sq(), h);
+                let grad = spiky_gradient(offset, h) / self.rest_density;
+                grad_self += grad;
+                grad_sq_sum += grad.mag_sq();
+            }
+            let error = density / self.rest_density - 1.0;
+            // under-dense particles are on the surface,
+            // pulling them in only as much as surface tension says
+            let error = if error < 0.0 {
+                error * self.surface_tension
+            } else {
+                error
+            };
+            self.lambdas[i] = -error / (grad_self.mag_sq() + grad_sq_sum + self.relaxation);
+        }
+
+        let pressure_ref = poly6((ARTIFICIAL_PRESSURE_DIST * h).powi(2), h);
+        self.corrections.resize(count, m::Vec2::zero());
+        for i in 0..count {
+            let pos = self.positions[i];
+            let mut correction = m::Vec2::zero();
+            for &j in self.neighbors_of(i) {
+                let offset = pos - self.positions[j];
+                let artificial_pressure =
+                    -self.pressure_scale * (poly6(offset.mag_sq(), h) / pressure_ref).powi(4);
+                correction += (self.lambdas[i] + self.lambdas[j] + artificial_pressure)
+                    * spiky_gradient(offset, h);
+            }
+            self.corrections[i] = correction / self.rest_density;
+        }
+        for (pos, correction) in self.positions.iter_mut().zip(&self.corrections) {
+            *pos += *correction;
+        }
+    }
+
+    /// Push particles out of colliders, pushing the colliders' bodies back.
+    ///
+    /// Returns which obstacles were touched by at least one particle.
+    pub(crate) fn collide(
+        &mut self,
+        obstacles: &[FluidObstacle],
+        body_poses: &mut [m::Pose],
+    ) -> Vec<bool> {
+        let particle_coll = Collider::new_circle(self.particle_radius);
+        let particle_inv_mass = 1.0 / self.particle_mass();
+        // particles may have moved in the density solve since the hash was built
+        let reach = self.kernel_radius + self.particle_radius;
+
+        let mut touched = vec![false; obstacles.len()];
+        let mut candidates = Vec::new();
+        for (obstacle, touched) in obstacles.iter().zip(&mut touched) {
+            let aabb = obstacle
+                .collider
+                .aabb(&obstacle.pose(body_poses))
+                .padded(reach);
+            candidates.clear();
+            self.hash.query(aabb.min, aabb.max, |i| candidates.push(i));
+
+            for &i in &candidates {
+                let obstacle_pose = obstacle.pose(body_poses);
+                let particle_pose = m::Pose::new(self.positions[i], m::Rotor2::identity());
+                let contact = match intersection_check(
+                    &obstacle_pose,
+                    &obstacle.collider,
+                    &particle_pose,
+                    &particle_coll,
+                )
+                .iter()
+                .next()
+                {
+                    Some(contact) => *contact,
+                    None => continue,
+                };
+                let normal = *contact.normal;
+                // one-way colliders only stop particles coming from the right side
+                if let Some(dir) = obstacle.collider.one_way {
+                    if normal.dot(obstacle_pose.rotation * *dir) < 0.5 {
+                        continue;
+                    }
+                }
+                let obstacle_point = obstacle_pose * contact.offsets[0];
+                let depth = (obstacle_point - particle_pose * contact.offsets[1]).dot(normal);
+                if depth <= 0.0 {
+                    continue;
+                }
+                *touched = true;
+
+                match obstacle.body {
+                    Some(bi) => {
+                        let offset = obstacle_point - body_poses[bi].translation;
+                        let offset_wedge_normal = offset.wedge(normal).xy;
+                        let eff_inv_mass = obstacle.inv_mass
+                            + obstacle.inv_mom_inertia * offset_wedge_normal.powi(2);
+                        let lambda = depth / (particle_inv_mass + eff_inv_mass);
+                        self.positions[i] += particle_inv_mass * lambda * normal;
+                        body_poses[bi].append_translation(-obstacle.inv_mass * lambda * normal);
+                        body_poses[bi].prepend_rotation(
+                            Angle::Rad(-obstacle.inv_mom_inertia * lambda * offset_wedge_normal)
+                                .into(),
+                        );
+                    }
+                    None => {
+                        self.positions[i] += depth * normal;
+                    }
+                }
+            }
+        }
+        touched
+    }
+
+    /// Compute velocities from the distance moved during the substep and apply viscosity.
+    pub(crate) fn update_velocities(&mut self, dt: f64) {
+        let inv_dt = 1.0 / dt;
+        for (vel, pos, prev_pos) in
+            izip!(&mut self.velocities, &self.positions, &self.prev_positions)
+        {
+            *vel = (*pos - *prev_pos) * inv_dt;
+        }
+
+        if self.viscosity <= 0.0 || self.neighbor_starts.len() != self.positions.len() + 1 {
+            return;
+        }
+        // blend velocities towards the average of the neighbors (XSPH viscosity)
+        let h = self.kernel_radius;
+        let blend = (self.viscosity * dt).min(1.0);
+        let count = self.positions.len();
+        self.corrections.resize(count, m::Vec2::zero());
+        for i in 0..count {
+            let mut weight_sum = 0.0;
+            let mut vel_diff_sum = m::Vec2::zero();
+            for &j in self.neighbors_of(i) {
+                let weight = poly6((self.positions[i] - self.positions[j]).mag_sq(), h);
+                weight_sum += weight;
+                vel_diff_sum += weight * (self.velocities[j] - self.velocities[i]);
+            }
+            self.corrections[i] = if weight_sum > 0.0 {
+                blend * vel_diff_sum / weight_sum
+            } else {
+                m::Vec2::zero()
+            };
+        }
+        for (vel, correction) in self.velocities.iter_mut().zip(&self.corrections) {
+            *vel += *correction;
+        }
+    }
+
+    fn neighbors_of(&self, i: usize) -> &[usize] {
+        &self.neighbors[self.neighbor_starts[i]..self.neighbor_starts[i + 1]]
+    }
+}
+
+/// A collider particles collide with, gathered by the physics system.
+pub(crate) struct FluidObstacle {
+    pub collider: Collider,
+    /// Pose of the collider relative to its body, or in world space if it has no body.
+    pub offset: m::Pose,
+    /// Index of the body in the solver's pose buffer.
+    pub body: Option<usize>,
+    pub inv_mass: f64,
+    pub inv_mom_inertia: f64,
+}
+
+impl FluidObstacle {
+    fn pose(&self, body_poses: &[m::Pose]) -> m::Pose {
+        match self.body {
+            Some(bi) => body_poses[bi] * self.offset,
+            None => self.offset,
+        }
+    }
+}
+
+/// A source of particles shooting out of a line at a constant velocity, like a tap or a hose.
+#[derive(Clone, Copy, Debug)]
+pub struct ParticleEmitter {
+    /// Center of the line particles come out of.
+    pub position: m::Vec2,
+    /// Velocity of the emitted particles. The line is perpendicular to it.
+    pub velocity: m::Vec2,
+    /// Width of the stream of particles.
+    pub width: f64,
+    // how far the latest row of particles has traveled
+    distance: f64,
+}
+
+impl ParticleEmitter {
+    pub fn new(position: m::Vec2, velocity: m::Vec2, width: f64) -> Self {
+        Self {
+            position,
+            velocity,
+            width,
+            distance: 0.0,
+        }
+    }
+
+    /// Spawn rows of particles into the fluid so that the stream they make
+    /// has the fluid's resting density. Call this once per frame.
+    pub fn emit(&mut self, fluid: &mut ParticleFluid, dt: f64) {
+        let speed = self.velocity.mag();
+        if speed == 0.0 {
+            return;
+        }
+        let spacing = fluid.spacing();
+        let dir = self.velocity / speed;
+        let across = m::left_normal(dir);
+        let row_len = ((self.width / spacing).round() as usize).max(1);
+        let row_start = -((row_len - 1) as f64) * spacing / 2.0;
+
+        self.distance += speed * dt;
+        while self.distance >= spacing {
+            self.distance -= spacing;
+            // rows that would have come out earlier in the frame have already moved
+            let row_center = self.position + self.distance * dir;
+            for k in 0..row_len {
+                let offset = (row_start + k as f64 * spacing) * across;
+                fluid.spawn(row_center + offset, self.velocity);
+            }
+        }
+    }
+}
+
+/// Smoothing kernel for density, (h² - r²)³ normalized to integrate to one in 2D.
+fn poly6(dist_sq: f64, h: f64) -> f64 {
+    let h_sq = h * h;
+    if dist_sq >= h_sq {
+        return 0.0;
+    }
+    4.0 / (PI * h_sq.powi(4)) * (h_sq - dist_sq).powi(3)
+}
+
+/// Gradient of the spiky kernel (h - r)³ normalized in 2D, which is steep near the center
+/// so particles close together push each other apart strongly.
+fn spiky_gradient(offset: m::Vec2, h: f64) -> m::Vec2 {
+    let dist = offset.mag();
+    if dist >= h || dist == 0.0 {
+        return m::Vec2::zero();
+    }
+    -30.0 / (PI * h.powi(5)) * (h - dist).powi(2) * offset / dist
+}
+
+/// Finds nearby particles quickly by sorting them into a grid of cells,
+/// which are stored in a hash table so the grid can be infinite.
+#[derive(Clone, Debug)]
+pub(crate) struct SpatialHash {
+    cell_size: f64,
+    // index into `entries` where each bucket begins, plus one at the end
+    bucket_starts: Vec<usize>,
+    // particle indices sorted by bucket, with the cell each particle is in
+    entries: Vec<(usize, [i64; 2])>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f64) -> Self {
+        Self {
+            cell_size,
+            bucket_starts: Vec::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    fn cell(&self, point: m::Vec2) -> [i64; 2] {
+        [
+            (point.x / self.cell_size).floor() as i64,
+            (point.y / self.cell_size).floor() as i64,
+        ]
+    }
+
+    fn bucket(&self, cell: [i64; 2]) -> usize {
+        let hash = (cell[0].wrapping_mul(92_837_111)) ^ (cell[1].wrapping_mul(689_287_499));
+        (hash as u64 % (self.bucket_starts.len() - 1) as u64) as usize
+    }
+
+    /// Sort the points into buckets, replacing whatever was there before.
+    pub fn build(&mut self, points: &[m::Vec2]) {
+        // twice as many buckets as points keeps collisions rare
+        let bucket_count = 2 * points.len() + 1;
+        self.bucket_starts.clear();
+        self.bucket_starts.resize(bucket_count + 1, 0);
+
+        // counting sort by bucket
+        let cells: Vec<[i64; 2]> = points.iter().map(|p| self.cell(*p)).collect();
+        let buckets: Vec<usize> = cells.iter().map(|c| self.bucket(*c)).collect();
+        for &b in &buckets {
+            self.bucket_starts[b + 1] += 1;
+        }
+        for b in 0..bucket_count {
+            self.bucket_starts[b + 1] += self.bucket_starts[b];
+        }
+        let mut next = self.bucket_starts.clone();
+        self.entries.clear();
+        self.entries.resize(points.len(), (0, [0, 0]));
+        for (i, (&b, &cell)) in buckets.iter().zip(&cells).enumerate() {
+            self.entries[next[b]] = (i, cell);
+            next[b] += 1;
+        }
+    }
+
+    /// Call a closure with the index of every point in cells overlapping the given box.
+    ///
+    /// This includes some points outside the box, so the caller should check distances.
+    pub fn query(&self, min: m::Vec2, max: m::Vec2, mut f: impl FnMut(usize)) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let (min, max) = (self.cell(min), self.cell(max));
+        for x in min[0]..=max[0] {
+            for y in min[1]..=max[1] {
+                let b = self.bucket([x, y]);
+                // different cells can share a bucket, so check the cell too
+                for &(i, cell) in &self.entries[self.bucket_starts[b]..self.bucket_starts[b + 1]] {
+                    if cell == [x, y] {
+                        f(i);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{
+        tests::{TestWorld, DT, GRAVITY},
+        Body,
+    };
+
+    /// Add a 4 meter wide box open at the top, with the inside of the floor at y = 0
+    /// and the walls at x = ±2.
+    fn add_walled_box(world: &mut TestWorld) {
+        world.add_static(Collider::new_rect(5.0, 1.0), m::Vec2::new(0.0, -0.5));
+        for side in &[-1.0, 1.0] {
+            world.add_static(Collider::new_rect(0.5, 4.0), m::Vec2::new(side * 2.25, 2.0));
+        }
+    }
+
+    #[test]
+    fn spatial_hash_matches_brute_force() {
+        let points: Vec<m::Vec2> = (0..200)
+            .map(|i| {
+                let t = i as f64;
+                m::Vec2::new((t * 0.37).sin() * 5.0, (t * 0.91).cos() * 5.0 - 3.0)
+            })
+            .collect();
+        let mut hash = SpatialHash::new(0.5);
+        hash.build(&points);
+
+        for center in &[
+            m::Vec2::zero(),
+            m::Vec2::new(-4.0, 1.0),
+            m::Vec2::new(2.5, -7.9),
+        ] {
+            let radius = 0.8;
+            let mut found = Vec::new();
+            hash.query(
+                *center - m::Vec2::new(radius, radius),
+                *center + m::Vec2::new(radius, radius),
+                |i| {
+                    if (points[i] - *center).mag() < radius {
+                        found.push(i);
+                    }
+                },
+            );
+            found.sort_unstable();
+            let expected: Vec<usize> = (0..points.len())
+                .filter(|i| (points[*i] - *center).mag() < radius)
+                .collect();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn kernel_gives_rest_density_in_lattice() {
+        // a particle in the middle of a lattice has zero density error,
+        // so a resting block of fluid doesn't move on its own
+        let mut fluid = ParticleFluid::new(0.05);
+        fluid.spawn_rect(
+            m::Vec2::new(-0.5, -0.5),
+            m::Vec2::new(0.5, 0.5),
+            m::Vec2::zero(),
+        );
+        let before = fluid.positions().to_vec();
+        fluid.prev_positions.clone_from(&fluid.positions);
+        fluid.solve_density();
+        let middle = before
+            .iter()
+            .position(|p| (*p - m::Vec2::new(0.05, 0.05)).mag() < 1e-9)
+            .unwrap();
+        assert!((fluid.positions()[middle] - before[middle]).mag() < 1e-9);
+    }
+
+    #[test]
+    fn walls_hold_the_fluid_in() {
+        let mut world = TestWorld::new();
+        add_walled_box(&mut world);
+        let mut fluid = ParticleFluid::new(0.05);
+        fluid.spawn_rect(
+            m::Vec2::new(-1.5, 0.5),
+            m::Vec2::new(1.5, 1.5),
+            m::Vec2::new(3.0, 0.0),
+        );
+        let count = fluid.len();
+        let fluid = world.physics.add_particle_fluid(fluid);
+
+        for _ in 0..300 {
+            world.tick(&GRAVITY);
+            let fluid = world.physics.particle_fluid(fluid).unwrap();
+            for pos in fluid.positions() {
+                assert!(pos.x.abs() < 2.0 && pos.y > 0.0, "escaped to {:?}", pos);
+            }
+        }
+        let fluid = world.physics.particle_fluid(fluid).unwrap();
+        assert_eq!(fluid.len(), count);
+        // settled on the floor
+        assert!(fluid.positions().iter().all(|pos| pos.y < 1.5));
+    }
+
+    #[test]
+    fn light_bodies_float() {
+        let mut world = TestWorld::new();
+        add_walled_box(&mut world);
+        let mut fluid = ParticleFluid::new(0.05);
+        fluid.spawn_rect(
+            m::Vec2::new(-2.0, 0.0),
+            m::Vec2::new(2.0, 1.0),
+            m::Vec2::zero(),
+        );
+        world.physics.add_particle_fluid(fluid);
+        let coll = Collider::new_square(0.5);
+        let body = world.add_body(coll, Body::new_dynamic(&coll, 0.3), m::Vec2::new(0.0, 1.5));
+
+        for _ in 0..300 {
+            world.tick(&GRAVITY);
+        }
+        // the surface is at about y = 1, and the box sinks less than halfway in
+        let height = world.pose(body).translation.y;
+        assert!(height > 0.9, "sank to {}", height);
+    }
+
+    #[test]
+    fn emitters_stop_at_max_particles() {
+        let mut world = TestWorld::new();
+        add_walled_box(&mut world);
+        let max_particles = 150;
+        let fluid = world
+            .physics
+            .add_particle_fluid(ParticleFluid::new(0.05).with_max_particles(max_particles));
+        let mut emitter =
+            ParticleEmitter::new(m::Vec2::new(0.0, 2.0), m::Vec2::new(0.0, -3.0), 0.5);
+
+        let mut pour = |world: &mut TestWorld, frames: usize| {
+            for _ in 0..frames {
+                let fluid = world.physics.particle_fluid_mut(fluid).unwrap();
+                emitter.emit(fluid, DT);
+                assert!(fluid.len() <= max_particles);
+                world.tick(&GRAVITY);
+            }
+            world.physics.particle_fluid(fluid).unwrap().len()
+        };
+        // 5 particles per row and 1 row every 2 frames
+        assert_eq!(pour(&mut world, 20), 50);
+        assert_eq!(pour(&mut world, 100), max_particles);
+
+        // removing particles makes room for more
+        let poured = world.physics.particle_fluid_mut(fluid).unwrap();
+        poured.retain(|pos, _| pos.x < 0.0);
+        let left = poured.len();
+        assert!(left > 0 && left < max_particles, "{} left", left);
+        assert!(poured.positions().iter().all(|pos| pos.x < 0.0));
+        assert_eq!(pour(&mut world, 100), max_particles);
+    }
+}
@@ -0,0 +1,423 @@
+//! Ropes and chains made of particles linked by distance constraints.
+
+use super::{Body, Collider, ConstraintBuilder, ConstraintHandle, ConstraintLimit, Physics};
+use crate::{graph, math as m};
+
+/// A builder for a [`Rope`][self::Rope] stretched between two points.
+///
+/// The rope is made of [particles][crate::physics::Body::new_particle]
+/// connected in a line by distance constraints.
+/// Its ends can be attached to bodies or pinned to points in the world.
+#[derive(Clone, Copy, Debug)]
+pub struct RopeBuilder {
+    start: m::Vec2,
+    end: m::Vec2,
+    segments: usize,
+    mass: f64,
+    compliance: f64,
+    bending_compliance: Option<f64>,
+    damping: f64,
+    collider: Option<Collider>,
+    break_force: Option<f64>,
+    start_attachment: Option<RopeAttachment>,
+    end_attachment: Option<RopeAttachment>,
+}
+
+/// Something the end of a rope is attached to.
+#[derive(Clone, Copy, Debug)]
+pub enum RopeAttachment {
+    /// A point on a body, relative to the body's pose.
+    Body {
+        body: graph::Node<Body>,
+        point: m::Vec2,
+    },
+    /// A fixed point in the world.
+    World(m::Vec2),
+}
+
+impl RopeBuilder {
+    /// Start building a rope going from `start` to `end` in world space.
+    ///
+    /// The distance between the points is the length of the rope at rest.
+    pub fn new(start: m::Vec2, end: m::Vec2) -> Self {
+        Self {
+            start,
+            end,
+            segments: 10,
+            mass: 1.0,
+            compliance: 0.0,
+            bending_compliance: None,
+            damping: 0.1,
+            collider: None,
+            break_force: None,
+            start_attachment: None,
+            end_attachment: None,
+        }
+    }
+
+    /// Set the number of segments, i.e. constraints between particles. 10 by default.
+    ///
+    /// More segments make the rope smoother but cost more to simulate.
+    pub fn with_segments(mut self, segments: usize) -> Self {
+        self.segments = segments.max(1);
+        self
+    }
+
+    /// Set the total mass of the rope, split evenly between its particles. 1 by default.
+    pub fn with_mass(mut self, mass: f64) -> Self {
+        self.mass = mass;
+        self
+    }
+
+    /// Let the segments stretch like springs.
+    /// Zero by default, making the rope as stiff as the solver allows.
+    ///
+    /// Units of compliance are m/N.
+    pub fn with_compliance(mut self, compliance: f64) -> Self {
+        self.compliance = compliance;
+        self
+    }
+
+    /// Make the rope resist bending with the given compliance.
+    /// Smaller values give a stiffer rope.
+    ///
+    /// Bending is resisted by stopping every other particle from getting closer together
+    /// than they are at rest, which prevents sharp folds
+    /// but still lets ropes with many segments curve gently.
+    /// By default the rope bends freely.
+    pub fn with_bending_compliance(mut self, compliance: f64) -> Self {
+        self.bending_compliance = Some(compliance);
+        self
+    }
+
+    /// Set the linear damping of the segments. 0.1 by default.
+    pub fn with_damping(mut self, damping: f64) -> Self {
+        self.damping = damping;
+        self
+    }
+
+    /// Give each particle a circle collider with the given radius so the rope collides with things.
+    ///
+    /// Particles of the rope don't collide with their neighbors,
+    /// but they can collide with the rest of the rope.
+    /// By default the rope has no colliders and passes through everything.
+    pub fn with_collision(mut self, radius: f64) -> Self {
+        self.collider = Some(Collider::new_circle(radius));
+        self
+    }
+
+    /// Make the rope snap if any segment or attachment has to hold more than the given force.
+    ///
+    /// The broken constraint is removed, and its owner particle receives a
+    /// [`ConstraintBroken`][crate::event::Event::ConstraintBroken] event
+    /// if it has an event sink. By default the rope never breaks.
+    /// A taut rope may snap at several segments on the same frame unless
+    /// [`Physics::one_break_per_island`][crate::physics::Physics::one_break_per_island] is set.
+    ///
+    /// Units of force are N.
+    pub fn with_break_force(mut self, max_force: f64) -> Self {
+        self.break_force = Some(max_force);
+        self
+    }
+
+    /// Attach the start of the rope to a point on a body, relative to the body's pose.
+    pub fn with_start_attached_to(mut self, body: graph::Node<Body>, point: m::Vec2) -> Self {
+        self.start_attachment = Some(RopeAttachment::Body { body, point });
+        self
+    }
+
+    /// Pin the start of the rope to the point it starts from.
+    pub fn with_start_pinned(mut self) -> Self {
+        self.start_attachment = Some(RopeAttachment::World(self.start));
+        self
+    }
+
+    /// Attach the end of the rope to a point on a body, relative to the body's pose.
+    pub fn with_end_attached_to(mut self, body: graph::Node<Body>, point: m::Vec2) -> Self {
+        self.end_attachment = Some(RopeAttachment::Body { body, point });
+        self
+    }
+
+    /// Pin the end of the rope to the point it ends at.
+    pub fn with_end_pinned(mut self) -> Self {
+        self.end_attachment = Some(RopeAttachment::World(self.end));
+        self
+    }
+
+    /// Create the particles and constraints of the rope.
+    ///
+    /// Every particle gets a pose and a body, plus a collider if collision is enabled.
+    pub fn build(
+        self,
+        physics: &mut Physics,
+        graph: &mut graph::Graph,
+        l_pose: &mut graph::Layer<m::Pose>,
+        l_body: &mut graph::Layer<Body>,
+        l_collider: &mut graph::Layer<Collider>,
+    ) -> Rope {
+        let particle_count = self.segments + 1;
+        let particle_mass = self.mass / particle_count as f64;
+        let segment_length = (self.end - self.start).mag() / self.segments as f64;
+
+        let break_force = self.break_force;
+        let with_break_force = |builder: ConstraintBuilder| match break_force {
+            Some(f) => builder.with_break_force(f),
+            None => builder,
+        };
+
+        let particles: Vec<graph::Node<Body>> = (0..particle_count)
+            .map(|i| {
+                let t = i as f64 / self.segments as f64;
+                let position = self.start + t * (self.end - self.start);
+                let pose = l_pose.insert(m::Pose::new(position, m::Rotor2::identity()), graph);
+                let body = l_body.insert(Body::new_particle(particle_mass), graph);
+                graph.connect(&pose, &body);
+                if let Some(coll) = self.collider {
+                    let coll = l_collider.insert(coll, graph);
+                    graph.connect(&pose, &coll);
+                    graph.connect(&body, &coll);
+                }
+                graph::NodeRef::as_node(&body, graph)
+            })
+            .collect();
+
+        let mut constraints = Vec::new();
+        for pair in particles.windows(2) {
+            constraints.push(
+                physics.add_constraint(
+                    with_break_force(ConstraintBuilder::new(pair[1]))
+                        .with_target(pair[0])
+                        .with_compliance(self.compliance)
+                        .with_linear_damping(self.damping)
+                        .disable_collision()
+                        .build_distance(segment_length),
+                ),
+            );
+        }
+        if let Some(bending_compliance) = self.bending_compliance {
+            for triple in particles.windows(3) {
+                constraints.push(
+                    physics.add_constraint(
+                        ConstraintBuilder::new(triple[2])
+                            .with_target(triple[0])
+                            .with_compliance(bending_compliance)
+                            .with_limit(ConstraintLimit::Gt)
+                            .disable_collision()
+                            .build_distance(2.0 * segment_length),
+                    ),
+                );
+            }
+        }
+        for (attachment, particle) in &[
+            (self.start_attachment, particles[0]),
+            (self.end_attachment, particles[particle_count - 1]),
+        ] {
+            let builder = match attachment {
+                Some(RopeAttachment::Body { body, point }) => ConstraintBuilder::new(*particle)
+                    .with_target(*body)
+                    .with_target_origin(*point)
+                    .disable_collision(),
+                Some(RopeAttachment::World(point)) => {
+                    ConstraintBuilder::new(*particle).with_target_origin(*point)
+                }
+                None => continue,
+            };
+            constraints.push(physics.add_constraint(with_break_force(builder).build_attachment()));
+        }
+
+        Rope {
+            particles,
+            constraints,
+        }
+    }
+}
+
+/// A rope created with [`RopeBuilder`][self::RopeBuilder].
+///
+/// Deleting a particle's body breaks the rope at that point,
+/// since the constraints attached to it disappear with it.
+/// Ropes built [`with_break_force`][RopeBuilder::with_break_force]
+/// can also snap on their own.
+#[derive(Clone, Debug)]
+pub struct Rope {
+    /// The bodies of the particles in order from the start to the end.
+    pub particles: Vec<graph::Node<Body>>,
+    /// Handles to the constraints holding the rope together and attaching it to things.
+    pub constraints: Vec<ConstraintHandle>,
+}
+
+impl Rope {
+    /// Get the positions of the rope's particles in world space,
+    /// skipping any that have been deleted.
+    pub fn points(
+        &self,
+        graph: &graph::Graph,
+        l_pose: &graph::Layer<m::Pose>,
+        l_body: &graph::Layer<Body>,
+    ) -> Vec<m::Vec2> {
+        self.particles
+            .iter()
+            .filter_map(|p| p.check(graph).map(|p| l_body.get(p)))
+            .filter_map(|body| graph.get_neighbor(&body, l_pose))
+            .map(|pose| pose.translation)
+            .collect()
+    }
+
+    /// Get the positions of the rope's particles in world space,
+    /// split into separate pieces wherever a segment has broken or a particle has been deleted.
+    pub fn pieces(
+        &self,
+        physics: &Physics,
+        graph: &graph::Graph,
+        l_pose: &graph::Layer<m::Pose>,
+        l_body: &graph::Layer<Body>,
+    ) -> Vec<Vec<m::Vec2>> {
+        let mut pieces = Vec::new();
+        let mut piece = Vec::new();
+        for (i, particle) in self.particles.iter().enumerate() {
+            let position = particle
+                .check(graph)
+                .map(|p| l_body.get(p))
+                .and_then(|body| graph.get_neighbor(&body, l_pose))
+                .map(|pose| pose.translation);
+            if let Some(position) = position {
+                piece.push(position);
+            }
+            // segment constraints come first in the list, one for each pair of particles
+            let segment_intact = position.is_some()
+                && i + 1 < self.particles.len()
+                && physics.get_constraint(self.constraints[i]).is_some();
+            if !segment_intact && !piece.is_empty() {
+                pieces.push(std::mem::take(&mut piece));
+            }
+        }
+        pieces
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::tests::{TestWorld, GRAVITY};
+
+    fn build(world: &mut TestWorld, builder: RopeBuilder) -> Rope {
+        builder.build(
+            &mut world.physics,
+            &mut world.graph,
+            &mut world.l_pose,
+            &mut world.l_body,
+            &mut world.l_collider,
+        )
+    }
+
+    fn points(world: &TestWorld, rope: &Rope) -> Vec<m::Vec2> {
+        rope.points(&world.graph, &world.l_pose, &world.l_body)
+    }
+
+    #[test]
+    fn weight_swings_down_on_pinned_rope() {
+        let mut world = TestWorld::new();
+        let coll = Collider::new_square(0.5);
+        let weight = world.add_body(coll, Body::new_dynamic(&coll, 4.0), m::Vec2::new(4.0, 0.0));
+        let rope = build(
+            &mut world,
+            RopeBuilder::new(m::Vec2::zero(), m::Vec2::new(4.0, 0.0))
+                .with_segments(8)
+                .with_start_pinned()
+                .with_end_attached_to(weight, m::Vec2::zero()),
+        );
+        assert_eq!(rope.particles.len(), 9);
+
+        for _ in 0..600 {
+            world.tick(&GRAVITY);
+        }
+        let points = points(&world, &rope);
+        assert!(points[0].mag() < 0.01, "{:?}", points[0]);
+        let length: f64 = points.windows(2).map(|w| (w[1] - w[0]).mag()).sum();
+        assert!(length > 3.9 && length < 4.2, "{}", length);
+        let end = world.pose(weight).translation;
+        assert!((points[8] - end).mag() < 0.01, "{:?} {:?}", points[8], end);
+        assert!(end.y < -3.0 && end.x.abs() < 1.5, "{:?}", end);
+    }
+
+    #[test]
+    fn bending_compliance_stiffens_rope() {
+        let mut world = TestWorld::new();
+        let stiff = build(
+            &mut world,
+            RopeBuilder::new(m::Vec2::zero(), m::Vec2::new(2.0, 0.0))
+                .with_segments(2)
+                .with_bending_compliance(0.0)
+                .with_start_pinned(),
+        );
+        let limp = build(
+            &mut world,
+            RopeBuilder::new(m::Vec2::new(0.0, 5.0), m::Vec2::new(2.0, 5.0))
+                .with_segments(2)
+                .with_start_pinned(),
+        );
+        // largest distance of a point from the line through the ends
+        let bend = |points: &[m::Vec2]| {
+            let dir = (points[2] - points[0]).normalized();
+            (points[1] - points[0]).wedge(dir).xy.abs()
+        };
+
+        let (mut stiff_bend, mut limp_bend) = (0.0f64, 0.0f64);
+        for _ in 0..120 {
+            world.tick(&GRAVITY);
+            stiff_bend = stiff_bend.max(bend(&points(&world, &stiff)));
+            limp_bend = limp_bend.max(bend(&points(&world, &limp)));
+        }
+        assert!(stiff_bend < 0.1, "{}", stiff_bend);
+        assert!(limp_bend > 0.2, "{}", limp_bend);
+    }
+
+    #[test]
+    fn overloaded_rope_breaks() {
+        let mut world = TestWorld::new();
+        let coll = Collider::new_square(0.5);
+        let weight = world.add_body(
+            coll,
+            Body::new_dynamic(&coll, 40.0),
+            m::Vec2::new(0.0, -2.0),
+        );
+        let rope = build(
+            &mut world,
+            RopeBuilder::new(m::Vec2::zero(), m::Vec2::new(0.0, -2.0))
+                .with_segments(4)
+                .with_break_force(50.0)
+                .with_start_pinned()
+                .with_end_attached_to(weight, m::Vec2::zero()),
+        );
+
+        for _ in 0..60 {
+            world.tick(&GRAVITY);
+        }
+        assert!(rope
+            .constraints
+            .iter()
+            .any(|c| world.physics.get_constraint(*c).is_none()));
+        assert!(world.pose(weight).translation.y < -3.0);
+
+        // without the break force it holds
+        let mut world = TestWorld::new();
+        let weight = world.add_body(
+            coll,
+            Body::new_dynamic(&coll, 40.0),
+            m::Vec2::new(0.0, -2.0),
+        );
+        let rope = build(
+            &mut world,
+            RopeBuilder::new(m::Vec2::zero(), m::Vec2::new(0.0, -2.0))
+                .with_segments(4)
+                .with_start_pinned()
+                .with_end_attached_to(weight, m::Vec2::zero()),
+        );
+        for _ in 0..60 {
+            world.tick(&GRAVITY);
+        }
+        let pieces = rope.pieces(&world.physics, &world.graph, &world.l_pose, &world.l_body);
+        assert_eq!(pieces.len(), 1);
+        assert!(world.pose(weight).translation.y > -2.2);
+    }
+}
@@ -0,0 +1,68 @@
+//! Putting bodies to sleep when they come to rest, and simulation islands used to decide when.
+
+/// Parameters controlling when bodies fall asleep.
+///
+/// A body falls asleep once it has been moving slower than the thresholds for `time_to_sleep`
+/// seconds, but only if every body in its island (the group of bodies connected to it via
+/// contacts and constraints) has done the same. Sleeping bodies are skipped in the simulation
+/// until something wakes them up.
+#[derive(Clone, Copy, Debug)]
+pub struct SleepSettings {
+    pub enabled: bool,
+    /// Linear speed in metres per second below which a body is considered still.
+    pub linear_threshold: f64,
+    /// Angular speed in radians per second below which a body is considered still.
+    pub angular_threshold: f64,
+    /// Time in seconds a body needs to stay still before it can fall asleep.
+    pub time_to_sleep: f64,
+}
+
+impl Default for SleepSettings {
+    fn default() -> Self {
+        SleepSettings {
+            enabled: true,
+            linear_threshold: 0.05,
+            angular_threshold: 0.05,
+            time_to_sleep: 0.5,
+        }
+    }
+}
+
+/// Disjoint sets of bodies, indexed the same way as bodies in the physics tick.
+pub(crate) struct Islands {
+    parents: Vec<usize>,
+}
+
+impl Islands {
+    /// Create islands with every body in its own island.
+    pub fn new(body_count: usize) -> Self {
+        Islands {
+            parents: (0..body_count).collect(),
+        }
+    }
+
+    /// Get an index identifying the island the given body is in.
+    pub fn root(&mut self, body: usize) -> usize {
+        let mut root = body;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+        // compress the path so the next lookup is faster
+        let mut curr = body;
+        while self.parents[curr] != root {
+            let next = self.parents[curr];
+            self.parents[curr] = root;
+            curr = next;
+        }
+        root
+    }
+
+    /// Merge the islands containing the two given bodies.
+    pub fn join(&mut self, body1: usize, body2: usize) {
+        let root1 = self.root(body1);
+        let root2 = self.root(body2);
+        if root1 != root2 {
+            self.parents[root2] = root1;
+        }
+    }
+}
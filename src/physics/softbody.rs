@@ -0,0 +1,359 @@
+//! Deformable bodies made of particles held together by constraints.
+
+use super::{AreaConstraintHandle, Body, Collider, ConstraintBuilder, ConstraintHandle, Physics};
+use crate::{graph, math as m};
+
+/// A constraint that keeps the area enclosed by a loop of bodies constant,
+/// letting it squish but not shrink or grow.
+///
+/// Used by [`SoftBody`][self::SoftBody] to keep its outline from collapsing,
+/// but also works on its own, e.g. to make a balloon out of a ring of particles.
+/// The constraint disappears if any of its bodies are destroyed.
+#[derive(Clone, Debug)]
+pub struct AreaConstraint {
+    /// The bodies on the loop in counterclockwise order.
+    pub bodies: Vec<graph::Node<Body>>,
+    /// The area the loop tries to keep.
+    pub rest_area: f64,
+    /// Inverse of stiffness, or how easily the area changes.
+    pub compliance: f64,
+}
+
+/// Signed area of a polygon, positive if the points are in counterclockwise order.
+pub(crate) fn polygon_area(points: &[m::Vec2]) -> f64 {
+    (0..points.len())
+        .map(|i| points[i].wedge(points[(i + 1) % points.len()]).xy)
+        .sum::<f64>()
+        / 2.0
+}
+
+/// A builder for a [`SoftBody`][self::SoftBody].
+///
+/// A soft body is a ring of particles around its outline, held together by distance
+/// constraints along its edges and an [`AreaConstraint`][self::AreaConstraint]
+/// that keeps it from collapsing. Lattice soft bodies also fill the inside with
+/// a grid of particles braced with diagonal constraints, which makes them hold their shape better.
+/// The outline particles have circle colliders so the soft body collides with other things.
+#[derive(Clone, Debug)]
+pub struct SoftBodyBuilder {
+    points: Vec<m::Vec2>,
+    // index pairs of particles connected by distance constraints
+    edges: Vec<[usize; 2]>,
+    // indices of the particles on the outline in counterclockwise order
+    outline: Vec<usize>,
+    mass: f64,
+    edge_compliance: f64,
+    area_compliance: f64,
+    damping: f64,
+    particle_radius: f64,
+}
+
+impl SoftBodyBuilder {
+    /// Start building a soft body with the given outline.
+    /// The points should be in counterclockwise order.
+    pub fn new_polygon(points: &[m::Vec2]) -> Self {
+        let outline: Vec<usize> = (0..points.len()).collect();
+        let edges = (0..points.len())
+            .map(|i| [i, (i + 1) % points.len()])
+            .collect();
+        let spacing = (0..points.len())
+            .map(|i| (points[(i + 1) % points.len()] - points[i]).mag())
+            .fold(f64::INFINITY, f64::min);
+        Self {
+            points: points.to_vec(),
+            edges,
+            outline,
+            mass: 1.0,
+            edge_compliance: 0.0,
+            area_compliance: 0.0,
+            damping: 0.1,
+            particle_radius: spacing / 2.0,
+        }
+    }
+
+    /// Start building a round soft body with the given number of particles on its outline.
+    pub fn new_ring(center: m::Vec2, radius: f64, particles: usize) -> Self {
+        let particles = particles.max(3);
+        let points: Vec<m::Vec2> = (0..particles)
+            .map(|i| {
+                let angle = 2.0 * std::f64::consts::PI * i as f64 / particles as f64;
+                center + radius * m::Vec2::new(angle.cos(), angle.sin())
+            })
+            .collect();
+        Self::new_polygon(&points)
+    }
+
+    /// Start building a rectangular soft body filled with a grid of particles,
+    /// `columns` wide and `rows` high.
+    pub fn new_lattice(
+        center: m::Vec2,
+        width: f64,
+        height: f64,
+        columns: usize,
+        rows: usize,
+    ) -> Self {
+        let (columns, rows) = (columns.max(2), rows.max(2));
+        let corner = center - m::Vec2::new(width, height) / 2.0;
+        let step = m::Vec2::new(width / (columns - 1) as f64, height / (rows - 1) as f64);
+        let idx = |col: usize, row: usize| row * columns + col;
+
+        let points = (0..rows)
+            .flat_map(|row| {
+                (0..columns)
+                    .map(move |col| corner + m::Vec2::new(col as f64 * step.x, row as f64 * step.y))
+            })
+            .collect();
+
+        let mut edges = Vec::new();
+        for row in 0..rows {
+            for col in 0..columns {
+                if col + 1 < columns {
+                    edges.push([idx(col, row), idx(col + 1, row)]);
+                }
+                if row + 1 < rows {
+                    edges.push([idx(col, row), idx(col, row + 1)]);
+                }
+                // diagonals to resist shearing
+                if col + 1 < columns && row + 1 < rows {
+                    edges.push([idx(col, row), idx(col + 1, row + 1)]);
+                    edges.push([idx(col + 1, row), idx(col, row + 1)]);
+                }
+            }
+        }
+
+        // counterclockwise around the edges of the grid starting from the bottom left
+        let outline = (0..columns)
+            .map(|col| idx(col, 0))
+            .chain((1..rows).map(|row| idx(columns - 1, row)))
+            .chain((0..columns - 1).rev().map(|col| idx(col, rows - 1)))
+            .chain((1..rows - 1).rev().map(|row| idx(0, row)))
+            .collect();
+
+        Self {
+            points,
+            edges,
+            outline,
+            mass: 1.0,
+            edge_compliance: 0.0,
+            area_compliance: 0.0,
+            damping: 0.1,
+            particle_radius: step.x.min(step.y) / 2.0,
+        }
+    }
+
+    /// Set the total mass of the soft body, split evenly between its particles. 1 by default.
+    pub fn with_mass(mut self, mass: f64) -> Self {
+        self.mass = mass;
+        self
+    }
+
+    /// Set the compliance of the distance constraints between particles.
+    /// Higher values make the soft body stretchier. Zero by default.
+    ///
+    /// Units of compliance are m/N.
+    pub fn with_edge_compliance(mut self, compliance: f64) -> Self {
+        self.edge_compliance = compliance;
+        self
+    }
+
+    /// Set the compliance of the area constraint.
+    /// Higher values let the soft body squash down more under pressure. Zero by default.
+    pub fn with_area_compliance(mut self, compliance: f64) -> Self {
+        self.area_compliance = compliance;
+        self
+    }
+
+    /// Set the linear damping of the distance constraints. 0.1 by default.
+    pub fn with_damping(mut self, damping: f64) -> Self {
+        self.damping = damping;
+        self
+    }
+
+    /// Set the radius of the colliders on the outline particles.
+    /// Half of the shortest distance between particles by default.
+    pub fn with_particle_radius(mut self, radius: f64) -> Self {
+        self.particle_radius = radius;
+        self
+    }
+
+    /// Create the particles and constraints of the soft body.
+    ///
+    /// Every particle gets a pose and a body, and particles on the outline also get a collider.
+    pub fn build(
+        self,
+        physics: &mut Physics,
+        graph: &mut graph::Graph,
+        l_pose: &mut graph::Layer<m::Pose>,
+        l_body: &mut graph::Layer<Body>,
+        l_collider: &mut graph::Layer<Collider>,
+    ) -> SoftBody {
+        let particle_mass = self.mass / self.points.len() as f64;
+        let mut on_outline = vec![false; self.points.len()];
+        for &i in &self.outline {
+            on_outline[i] = true;
+        }
+
+        let particles: Vec<graph::Node<Body>> = self
+            .points
+            .iter()
+            .zip(&on_outline)
+            .map(|(position, on_outline)| {
+                let pose = l_pose.insert(m::Pose::new(*position, m::Rotor2::identity()), graph);
+                let body = l_body.insert(Body::new_particle(particle_mass), graph);
+                graph.connect(&pose, &body);
+                if *on_outline {
+                    let coll = l_collider.insert(Collider::new_circle(self.particle_radius), graph);
+                    graph.connect(&pose, &coll);
+                    graph.connect(&body, &coll);
+                }
+                graph::NodeRef::as_node(&body, graph)
+            })
+            .collect();
+
+        let constraints = self
+            .edges
+            .iter()
+            .map(|&[a, b]| {
+                physics.add_constraint(
+                    ConstraintBuilder::new(particles[b])
+                        .with_target(particles[a])
+                        .with_compliance(self.edge_compliance)
+                        .with_linear_damping(self.damping)
+                        .disable_collision()
+                        .build_distance((self.points[b] - self.points[a]).mag()),
+                )
+            })
+            .collect();
+
+        let outline_points: Vec<m::Vec2> = self.outline.iter().map(|&i| self.points[i]).collect();
+        let area_constraint = physics.add_area_constraint(AreaConstraint {
+            bodies: self.outline.iter().map(|&i| particles[i]).collect(),
+            rest_area: polygon_area(&outline_points),
+            compliance: self.area_compliance,
+        });
+
+        SoftBody {
+            particles,
+            outline: self.outline,
+            constraints,
+            area_constraint,
+        }
+    }
+}
+
+/// A soft body created with [`SoftBodyBuilder`][self::SoftBodyBuilder].
+#[derive(Clone, Debug)]
+pub struct SoftBody {
+    /// The bodies of all the particles.
+    pub particles: Vec<graph::Node<Body>>,
+    /// Indices into `particles` of the ones on the outline, in counterclockwise order.
+    pub outline: Vec<usize>,
+    /// Handles to the distance constraints between particles.
+    pub constraints: Vec<ConstraintHandle>,
+    /// Handle to the constraint keeping the area inside the outline constant.
+    pub area_constraint: AreaConstraintHandle,
+}
+
+impl SoftBody {
+    /// Get the positions of the particles on the outline in world space
+    /// in counterclockwise order, skipping any that have been deleted.
+    pub fn outline_points(
+        &self,
+        graph: &graph::Graph,
+        l_pose: &graph::Layer<m::Pose>,
+        l_body: &graph::Layer<Body>,
+    ) -> Vec<m::Vec2> {
+        self.outline
+            .iter()
+            .filter_map(|&i| self.particles[i].check(graph).map(|p| l_body.get(p)))
+            .filter_map(|body| graph.get_neighbor(&body, l_pose))
+            .map(|pose| pose.translation)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::tests::{TestWorld, GRAVITY};
+
+    fn build(world: &mut TestWorld, builder: SoftBodyBuilder) -> SoftBody {
+        builder.build(
+            &mut world.physics,
+            &mut world.graph,
+            &mut world.l_pose,
+            &mut world.l_body,
+            &mut world.l_collider,
+        )
+    }
+
+    /// Area of the outline with the shoelace formula.
+    fn area(world: &TestWorld, body: &SoftBody) -> f64 {
+        let points = body.outline_points(&world.graph, &world.l_pose, &world.l_body);
+        (0..points.len())
+            .map(|i| points[i].wedge(points[(i + 1) % points.len()]).xy)
+            .sum::<f64>()
+            / 2.0
+    }
+
+    #[test]
+    fn soft_bodies_land_and_keep_their_area() {
+        let mut world = TestWorld::new();
+        // floor with its top at y = -0.5
+        world.add_static(Collider::new_rect(40.0, 1.0), m::Vec2::new(0.0, -1.0));
+        let ring_builder =
+            SoftBodyBuilder::new_ring(m::Vec2::new(-3.0, 3.0), 1.0, 16).with_edge_compliance(0.001);
+        let lattice_builder =
+            SoftBodyBuilder::new_lattice(m::Vec2::new(3.0, 3.0), 2.0, 1.0, 5, 3).with_mass(2.0);
+        let radii = [
+            ring_builder.particle_radius,
+            lattice_builder.particle_radius,
+        ];
+        let ring = build(&mut world, ring_builder);
+        let lattice = build(&mut world, lattice_builder);
+        let squishy = build(
+            &mut world,
+            SoftBodyBuilder::new_ring(m::Vec2::new(-8.0, 3.0), 1.0, 16)
+                .with_edge_compliance(0.001)
+                .with_area_compliance(0.1),
+        );
+        assert_eq!(lattice.outline.len(), 12);
+        let start_areas: Vec<f64> = [&ring, &lattice, &squishy]
+            .iter()
+            .map(|body| area(&world, body))
+            .collect();
+        assert!((start_areas[1] - 2.0).abs() < 1e-9, "{}", start_areas[1]);
+
+        for _ in 0..300 {
+            world.tick(&GRAVITY);
+        }
+        let area_change = |i: usize, body: &SoftBody| area(&world, body) / start_areas[i] - 1.0;
+        assert!(
+            area_change(0, &ring).abs() < 0.1,
+            "{}",
+            area_change(0, &ring)
+        );
+        assert!(
+            area_change(1, &lattice).abs() < 0.1,
+            "{}",
+            area_change(1, &lattice)
+        );
+        assert!(
+            area_change(2, &squishy) < area_change(0, &ring),
+            "{} {}",
+            area_change(2, &squishy),
+            area_change(0, &ring)
+        );
+
+        // resting on the floor on the particles' colliders
+        for (body, radius) in [&ring, &lattice].iter().zip(&radii) {
+            let lowest = body
+                .outline_points(&world.graph, &world.l_pose, &world.l_body)
+                .iter()
+                .map(|p| p.y)
+                .fold(f64::INFINITY, f64::min);
+            let expected = -0.5 + radius;
+            assert!((lowest - expected).abs() < 0.02, "{} {}", lowest, expected);
+        }
+    }
+}